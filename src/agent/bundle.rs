@@ -0,0 +1,123 @@
+//! An agent's files as authored, for tools that mirror agents elsewhere.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::spec::{AgentSpec, PromptSource};
+use super::store::AgentStore;
+
+/// One file of an agent bundle, with its path as written in `agent.yaml`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    pub path: String,
+    pub content: String,
+}
+
+impl AgentStore {
+    /// The agent's `agent.yaml` as it is on disk, before interpolation.
+    pub fn read_manifest(&self, agent: &AgentSpec) -> io::Result<String> {
+        fs::read_to_string(self.source_dir(agent)?.join("agent.yaml"))
+    }
+
+    /// `agent.yaml` followed by the prompt and bootstrap files it references from its
+    /// own directory. Files that are missing, inherited through `extends` or outside the
+    /// agent's directory are left out.
+    pub fn read_bundle(&self, agent: &AgentSpec) -> io::Result<Vec<SourceFile>> {
+        let dir = self.source_dir(agent)?;
+        let mut files = vec![SourceFile {
+            path: "agent.yaml".to_string(),
+            content: fs::read_to_string(dir.join("agent.yaml"))?,
+        }];
+
+        let prompts = [&agent.system_prompt, &agent.instructions]
+            .into_iter()
+            .flatten()
+            .filter_map(|prompt| match &prompt.source {
+                PromptSource::File(path) => Some(path),
+                PromptSource::Inline => None,
+            });
+        let bootstrap = agent.bootstrap.iter().map(|b| &b.path);
+        let root = dir.canonicalize()?;
+        let mut seen = HashSet::new();
+        for path in prompts.chain(bootstrap) {
+            if Path::new(path).is_absolute() {
+                continue;
+            }
+            let Ok(full) = dir.join(path).canonicalize() else {
+                continue;
+            };
+            if !full.starts_with(&root) || !seen.insert(full.clone()) {
+                continue;
+            }
+            if let Ok(content) = fs::read_to_string(&full) {
+                files.push(SourceFile {
+                    path: path.clone(),
+                    content,
+                });
+            }
+        }
+        Ok(files)
+    }
+
+    fn source_dir(&self, agent: &AgentSpec) -> io::Result<&Path> {
+        self.agent_dir(&agent.namespace, &agent.metadata.name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "agent is no longer loaded"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::LoadOptions;
+    use tempfile::TempDir;
+
+    #[test]
+    fn bundle_contains_own_referenced_files() {
+        let tmp = TempDir::new().unwrap();
+        let base = tmp.path().join("base");
+        let child = tmp.path().join("child");
+        fs::create_dir_all(&base).unwrap();
+        fs::create_dir_all(&child).unwrap();
+        fs::write(
+            base.join("agent.yaml"),
+            "apiVersion: agnx/v1
+kind: Agent
+metadata:
+  name: base
+spec:
+  model: openrouter/m
+  system_prompt:
+    file: ./BASE.md
+",
+        )
+        .unwrap();
+        fs::write(base.join("BASE.md"), "Inherited.").unwrap();
+        let manifest = "# authored by hand
+extends: base
+metadata:
+  name: child
+spec:
+  instructions:
+    file: ./INSTRUCTIONS.md
+  bootstrap:
+    - path: ./SOUL.md
+    - path: ./INSTRUCTIONS.md
+    - path: ../base/BASE.md
+    - path: ./MISSING.md
+";
+        fs::write(child.join("agent.yaml"), manifest).unwrap();
+        fs::write(child.join("INSTRUCTIONS.md"), "Do things.").unwrap();
+        fs::write(child.join("SOUL.md"), "Be calm.").unwrap();
+
+        let store = AgentStore::scan(tmp.path(), &LoadOptions::default()).store;
+        let agent = store.get("child").unwrap();
+        assert_eq!(store.read_manifest(&agent).unwrap(), manifest);
+
+        let bundle = store.read_bundle(&agent).unwrap();
+        let paths: Vec<&str> = bundle.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["agent.yaml", "./INSTRUCTIONS.md", "./SOUL.md"]);
+        assert_eq!(bundle[2].content, "Be calm.");
+    }
+}
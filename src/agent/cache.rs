@@ -0,0 +1,105 @@
+//! LRU cache of fully loaded agents, used when agents are loaded lazily.
+
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use lru::LruCache;
+
+use super::spec::AgentSpec;
+
+/// Loaded agents keyed by `namespace/name`, with hit/miss counters.
+///
+/// Shared by every snapshot of an [`AgentStore`](super::AgentStore); reloads invalidate
+/// the entries of the agents they touch.
+#[derive(Debug)]
+pub(super) struct AgentCache {
+    entries: Mutex<LruCache<String, Arc<AgentSpec>>>,
+    /// Agents that are indexed but failed to load in full, until they are reloaded.
+    failed: Mutex<HashSet<String>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// Counters and occupancy of the agent cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub capacity: usize,
+    /// Agents currently held in memory.
+    pub len: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl AgentCache {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            failed: Mutex::new(HashSet::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Look up an agent, counting a hit or a miss and marking it as recently used.
+    pub fn get(&self, key: &str) -> Option<Arc<AgentSpec>> {
+        let agent = self.lock().get(key).cloned();
+        let counter = if agent.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        agent
+    }
+
+    /// Add a freshly loaded agent, evicting the least recently used one if full.
+    pub fn insert(&self, key: String, agent: Arc<AgentSpec>) {
+        let evicted = self.lock().push(key.clone(), agent);
+        if evicted.is_some_and(|(old, _)| old != key) {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Remember that an agent failed to load, so it is hidden until it is reloaded.
+    pub fn fail(&self, key: String) {
+        self.failed_keys().insert(key);
+    }
+
+    pub fn has_failed(&self, key: &str) -> bool {
+        self.failed_keys().contains(key)
+    }
+
+    /// Drop entries and failures that are out of date.
+    pub fn invalidate<'a>(&self, keys: impl IntoIterator<Item = &'a String>) {
+        let mut entries = self.lock();
+        let mut failed = self.failed_keys();
+        for key in keys {
+            entries.pop(key);
+            failed.remove(key);
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.lock();
+        CacheStats {
+            capacity: entries.cap().get(),
+            len: entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<String, Arc<AgentSpec>>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn failed_keys(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.failed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,535 @@
+//! Agents deployed at runtime through the admin API.
+//!
+//! Deployed agents are written to `<dynamic_agents_dir>/<namespace>/<name>/agent.yaml`,
+//! so they are picked up again by the next scan after a restart.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use super::DEFAULT_NAMESPACE;
+use super::diagnostic::{Diagnostic, DiagnosticCode, Severity};
+use super::revision::Revision;
+use super::spec::{
+    AgentSpec, NAMESPACE_HINT, agent_name_hint, is_valid_agent_name, is_valid_namespace,
+};
+use super::store::{AgentRegistry, log_scan_warnings};
+
+/// What a deployment did to the stored definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeployStatus {
+    Created,
+    Updated,
+    /// The stored definition was already equivalent.
+    Unchanged,
+}
+
+impl DeployStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeployStatus::Created => "created",
+            DeployStatus::Updated => "updated",
+            DeployStatus::Unchanged => "unchanged",
+        }
+    }
+}
+
+/// The result of a successful deployment.
+#[derive(Debug)]
+pub struct Deployment {
+    pub status: DeployStatus,
+    pub namespace: String,
+    pub name: String,
+    /// Revision of the agent as now loaded.
+    pub revision: Revision,
+    /// Warnings from validating the definition.
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Why an agent could not be deployed or removed.
+#[derive(Debug)]
+pub enum DeployError {
+    /// No dynamic agents directory is configured.
+    Disabled,
+    /// The definition failed validation.
+    Invalid(Vec<Diagnostic>),
+    /// The agent is defined in the agents directory, which the admin API does not manage.
+    Static {
+        namespace: String,
+        name: String,
+    },
+    NotFound {
+        name: String,
+    },
+    /// `If-Match` did not list the current revision, or there is no current agent.
+    PreconditionFailed {
+        current: Option<String>,
+    },
+    Io(io::Error),
+}
+
+impl std::fmt::Display for DeployError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeployError::Disabled => write!(f, "no dynamic agents directory is configured"),
+            DeployError::Invalid(diagnostics) => {
+                let errors = diagnostics
+                    .iter()
+                    .filter(|d| d.severity == Severity::Error)
+                    .count();
+                write!(f, "agent definition has {errors} error(s)")
+            }
+            DeployError::Static { namespace, name } => write!(
+                f,
+                "agent '{name}' in namespace '{namespace}' is defined in the agents directory and cannot be managed through the admin API"
+            ),
+            DeployError::NotFound { name } => write!(f, "agent '{name}' not found"),
+            DeployError::PreconditionFailed {
+                current: Some(hash),
+            } => {
+                write!(f, "agent has changed; its current revision is {hash}")
+            }
+            DeployError::PreconditionFailed { current: None } => {
+                write!(f, "agent does not exist")
+            }
+            DeployError::Io(e) => write!(f, "failed to write agent: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DeployError {}
+
+impl From<io::Error> for DeployError {
+    fn from(e: io::Error) -> Self {
+        DeployError::Io(e)
+    }
+}
+
+impl AgentRegistry {
+    /// Validate an `agent.yaml` document, store it in the dynamic agents directory and
+    /// load it.
+    ///
+    /// With `if_match` (an `If-Match` header value) the agent must already exist at one
+    /// of the listed revisions, so concurrent deployers don't overwrite each other.
+    pub fn deploy(&self, text: &str, if_match: Option<&str>) -> Result<Deployment, DeployError> {
+        let guard = self.lock_updates();
+        let store = self.snapshot();
+        let dynamic_root = store.dynamic_root().ok_or(DeployError::Disabled)?;
+
+        let (namespace, name) = identity(text).map_err(DeployError::Invalid)?;
+        let dir = dynamic_root.join(&namespace).join(&name);
+        if store
+            .agent_dir(&namespace, &name)
+            .is_some_and(|existing| !existing.starts_with(dynamic_root))
+        {
+            return Err(DeployError::Static { namespace, name });
+        }
+        if let Some(if_match) = if_match {
+            check_revision(store.get_in(&namespace, &name).as_deref(), if_match)?;
+        }
+
+        let (spec, warnings) = AgentSpec::load_from_str(&dir, text, store.options(), &|parent| {
+            store.extends_lookup(&namespace, parent)
+        })
+        .map_err(|e| DeployError::Invalid(e.diagnostics()))?;
+
+        let manifest = dir.join("agent.yaml");
+        let status = match fs::read_to_string(&manifest) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => DeployStatus::Created,
+            Err(e) => return Err(e.into()),
+            Ok(existing) if same_document(&existing, text) => DeployStatus::Unchanged,
+            Ok(_) => DeployStatus::Updated,
+        };
+        if status != DeployStatus::Unchanged {
+            fs::create_dir_all(&dir)?;
+            // Write then rename, so a scan never sees a half-written file.
+            let partial = dir.join(".agent.yaml.partial");
+            fs::write(&partial, text)?;
+            fs::rename(&partial, &manifest)?;
+        }
+        if status != DeployStatus::Unchanged || store.get_in(&namespace, &name).is_none() {
+            log_scan_warnings(&self.reload_dirs_locked(&guard, [dir.as_path()]));
+        }
+
+        let revision = self
+            .snapshot()
+            .get_in(&namespace, &name)
+            .map_or(spec.revision, |agent| agent.revision.clone());
+        Ok(Deployment {
+            status,
+            namespace,
+            name,
+            revision,
+            warnings: warnings.iter().map(|w| w.to_diagnostic()).collect(),
+        })
+    }
+
+    /// Remove a deployed agent. Without a namespace, `name` is resolved like
+    /// [`AgentStore::get`](super::AgentStore::get) does.
+    pub fn undeploy(
+        &self,
+        namespace: Option<&str>,
+        name: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), DeployError> {
+        let guard = self.lock_updates();
+        let store = self.snapshot();
+        let dynamic_root = store.dynamic_root().ok_or(DeployError::Disabled)?;
+
+        let agent = match namespace {
+            Some(namespace) => store.get_in(namespace, name),
+            None => store.get(name),
+        };
+        let not_found = || DeployError::NotFound {
+            name: name.to_string(),
+        };
+        let agent = agent.ok_or_else(not_found)?;
+        if let Some(if_match) = if_match {
+            check_revision(Some(&agent), if_match)?;
+        }
+        let dir = store
+            .agent_dir(&agent.namespace, name)
+            .ok_or_else(not_found)?
+            .to_path_buf();
+        if !dir.starts_with(dynamic_root) {
+            return Err(DeployError::Static {
+                namespace: agent.namespace.clone(),
+                name: name.to_string(),
+            });
+        }
+
+        fs::remove_dir_all(&dir)?;
+        remove_empty_parents(&dir, dynamic_root);
+        log_scan_warnings(&self.reload_dirs_locked(&guard, [dir.as_path()]));
+        Ok(())
+    }
+}
+
+fn check_revision(current: Option<&AgentSpec>, if_match: &str) -> Result<(), DeployError> {
+    match current {
+        Some(agent) if agent.revision.matches(if_match) => Ok(()),
+        _ => Err(DeployError::PreconditionFailed {
+            current: current.map(|agent| agent.revision.hash.clone()),
+        }),
+    }
+}
+
+/// Namespace and name from a document, checked before they are used as directory names.
+fn identity(text: &str) -> Result<(String, String), Vec<Diagnostic>> {
+    let path = Path::new("agent.yaml");
+    let doc: Value = serde_saphyr::from_str(text).map_err(|e| {
+        vec![Diagnostic::error(
+            DiagnosticCode::YamlParse,
+            path,
+            e.without_snippet().to_string(),
+        )]
+    })?;
+    let field = |field: &str| {
+        doc.pointer(&format!("/metadata/{field}"))
+            .and_then(Value::as_str)
+    };
+
+    let name = field("name").unwrap_or_default();
+    if !is_valid_agent_name(name) {
+        return Err(vec![
+            Diagnostic::error(
+                DiagnosticCode::InvalidName,
+                path,
+                format!("invalid metadata.name '{name}'"),
+            )
+            .with_hint(if name.is_empty() {
+                "deployed agents must set a valid metadata.name".to_string()
+            } else {
+                agent_name_hint(name)
+            }),
+        ]);
+    }
+    let namespace = field("namespace").unwrap_or(DEFAULT_NAMESPACE);
+    if !is_valid_namespace(namespace) {
+        return Err(vec![
+            Diagnostic::error(
+                DiagnosticCode::InvalidNamespace,
+                path,
+                format!("invalid metadata.namespace '{namespace}'"),
+            )
+            .with_hint(NAMESPACE_HINT),
+        ]);
+    }
+    Ok((namespace.to_string(), name.to_string()))
+}
+
+/// Whether two documents have the same content, ignoring formatting and comments.
+fn same_document(a: &str, b: &str) -> bool {
+    let parse = |text: &str| serde_saphyr::from_str::<Value>(text).ok();
+    matches!((parse(a), parse(b)), (Some(a), Some(b)) if a == b)
+}
+
+/// Remove directories left empty between `dir` and `root`, such as a namespace whose
+/// last agent was removed.
+fn remove_empty_parents(dir: &Path, root: &Path) {
+    let mut current: Option<PathBuf> = dir.parent().map(Path::to_path_buf);
+    while let Some(parent) = current {
+        if parent == root || !parent.starts_with(root) || fs::remove_dir(&parent).is_err() {
+            break;
+        }
+        current = parent.parent().map(Path::to_path_buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{AgentStore, LoadOptions};
+    use tempfile::TempDir;
+
+    const SUPPORT: &str = "apiVersion: agnx/v1
+kind: Agent
+metadata:
+  name: support
+spec:
+  model: openrouter/some-model
+  system_prompt:
+    inline: Be helpful.
+";
+
+    fn registry(tmp: &TempDir) -> AgentRegistry {
+        let agents_dir = tmp.path().join("agents");
+        let static_dir = agents_dir.join("static-agent");
+        fs::create_dir_all(&static_dir).unwrap();
+        fs::write(
+            static_dir.join("agent.yaml"),
+            SUPPORT.replace("name: support", "name: static-agent"),
+        )
+        .unwrap();
+        let report = AgentStore::scan_all(
+            &agents_dir,
+            Some(&tmp.path().join("dynamic")),
+            &LoadOptions::default(),
+        );
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        AgentRegistry::new(report.store)
+    }
+
+    #[test]
+    fn deploy_creates_updates_and_survives_restart() {
+        let tmp = TempDir::new().unwrap();
+        let registry = registry(&tmp);
+
+        let created = registry.deploy(SUPPORT, None).unwrap();
+        assert_eq!(created.status, DeployStatus::Created);
+        assert_eq!(
+            (created.namespace.as_str(), created.name.as_str()),
+            ("default", "support")
+        );
+        let manifest = tmp.path().join("dynamic/default/support/agent.yaml");
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), SUPPORT);
+        assert!(registry.snapshot().get("support").is_some());
+
+        // The same document in another layout is unchanged.
+        let reformatted = format!(
+            "# redeployed\n{}",
+            SUPPORT.replace(
+                "  system_prompt:\n    inline: Be helpful.",
+                "  system_prompt: {inline: Be helpful.}"
+            )
+        );
+        assert_eq!(
+            registry.deploy(&reformatted, None).unwrap().status,
+            DeployStatus::Unchanged
+        );
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), SUPPORT);
+
+        let updated = registry
+            .deploy(&SUPPORT.replace("Be helpful.", "Be brief."), None)
+            .unwrap();
+        assert_eq!(updated.status, DeployStatus::Updated);
+        let prompt = |registry: &AgentRegistry| {
+            registry
+                .snapshot()
+                .get("support")
+                .unwrap()
+                .system_prompt
+                .clone()
+                .unwrap()
+                .content
+        };
+        assert_eq!(prompt(&registry).as_deref(), Some("Be brief."));
+
+        let restarted = AgentRegistry::new(registry.snapshot().rescan().store);
+        assert_eq!(restarted.snapshot().len(), 2);
+        assert_eq!(prompt(&restarted).as_deref(), Some("Be brief."));
+    }
+
+    #[test]
+    fn deploy_rejects_invalid_and_static_agents() {
+        let tmp = TempDir::new().unwrap();
+        let registry = registry(&tmp);
+
+        let Err(DeployError::Invalid(diagnostics)) =
+            registry.deploy(&SUPPORT.replace("openrouter/some-model", "nope"), None)
+        else {
+            panic!("expected validation errors");
+        };
+        assert_eq!(diagnostics[0].code, DiagnosticCode::UnknownModel);
+        assert!(diagnostics[0].span.is_some());
+
+        let Err(DeployError::Invalid(diagnostics)) =
+            registry.deploy(&SUPPORT.replace("name: support", "name: ../escape"), None)
+        else {
+            panic!("expected an invalid name");
+        };
+        assert_eq!(diagnostics[0].code, DiagnosticCode::InvalidName);
+        assert!(!tmp.path().join("dynamic").exists());
+
+        assert!(matches!(
+            registry.deploy(
+                &SUPPORT.replace("name: support", "name: static-agent"),
+                None
+            ),
+            Err(DeployError::Static { .. })
+        ));
+    }
+
+    #[test]
+    fn deployed_agents_cannot_read_the_host() {
+        let tmp = TempDir::new().unwrap();
+        let registry = registry(&tmp);
+        let codes = |text: &str| match registry.deploy(text, None) {
+            Err(DeployError::Invalid(diagnostics)) => {
+                diagnostics.iter().map(|d| d.code).collect::<Vec<_>>()
+            }
+            other => panic!("expected validation errors, got {other:?}"),
+        };
+
+        // Environment variables are left as they are.
+        let deployed = SUPPORT.replace("Be helpful.", "Read ${PATH}");
+        registry.deploy(&deployed, None).unwrap();
+        let agent = registry.snapshot().get("support").unwrap();
+        assert_eq!(
+            agent.system_prompt.as_ref().unwrap().content.as_deref(),
+            Some("Read ${PATH}")
+        );
+
+        let escaping = [
+            "  system_prompt: /etc/passwd",
+            "  system_prompt:\n    file: ../../agents/static-agent/agent.yaml",
+            "  system_prompt:\n    inline: Hi\n  bootstrap:\n    - path: ../secrets.md",
+        ];
+        for prompt in escaping {
+            let text = SUPPORT.replace("  system_prompt:\n    inline: Be helpful.", prompt);
+            assert_eq!(codes(&text), [DiagnosticCode::PathOutsideAgent], "{prompt}");
+        }
+
+        // Only other deployed agents can be extended.
+        let child = "apiVersion: agnx/v1\nkind: Agent\nmetadata:\n  name: child\n";
+        assert_eq!(
+            codes(&format!("{child}extends: static-agent\n")),
+            [DiagnosticCode::InvalidExtends]
+        );
+        assert_eq!(
+            codes(&format!("{child}extends: ../../../agents/static-agent\n")),
+            [DiagnosticCode::InvalidExtends]
+        );
+        registry
+            .deploy(&format!("{child}extends: support\n"), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn concurrent_reloads_do_not_lose_deployments() {
+        let tmp = TempDir::new().unwrap();
+        let registry = registry(&tmp);
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let static_dir = tmp.path().join("agents/static-agent");
+                for _ in 0..200 {
+                    registry.reload_dirs([static_dir.as_path()]);
+                }
+            });
+            scope.spawn(|| {
+                for index in 0..20 {
+                    let name = format!("name: agent-{index}");
+                    registry
+                        .deploy(&SUPPORT.replace("name: support", &name), None)
+                        .unwrap();
+                }
+            });
+        });
+
+        let store = registry.snapshot();
+        assert_eq!(store.len(), 21);
+        for index in 0..20 {
+            assert!(
+                store.get(&format!("agent-{index}")).is_some(),
+                "agent-{index}"
+            );
+        }
+    }
+
+    #[test]
+    fn undeploy_removes_only_deployed_agents() {
+        let tmp = TempDir::new().unwrap();
+        let registry = registry(&tmp);
+        registry
+            .deploy(
+                &SUPPORT.replace("name: support", "name: support\n  namespace: team.a"),
+                None,
+            )
+            .unwrap();
+        assert!(registry.snapshot().get_in("team.a", "support").is_some());
+
+        assert!(matches!(
+            registry.undeploy(Some("default"), "support", None),
+            Err(DeployError::NotFound { .. })
+        ));
+        assert!(matches!(
+            registry.undeploy(None, "static-agent", None),
+            Err(DeployError::Static { .. })
+        ));
+
+        registry.undeploy(None, "support", None).unwrap();
+        assert!(registry.snapshot().get("support").is_none());
+        assert!(!tmp.path().join("dynamic/team.a").exists());
+        assert!(tmp.path().join("dynamic").exists());
+    }
+
+    #[test]
+    fn if_match_guards_against_concurrent_updates() {
+        let tmp = TempDir::new().unwrap();
+        let registry = registry(&tmp);
+
+        assert!(matches!(
+            registry.deploy(SUPPORT, Some("*")),
+            Err(DeployError::PreconditionFailed { current: None })
+        ));
+        let first = registry.deploy(SUPPORT, None).unwrap().revision;
+        assert_eq!(
+            registry.snapshot().get("support").unwrap().revision.hash,
+            first.hash
+        );
+
+        // Two deployers start from the same revision; only the first one wins.
+        let brief = SUPPORT.replace("Be helpful.", "Be brief.");
+        let second = registry.deploy(&brief, Some(&first.etag())).unwrap();
+        assert_eq!(second.status, DeployStatus::Updated);
+        assert_ne!(second.revision.hash, first.hash);
+        let Err(DeployError::PreconditionFailed { current }) = registry.deploy(
+            &SUPPORT.replace("Be helpful.", "Be kind."),
+            Some(&first.etag()),
+        ) else {
+            panic!("expected a failed precondition");
+        };
+        assert_eq!(current.as_deref(), Some(second.revision.hash.as_str()));
+
+        assert!(matches!(
+            registry.undeploy(None, "support", Some(&first.etag())),
+            Err(DeployError::PreconditionFailed { .. })
+        ));
+        registry
+            .undeploy(None, "support", Some(&second.revision.etag()))
+            .unwrap();
+    }
+}
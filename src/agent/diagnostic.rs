@@ -0,0 +1,245 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use super::source_map::SourceSpan;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Stable identifiers for agent diagnostics.
+///
+/// The string form (see [`DiagnosticCode::as_str`]) is part of the public contract used
+/// by tooling and CI, so existing codes must never be renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticCode {
+    /// `agent.yaml` could not be read.
+    ReadError,
+    /// An `${VAR}` reference could not be expanded.
+    EnvInterpolation,
+    /// `agent.yaml` is not valid YAML or does not match the AAF structure.
+    YamlParse,
+    UnsupportedApiVersion,
+    UnsupportedKind,
+    InvalidName,
+    /// `metadata.namespace`, or the namespace derived from the agent's directory, is invalid.
+    InvalidNamespace,
+    EmptyModelName,
+    /// A key that is not part of the AAF spec.
+    UnknownField,
+    /// A documented AAF section that is not implemented yet.
+    UnsupportedField,
+    /// A field written in a form that the document's apiVersion deprecates.
+    DeprecatedField,
+    /// A field written in a form that the document's apiVersion no longer accepts.
+    RemovedField,
+    /// `extends` does not name a loadable parent agent.
+    InvalidExtends,
+    /// `extends` chains lead back to the same agent.
+    InheritanceCycle,
+    /// `spec.model` names neither a configured alias nor a `provider/name` pair.
+    UnknownModel,
+    TemperatureOutOfRange,
+    /// A `spec.model` generation parameter is out of range or malformed.
+    InvalidModelParameter,
+    /// A `spec.model` generation parameter that the provider does not accept.
+    UnsupportedParameter,
+    /// A Markdown file referenced from `agent.yaml` could not be read.
+    MissingFile,
+    /// A file marked `required: true` could not be read.
+    MissingRequiredFile,
+    /// A deployed agent references a file outside its own directory.
+    PathOutsideAgent,
+    /// A skill directory without a readable `SKILL.md`, or with malformed frontmatter.
+    InvalidSkill,
+    /// Two skills of an agent have the same ID.
+    DuplicateSkill,
+    /// A `spec.tools` entry with an unknown type, a missing field or a missing file.
+    InvalidTool,
+    /// Two tools of an agent have the same name.
+    DuplicateTool,
+    /// Instructions reference a `${...}` variable that templates do not provide.
+    UnknownTemplateVariable,
+    AgentsDirMissing,
+    /// Two agents declare the same `metadata.name`.
+    DuplicateName,
+    /// A directory was checked but contained no agents.
+    NoAgents,
+    /// The Ollama server an agent uses does not have its model.
+    MissingModel,
+}
+
+impl DiagnosticCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCode::ReadError => "read-error",
+            DiagnosticCode::EnvInterpolation => "env-interpolation",
+            DiagnosticCode::YamlParse => "yaml-parse",
+            DiagnosticCode::UnsupportedApiVersion => "unsupported-api-version",
+            DiagnosticCode::UnsupportedKind => "unsupported-kind",
+            DiagnosticCode::InvalidName => "invalid-name",
+            DiagnosticCode::InvalidNamespace => "invalid-namespace",
+            DiagnosticCode::EmptyModelName => "empty-model-name",
+            DiagnosticCode::UnknownField => "unknown-field",
+            DiagnosticCode::UnsupportedField => "unsupported-field",
+            DiagnosticCode::DeprecatedField => "deprecated-field",
+            DiagnosticCode::RemovedField => "removed-field",
+            DiagnosticCode::InvalidExtends => "invalid-extends",
+            DiagnosticCode::InheritanceCycle => "inheritance-cycle",
+            DiagnosticCode::UnknownModel => "unknown-model",
+            DiagnosticCode::TemperatureOutOfRange => "temperature-out-of-range",
+            DiagnosticCode::InvalidModelParameter => "invalid-model-parameter",
+            DiagnosticCode::UnsupportedParameter => "unsupported-parameter",
+            DiagnosticCode::MissingFile => "missing-file",
+            DiagnosticCode::MissingRequiredFile => "missing-required-file",
+            DiagnosticCode::PathOutsideAgent => "path-outside-agent",
+            DiagnosticCode::InvalidSkill => "invalid-skill",
+            DiagnosticCode::DuplicateSkill => "duplicate-skill",
+            DiagnosticCode::InvalidTool => "invalid-tool",
+            DiagnosticCode::DuplicateTool => "duplicate-tool",
+            DiagnosticCode::UnknownTemplateVariable => "unknown-template-variable",
+            DiagnosticCode::AgentsDirMissing => "agents-dir-missing",
+            DiagnosticCode::DuplicateName => "duplicate-name",
+            DiagnosticCode::NoAgents => "no-agents",
+            DiagnosticCode::MissingModel => "missing-model",
+        }
+    }
+}
+
+impl std::fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for DiagnosticCode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// A single error or warning about an agent definition, pointing at its source.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub code: DiagnosticCode,
+    pub severity: Severity,
+    /// File the diagnostic refers to (usually `agent.yaml`).
+    pub path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: DiagnosticCode, path: &Path, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, code, path, message)
+    }
+
+    pub fn warning(code: DiagnosticCode, path: &Path, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, code, path, message)
+    }
+
+    fn new(
+        severity: Severity,
+        code: DiagnosticCode,
+        path: &Path,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            code,
+            severity,
+            path: path.to_path_buf(),
+            span: None,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn with_span(mut self, span: Option<SourceSpan>) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(span) = &self.span {
+            write!(f, ":{}:{}", span.line, span.column)?;
+        }
+        write!(f, ": {}[{}]: {}", self.severity, self.code, self.message)?;
+        if let Some(hint) = &self.hint {
+            write!(f, " (hint: {hint})")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_includes_location_code_and_hint() {
+        let d = Diagnostic::error(
+            DiagnosticCode::UnsupportedKind,
+            Path::new("agents/a/agent.yaml"),
+            "unsupported kind 'Tool'",
+        )
+        .with_span(Some(SourceSpan {
+            line: 2,
+            column: 7,
+            end_line: 2,
+            end_column: 11,
+        }))
+        .with_hint("use 'Agent'");
+
+        assert_eq!(
+            d.to_string(),
+            "agents/a/agent.yaml:2:7: error[unsupported-kind]: unsupported kind 'Tool' (hint: use 'Agent')"
+        );
+    }
+
+    #[test]
+    fn serializes_code_and_severity_as_strings() {
+        let d = Diagnostic::warning(
+            DiagnosticCode::MissingFile,
+            Path::new("agent.yaml"),
+            "missing",
+        );
+        let v = serde_json::to_value(&d).unwrap();
+        assert_eq!(v["code"], "missing-file");
+        assert_eq!(v["severity"], "warning");
+        assert!(v.get("span").is_none());
+        assert!(v.get("hint").is_none());
+    }
+}
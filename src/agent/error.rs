@@ -1,17 +1,44 @@
+use std::path::PathBuf;
+
+use super::diagnostic::{Diagnostic, DiagnosticCode};
+use super::provider::Provider;
+use super::source_map::SourceSpan;
+use super::template::TEMPLATE_VARIABLES;
+
 /// Error type for agent loading operations.
 #[derive(Debug)]
 pub enum AgentLoadError {
-    Io(std::io::Error),
-    Yaml(serde_saphyr::Error),
-    Validation(String),
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// The agent definition was read but is invalid. Always holds at least one
+    /// error-severity diagnostic.
+    Validation(Vec<Diagnostic>),
+}
+
+impl AgentLoadError {
+    /// Structured diagnostics describing this error.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            AgentLoadError::Io { path, error } => vec![Diagnostic::error(
+                DiagnosticCode::ReadError,
+                path,
+                format!("failed to read file: {error}"),
+            )],
+            AgentLoadError::Validation(diagnostics) => diagnostics.clone(),
+        }
+    }
 }
 
 impl std::fmt::Display for AgentLoadError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            AgentLoadError::Io(e) => write!(f, "IO error: {e}"),
-            AgentLoadError::Yaml(e) => write!(f, "YAML parse error: {e}"),
-            AgentLoadError::Validation(e) => write!(f, "Validation error: {e}"),
+            AgentLoadError::Io { error, .. } => write!(f, "IO error: {error}"),
+            AgentLoadError::Validation(diagnostics) => {
+                let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+                write!(f, "Validation error: {}", messages.join("; "))
+            }
         }
     }
 }
@@ -19,19 +46,12 @@ impl std::fmt::Display for AgentLoadError {
 impl std::error::Error for AgentLoadError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            AgentLoadError::Io(e) => Some(e),
-            AgentLoadError::Yaml(e) => Some(e),
+            AgentLoadError::Io { error, .. } => Some(error),
             AgentLoadError::Validation(_) => None,
         }
     }
 }
 
-impl From<std::io::Error> for AgentLoadError {
-    fn from(e: std::io::Error) -> Self {
-        AgentLoadError::Io(e)
-    }
-}
-
 /// Non-fatal issues encountered while loading an agent.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AgentLoadWarning {
@@ -40,5 +60,206 @@ pub enum AgentLoadWarning {
         field: &'static str,
         path: std::path::PathBuf,
         error: String,
+        /// The `agent.yaml` that references the file, and where.
+        manifest: PathBuf,
+        span: Option<SourceSpan>,
+    },
+    /// A key that is not part of the AAF spec, probably a typo.
+    UnknownField {
+        agent: String,
+        /// Dotted path to the key, e.g. `spec.model.temprature`.
+        field: String,
+        /// The known field the key most resembles.
+        suggestion: Option<String>,
+        manifest: PathBuf,
+        span: Option<SourceSpan>,
+    },
+    /// A documented AAF section that Agnx does not implement yet, and ignores.
+    UnsupportedField {
+        agent: String,
+        field: String,
+        manifest: PathBuf,
+        span: Option<SourceSpan>,
+    },
+    /// A field written in a form that the document's apiVersion deprecates.
+    DeprecatedField {
+        agent: String,
+        field: String,
+        /// How the field should be written instead.
+        replacement: &'static str,
+        deprecated_in: &'static str,
+        manifest: PathBuf,
+        span: Option<SourceSpan>,
+    },
+    /// A model parameter that the provider does not accept, so requests leave it out.
+    UnsupportedParameter {
+        agent: String,
+        provider: String,
+        parameter: &'static str,
+        manifest: PathBuf,
+        span: Option<SourceSpan>,
+    },
+    /// A skill that could not be read, or whose frontmatter is malformed. Skills with
+    /// malformed frontmatter are still loaded, under their directory name.
+    InvalidSkill {
+        agent: String,
+        /// The `SKILL.md` in question.
+        path: PathBuf,
+        error: String,
+        manifest: PathBuf,
+        /// Location of the `skills[].path` entry, for listed skills.
+        span: Option<SourceSpan>,
+    },
+    /// A skill whose ID is already taken; it is skipped.
+    DuplicateSkill {
+        agent: String,
+        id: String,
+        path: PathBuf,
+        /// The `SKILL.md` of the skill that keeps the ID.
+        existing: PathBuf,
     },
+    /// A prompt references a `${...}` variable that rendering will not fill in.
+    UnknownTemplateVariable {
+        agent: String,
+        field: &'static str,
+        variable: String,
+        /// The file containing the reference: the prompt file, or `agent.yaml` for
+        /// inline prompts.
+        path: PathBuf,
+        span: Option<SourceSpan>,
+    },
+}
+
+impl AgentLoadWarning {
+    /// Structured diagnostic describing this warning.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            AgentLoadWarning::MissingFile {
+                field,
+                path,
+                error,
+                manifest,
+                span,
+                ..
+            } => Diagnostic::warning(
+                DiagnosticCode::MissingFile,
+                manifest,
+                format!(
+                    "{field} file '{}' could not be read: {error}",
+                    path.display()
+                ),
+            )
+            .with_span(*span)
+            .with_hint("check that the path is relative to the agent directory"),
+            AgentLoadWarning::UnknownField {
+                field,
+                suggestion,
+                manifest,
+                span,
+                ..
+            } => {
+                let diagnostic = Diagnostic::warning(
+                    DiagnosticCode::UnknownField,
+                    manifest,
+                    format!("unknown field '{field}'"),
+                )
+                .with_span(*span);
+                match suggestion {
+                    Some(s) => diagnostic.with_hint(format!("did you mean '{s}'?")),
+                    None => diagnostic,
+                }
+            }
+            AgentLoadWarning::UnsupportedField {
+                field,
+                manifest,
+                span,
+                ..
+            } => Diagnostic::warning(
+                DiagnosticCode::UnsupportedField,
+                manifest,
+                format!("'{field}' is not yet supported and will be ignored"),
+            )
+            .with_span(*span),
+            AgentLoadWarning::DeprecatedField {
+                field,
+                replacement,
+                deprecated_in,
+                manifest,
+                span,
+                ..
+            } => Diagnostic::warning(
+                DiagnosticCode::DeprecatedField,
+                manifest,
+                format!("'{field}' is deprecated in {deprecated_in}"),
+            )
+            .with_span(*span)
+            .with_hint(format!(
+                "use {replacement} instead; `agnx migrate` rewrites it for you"
+            )),
+            AgentLoadWarning::UnsupportedParameter {
+                provider,
+                parameter,
+                manifest,
+                span,
+                ..
+            } => Diagnostic::warning(
+                DiagnosticCode::UnsupportedParameter,
+                manifest,
+                format!(
+                    "spec.model.{parameter} is not supported by provider '{provider}' and will be ignored"
+                ),
+            )
+            .with_span(*span)
+            .with_hint(format!(
+                "providers that accept it: {}; provider-specific options go under spec.model.extra",
+                Provider::supporting(parameter).unwrap_or_default().join(", ")
+            )),
+            AgentLoadWarning::InvalidSkill {
+                path,
+                error,
+                manifest,
+                span: Some(span),
+                ..
+            } => Diagnostic::warning(
+                DiagnosticCode::InvalidSkill,
+                manifest,
+                format!("skill '{}': {error}", path.display()),
+            )
+            .with_span(Some(*span)),
+            AgentLoadWarning::InvalidSkill {
+                path,
+                error,
+                span: None,
+                ..
+            } => Diagnostic::warning(DiagnosticCode::InvalidSkill, path, error.clone())
+                .with_hint("SKILL.md frontmatter goes between '---' lines at the top"),
+            AgentLoadWarning::DuplicateSkill {
+                id, path, existing, ..
+            } => Diagnostic::warning(
+                DiagnosticCode::DuplicateSkill,
+                path,
+                format!(
+                    "skill ID '{id}' is already used by '{}'; skipping this skill",
+                    existing.display()
+                ),
+            )
+            .with_hint("give one of the skills a different frontmatter `name`"),
+            AgentLoadWarning::UnknownTemplateVariable {
+                field,
+                variable,
+                path,
+                span,
+                ..
+            } => Diagnostic::warning(
+                DiagnosticCode::UnknownTemplateVariable,
+                path,
+                format!("{field} references unknown variable '${{{variable}}}'"),
+            )
+            .with_span(*span)
+            .with_hint(format!(
+                "available variables: {}; write $${{ for a literal '${{'",
+                TEMPLATE_VARIABLES.join(", ")
+            )),
+        }
+    }
 }
@@ -0,0 +1,457 @@
+//! `extends:` inheritance between agent definitions.
+//!
+//! A child document is deep-merged over its parent chain before it is deserialized:
+//!
+//! - Mappings merge key by key; the child's value wins.
+//! - Sequences and scalars (including a string `spec.model`) replace the parent's value.
+//! - An explicit `null` removes the inherited value.
+//! - `metadata.name` and `metadata.namespace` are never inherited.
+//! - Relative file references in a parent are resolved against the parent's directory.
+
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::interpolate::interpolate_env;
+
+pub(super) const EXTENDS_KEY: &str = "extends";
+
+/// A parent in an agent's inheritance chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentParent {
+    /// The parent's `metadata.name`, if it has one.
+    pub name: Option<String>,
+    pub dir: PathBuf,
+}
+
+/// Why an `extends` chain could not be resolved.
+#[derive(Debug)]
+pub(super) enum ExtendsError {
+    /// `extends` is not a string.
+    NotAString,
+    /// A name that no loaded agent has.
+    UnknownParent(String),
+    /// The parent definition could not be read or parsed.
+    InvalidParent { dir: PathBuf, error: String },
+    /// The chain leads back to an agent already in it.
+    Cycle(Vec<PathBuf>),
+    /// A deployed agent's parent is not a deployed agent.
+    OutsideRoot { dir: PathBuf, root: PathBuf },
+}
+
+impl std::fmt::Display for ExtendsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtendsError::NotAString => write!(f, "extends must be a path or an agent name"),
+            ExtendsError::UnknownParent(name) => {
+                write!(f, "extends unknown agent '{name}'")
+            }
+            ExtendsError::InvalidParent { dir, error } => {
+                write!(
+                    f,
+                    "parent agent {} could not be loaded: {error}",
+                    dir.display()
+                )
+            }
+            ExtendsError::Cycle(chain) => {
+                let chain: Vec<String> = chain.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "inheritance cycle: {}", chain.join(" -> "))
+            }
+            ExtendsError::OutsideRoot { dir, root } => {
+                write!(
+                    f,
+                    "parent agent {} is outside {}; deployed agents can only extend other deployed agents",
+                    dir.display(),
+                    root.display()
+                )
+            }
+        }
+    }
+}
+
+/// Whether an `extends` value is a path (as opposed to an agent name).
+pub(super) fn is_path_reference(reference: &str) -> bool {
+    reference.starts_with('.') || reference.contains('/') || reference.contains('\\')
+}
+
+/// Remove and return the document's `extends` reference.
+pub(super) fn take_extends(doc: &mut Value) -> Result<Option<String>, ExtendsError> {
+    match doc.as_object_mut().and_then(|m| m.remove(EXTENDS_KEY)) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(reference)) => Ok(Some(reference)),
+        Some(_) => Err(ExtendsError::NotAString),
+    }
+}
+
+/// Merge `doc` (whose `extends` has already been taken) over its parent chain.
+///
+/// `lookup` maps agent names to their directories. With a `sandbox`, every parent must
+/// be inside it, is read without environment interpolation and may not reference files
+/// outside its own directory. Returns the merged document and the parent chain, nearest
+/// parent first.
+pub(super) fn resolve(
+    doc: Value,
+    reference: String,
+    agent_dir: &Path,
+    lookup: &dyn Fn(&str) -> Option<PathBuf>,
+    sandbox: Option<&Path>,
+) -> Result<(Value, Vec<AgentParent>), ExtendsError> {
+    let sandbox = sandbox.map(canonical);
+    let mut chain = vec![canonical(agent_dir)];
+    let mut parents = Vec::new();
+    let mut layers = Vec::new();
+
+    let mut next = Some(reference);
+    while let Some(reference) = next {
+        let dir = if is_path_reference(&reference) {
+            chain[chain.len() - 1].join(&reference)
+        } else {
+            lookup(&reference).ok_or(ExtendsError::UnknownParent(reference))?
+        };
+        let dir = canonical(&dir);
+        if chain.contains(&dir) {
+            chain.push(dir);
+            return Err(ExtendsError::Cycle(chain));
+        }
+
+        if let Some(root) = sandbox.as_ref().filter(|root| !dir.starts_with(root)) {
+            return Err(ExtendsError::OutsideRoot {
+                dir,
+                root: root.clone(),
+            });
+        }
+
+        let mut layer = read_document(&dir, sandbox.is_none())?;
+        next = take_extends(&mut layer)?;
+        if sandbox.is_some()
+            && let Some((_, path)) = escaping_references(&mut layer).into_iter().next()
+        {
+            return Err(ExtendsError::InvalidParent {
+                dir,
+                error: format!("file reference '{path}' is outside the agent directory"),
+            });
+        }
+        rebase_paths(&mut layer, &dir);
+        let metadata = layer.get_mut("metadata").and_then(Value::as_object_mut);
+        let name = metadata.and_then(|m| {
+            // A child is served under its own namespace, not its parent's.
+            m.remove("namespace");
+            m.remove("name")
+        });
+        let name = name.and_then(|v| v.as_str().map(str::to_string));
+
+        parents.push(AgentParent {
+            name,
+            dir: dir.clone(),
+        });
+        layers.push(layer);
+        chain.push(dir);
+    }
+
+    let mut merged = Value::Object(Map::new());
+    for layer in layers.into_iter().rev() {
+        merge(&mut merged, layer);
+    }
+    merge(&mut merged, doc);
+    Ok((merged, parents))
+}
+
+/// Order agent directories so that parents come before the agents that extend them.
+///
+/// Directories whose definition cannot be read keep their relative position; cycles
+/// are broken arbitrarily and reported when the agents themselves are loaded.
+pub(super) fn dependency_order(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let headers: Vec<(Option<String>, Option<String>)> = dirs
+        .iter()
+        .map(|dir| {
+            let Ok(mut doc) = read_document(dir, true) else {
+                return (None, None);
+            };
+            let name = doc
+                .pointer("/metadata/name")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            (name, take_extends(&mut doc).ok().flatten())
+        })
+        .collect();
+
+    let mut by_name = HashMap::new();
+    for (index, (name, _)) in headers.iter().enumerate() {
+        if let Some(name) = name {
+            by_name.entry(name.as_str()).or_insert(index);
+        }
+    }
+    let by_dir: HashMap<PathBuf, usize> = dirs
+        .iter()
+        .enumerate()
+        .map(|(index, dir)| (canonical(dir), index))
+        .collect();
+    let parent_of = |index: usize| {
+        let reference = headers[index].1.as_deref()?;
+        if is_path_reference(reference) {
+            by_dir
+                .get(&canonical(&dirs[index].join(reference)))
+                .copied()
+        } else {
+            by_name.get(reference).copied()
+        }
+    };
+
+    // 0 = unvisited, 1 = in progress, 2 = done.
+    let mut state = vec![0u8; dirs.len()];
+    let mut order = Vec::with_capacity(dirs.len());
+    for start in 0..dirs.len() {
+        let mut stack = Vec::new();
+        let mut current = Some(start);
+        while let Some(index) = current.filter(|&i| state[i] == 0) {
+            state[index] = 1;
+            stack.push(index);
+            current = parent_of(index);
+        }
+        for index in stack.into_iter().rev() {
+            state[index] = 2;
+            order.push(dirs[index].clone());
+        }
+    }
+    order
+}
+
+/// Canonical form of `dir`, used to compare agent directories.
+pub(super) fn canonical(dir: &Path) -> PathBuf {
+    fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf())
+}
+
+fn read_document(dir: &Path, interpolate: bool) -> Result<Value, ExtendsError> {
+    let invalid = |error: String| ExtendsError::InvalidParent {
+        dir: dir.to_path_buf(),
+        error,
+    };
+    let text = fs::read_to_string(dir.join("agent.yaml")).map_err(|e| invalid(e.to_string()))?;
+    let text = match interpolate {
+        true => interpolate_env(&text).map_err(|e| invalid(e.to_string()))?,
+        false => Cow::Borrowed(text.as_str()),
+    };
+    serde_saphyr::from_str(&text).map_err(|e| invalid(e.without_snippet().to_string()))
+}
+
+/// Deep-merge `overlay` into `base`.
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                if value.is_null() {
+                    base.remove(&key);
+                    continue;
+                }
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Make relative file references in a parent document absolute, so they keep pointing
+/// into the parent's directory once merged into a child.
+fn rebase_paths(doc: &mut Value, dir: &Path) {
+    visit_file_references(doc, |_, value, reference| match reference {
+        Reference::Path => rebase(value, dir),
+        Reference::Program => rebase_program(value, dir),
+    });
+}
+
+/// How a file reference is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Reference {
+    /// A path, relative to the agent's directory.
+    Path,
+    /// A command line whose program is a path if it contains a `/`.
+    Program,
+}
+
+/// Call `visit` with the key path, value and kind of every file reference in `spec`.
+pub(super) fn visit_file_references(
+    doc: &mut Value,
+    mut visit: impl FnMut(&[&str], &mut Value, Reference),
+) {
+    let Some(spec) = doc.get_mut("spec").and_then(Value::as_object_mut) else {
+        return;
+    };
+
+    for field in ["system_prompt", "instructions"] {
+        match spec.get_mut(field) {
+            Some(path @ Value::String(_)) => visit(&["spec", field], path, Reference::Path),
+            Some(Value::Object(tagged)) => {
+                if let Some(path) = tagged.get_mut("file") {
+                    visit(&["spec", field, "file"], path, Reference::Path);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(path) = spec.get_mut("skills_dir") {
+        visit(&["spec", "skills_dir"], path, Reference::Path);
+    }
+    for list in ["bootstrap", "skills"] {
+        if let Some(Value::Array(entries)) = spec.get_mut(list) {
+            for (index, entry) in entries.iter_mut().enumerate() {
+                if let Some(path) = entry.get_mut("path") {
+                    visit(
+                        &["spec", list, &index.to_string(), "path"],
+                        path,
+                        Reference::Path,
+                    );
+                }
+            }
+        }
+    }
+    if let Some(Value::Array(tools)) = spec.get_mut("tools") {
+        for (index, tool) in tools.iter_mut().enumerate() {
+            let index = index.to_string();
+            if let Some(readme) = tool.get_mut("readme") {
+                visit(
+                    &["spec", "tools", &index, "readme"],
+                    readme,
+                    Reference::Path,
+                );
+            }
+            for field in ["command", "server"] {
+                match tool.get_mut(field) {
+                    // An MCP `server` may be a URL rather than a command.
+                    Some(Value::String(url)) if url.contains("://") => {}
+                    Some(command) => visit(
+                        &["spec", "tools", &index, field],
+                        command,
+                        Reference::Program,
+                    ),
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+/// File references that leave the agent's directory: absolute paths and paths with
+/// `..`. Returns the key path and value of each.
+pub(super) fn escaping_references(doc: &mut Value) -> Vec<(Vec<String>, String)> {
+    let mut escaping = Vec::new();
+    visit_file_references(doc, |keys, value, reference| {
+        let Value::String(text) = value else {
+            return;
+        };
+        let path = match reference {
+            Reference::Path => text.as_str(),
+            Reference::Program => match split_program(text) {
+                (program, _) if program.contains('/') => program,
+                _ => return,
+            },
+        };
+        let path = Path::new(path);
+        if path.has_root() || path.components().any(|c| c == Component::ParentDir) {
+            escaping.push((keys.iter().map(|k| k.to_string()).collect(), text.clone()));
+        }
+    });
+    escaping
+}
+
+/// Split a command line into its program and the rest.
+fn split_program(command: &str) -> (&str, &str) {
+    let trimmed = command.trim_start();
+    let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+    trimmed.split_at(end)
+}
+
+/// Rebase the program of a command line if it is a relative path; bare program names
+/// are looked up on `PATH`.
+fn rebase_program(value: &mut Value, dir: &Path) {
+    if let Value::String(command) = value {
+        let (program, args) = split_program(command);
+        if program.contains('/') && Path::new(program).is_relative() {
+            *command = format!("{}{args}", dir.join(program).display());
+        }
+    }
+}
+
+fn rebase(value: &mut Value, dir: &Path) {
+    if let Value::String(path) = value
+        && Path::new(path.as_str()).is_relative()
+    {
+        *path = dir.join(path.as_str()).display().to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn merge_rules() {
+        let mut base = json!({
+            "metadata": { "labels": { "team": "core", "tier": "free" } },
+            "spec": {
+                "model": { "provider": "openrouter", "name": "a", "temperature": 0.7 },
+                "bootstrap": [{ "path": "A.md" }],
+                "instructions": "I.md"
+            }
+        });
+        merge(
+            &mut base,
+            json!({
+                "metadata": { "labels": { "tier": "premium" } },
+                "spec": {
+                    "model": { "temperature": 0.2 },
+                    "bootstrap": [{ "path": "B.md" }],
+                    "instructions": null
+                }
+            }),
+        );
+
+        assert_eq!(
+            base,
+            json!({
+                "metadata": { "labels": { "team": "core", "tier": "premium" } },
+                "spec": {
+                    "model": { "provider": "openrouter", "name": "a", "temperature": 0.2 },
+                    "bootstrap": [{ "path": "B.md" }]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn rebases_relative_paths_only() {
+        let mut doc = json!({
+            "spec": {
+                "system_prompt": "./S.md",
+                "instructions": { "file": "/abs/I.md" },
+                "bootstrap": [{ "path": "SOUL.md" }],
+                "tools": [
+                    { "command": "./bin/lint --fix", "readme": "LINT.md" },
+                    { "command": "rg -n", "server": "/opt/mcp" },
+                    { "server": "./bin/mcp --stdio" },
+                    { "server": "https://mcp.example.com/sse" }
+                ]
+            }
+        });
+        rebase_paths(&mut doc, Path::new("/agents/base"));
+
+        assert_eq!(doc["spec"]["system_prompt"], "/agents/base/./S.md");
+        assert_eq!(doc["spec"]["instructions"]["file"], "/abs/I.md");
+        assert_eq!(doc["spec"]["bootstrap"][0]["path"], "/agents/base/SOUL.md");
+        let tools = &doc["spec"]["tools"];
+        assert_eq!(tools[0]["command"], "/agents/base/./bin/lint --fix");
+        assert_eq!(tools[0]["readme"], "/agents/base/LINT.md");
+        assert_eq!(tools[1]["command"], "rg -n");
+        assert_eq!(tools[1]["server"], "/opt/mcp");
+        assert_eq!(tools[2]["server"], "/agents/base/./bin/mcp --stdio");
+        assert_eq!(tools[3]["server"], "https://mcp.example.com/sse");
+    }
+}
@@ -0,0 +1,350 @@
+//! Detection of unknown keys in `agent.yaml`.
+//!
+//! serde ignores keys it does not recognise, so a typo such as `temprature` would
+//! otherwise be dropped silently. The known AAF layout is described here and compared
+//! against the document's keys.
+
+use std::path::Path;
+
+use super::diagnostic::{Diagnostic, DiagnosticCode};
+use super::source_map::{SourceMap, SourceSpan};
+
+/// Expected shape of a value in `agent.yaml`.
+enum Shape {
+    /// A scalar, or anything whose contents are not checked.
+    Any,
+    /// A mapping with these keys. Only checked when the value actually is a mapping, so
+    /// fields that also accept a string (`spec.model`, prompts) use this too.
+    Map(&'static [(&'static str, Shape)]),
+    /// A sequence whose elements have the given shape.
+    Seq(&'static Shape),
+    /// Documented in the AAF spec, but not implemented yet.
+    Unsupported,
+}
+
+const ROOT: Shape = Shape::Map(&[
+    ("apiVersion", Shape::Any),
+    ("kind", Shape::Any),
+    ("extends", Shape::Any),
+    ("metadata", METADATA),
+    ("spec", SPEC),
+]);
+
+const METADATA: Shape = Shape::Map(&[
+    ("name", Shape::Any),
+    ("namespace", Shape::Any),
+    ("description", Shape::Any),
+    ("version", Shape::Any),
+    ("labels", Shape::Any),
+    ("a2a", Shape::Unsupported),
+]);
+
+const SPEC: Shape = Shape::Map(&[
+    ("model", MODEL),
+    ("system_prompt", PROMPT),
+    ("instructions", PROMPT),
+    ("bootstrap", Shape::Seq(&BOOTSTRAP)),
+    ("memory", Shape::Unsupported),
+    ("tools", Shape::Seq(&TOOL)),
+    ("skills_dir", Shape::Any),
+    ("skills", Shape::Seq(&SKILL)),
+    ("triggers", Shape::Unsupported),
+]);
+
+const MODEL: Shape = Shape::Map(&[
+    ("provider", Shape::Any),
+    ("name", Shape::Any),
+    ("temperature", Shape::Any),
+    ("max_input_tokens", Shape::Any),
+    ("max_output_tokens", Shape::Any),
+    ("max_tokens", Shape::Any),
+    ("base_url", Shape::Any),
+    ("top_p", Shape::Any),
+    ("top_k", Shape::Any),
+    ("stop", Shape::Any),
+    ("seed", Shape::Any),
+    ("presence_penalty", Shape::Any),
+    ("frequency_penalty", Shape::Any),
+    ("reasoning_effort", Shape::Any),
+    ("extra", Shape::Any),
+]);
+
+const PROMPT: Shape = Shape::Map(&[("file", Shape::Any), ("inline", Shape::Any)]);
+
+const SKILL: Shape = Shape::Map(&[("path", Shape::Any), ("url", Shape::Unsupported)]);
+
+const TOOL: Shape = Shape::Map(&[
+    ("name", Shape::Any),
+    ("type", Shape::Any),
+    ("server", Shape::Any),
+    ("config", Shape::Any),
+    ("command", Shape::Any),
+    ("readme", Shape::Any),
+]);
+
+const BOOTSTRAP: Shape = Shape::Map(&[
+    ("path", Shape::Any),
+    ("max_tokens", Shape::Any),
+    ("required", Shape::Any),
+]);
+
+/// A key that Agnx does not act on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct FieldIssue {
+    /// Dotted path to the key, e.g. `spec.model.temprature`.
+    pub field: String,
+    pub span: Option<SourceSpan>,
+    pub kind: FieldIssueKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum FieldIssueKind {
+    /// Not part of the AAF spec; possibly a typo of `suggestion`.
+    Unknown { suggestion: Option<String> },
+    /// Part of the AAF spec but not implemented yet.
+    Unsupported,
+}
+
+/// Find unknown and not-yet-supported keys in `source`.
+pub(super) fn check(source: &SourceMap) -> Vec<FieldIssue> {
+    let mut issues = Vec::new();
+    walk(source, &mut Vec::new(), &ROOT, &mut issues);
+    issues
+}
+
+/// Find unknown keys in the model aliases under `models:` in `agnx.yaml` (`text`), which
+/// have the same layout as `spec.model`.
+pub fn check_model_aliases(config_path: &Path, text: &str) -> Vec<Diagnostic> {
+    let source = SourceMap::parse(text);
+    let mut issues = Vec::new();
+    for (alias, _) in source.keys(&["models"]) {
+        let mut path = vec!["models".to_string(), alias.to_string()];
+        walk(&source, &mut path, &MODEL, &mut issues);
+    }
+    issues
+        .into_iter()
+        .map(|issue| {
+            let diagnostic = Diagnostic::error(
+                DiagnosticCode::UnknownField,
+                config_path,
+                format!("unknown field '{}'", issue.field),
+            )
+            .with_span(issue.span);
+            match issue.kind {
+                FieldIssueKind::Unknown {
+                    suggestion: Some(s),
+                } => diagnostic.with_hint(format!("did you mean '{s}'?")),
+                _ => diagnostic,
+            }
+        })
+        .collect()
+}
+
+fn walk(source: &SourceMap, path: &mut Vec<String>, shape: &Shape, issues: &mut Vec<FieldIssue>) {
+    let segments: Vec<&str> = path.iter().map(String::as_str).collect();
+    match shape {
+        Shape::Any | Shape::Unsupported => {}
+        Shape::Seq(element) => {
+            for index in 0..source.seq_len(&segments) {
+                path.push(index.to_string());
+                walk(source, path, element, issues);
+                path.pop();
+            }
+        }
+        Shape::Map(fields) => {
+            for (key, span) in source.keys(&segments) {
+                let field = display_path(path, key);
+                match fields.iter().find(|(name, _)| *name == key) {
+                    Some((_, Shape::Unsupported)) => issues.push(FieldIssue {
+                        field,
+                        span,
+                        kind: FieldIssueKind::Unsupported,
+                    }),
+                    Some((_, shape)) => {
+                        path.push(key.to_string());
+                        walk(source, path, shape, issues);
+                        path.pop();
+                    }
+                    None => issues.push(FieldIssue {
+                        field,
+                        span,
+                        kind: FieldIssueKind::Unknown {
+                            suggestion: suggest(key, fields.iter().map(|(name, _)| *name)),
+                        },
+                    }),
+                }
+            }
+        }
+    }
+}
+
+/// `spec.bootstrap[0].path`-style rendering of a key path.
+fn display_path(path: &[String], key: &str) -> String {
+    let mut out = String::new();
+    for segment in path.iter().map(String::as_str).chain([key]) {
+        if segment.parse::<usize>().is_ok() {
+            out.push_str(&format!("[{segment}]"));
+        } else {
+            if !out.is_empty() {
+                out.push('.');
+            }
+            out.push_str(segment);
+        }
+    }
+    out
+}
+
+/// The candidate closest to `key`, if it is close enough to be a likely typo.
+fn suggest<'a>(key: &str, candidates: impl Iterator<Item = &'a str>) -> Option<String> {
+    let max_distance = (key.chars().count() / 3).max(1);
+    candidates
+        .map(|c| (edit_distance(key, c), c))
+        .filter(|(d, _)| *d <= max_distance)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c.to_string())
+}
+
+/// Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn reports_typos_with_suggestions_and_unsupported_sections() {
+        let source = SourceMap::parse(
+            "apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: a
+  labels:
+    anything: goes
+spec:
+  model:
+    provider: openrouter
+    temprature: 0.2
+    max_output_token: 10
+  bootstrap:
+    - path: ./A.md
+      requird: true
+  memory: {}
+  colour: blue
+",
+        );
+
+        let issues: Vec<_> = check(&source)
+            .into_iter()
+            .map(|i| (i.field, i.span.map(|s| (s.line, s.column)), i.kind))
+            .collect();
+        let unknown = |s: Option<&str>| FieldIssueKind::Unknown {
+            suggestion: s.map(str::to_string),
+        };
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    "spec.model.temprature".to_string(),
+                    Some((10, 5)),
+                    unknown(Some("temperature"))
+                ),
+                (
+                    "spec.model.max_output_token".to_string(),
+                    Some((11, 5)),
+                    unknown(Some("max_output_tokens"))
+                ),
+                (
+                    "spec.bootstrap[0].requird".to_string(),
+                    Some((14, 7)),
+                    unknown(Some("required"))
+                ),
+                (
+                    "spec.memory".to_string(),
+                    Some((15, 3)),
+                    FieldIssueKind::Unsupported
+                ),
+                ("spec.colour".to_string(), Some((16, 3)), unknown(None)),
+            ]
+        );
+    }
+
+    /// Leaf schemas reachable from `nodes`, following `$ref`s and `anyOf`/`oneOf` branches.
+    fn resolve<'a>(root: &'a Value, nodes: Vec<&'a Value>) -> Vec<&'a Value> {
+        let mut leaves = Vec::new();
+        for node in nodes {
+            if let Some(reference) = node.get("$ref").and_then(Value::as_str) {
+                let pointer = reference.trim_start_matches('#');
+                leaves.extend(resolve(root, vec![root.pointer(pointer).unwrap()]));
+            } else if let Some(branches) = node.get("anyOf").or_else(|| node.get("oneOf")) {
+                leaves.extend(resolve(root, branches.as_array().unwrap().iter().collect()));
+            } else {
+                leaves.push(node);
+            }
+        }
+        leaves
+    }
+
+    fn assert_matches_schema(root: &Value, nodes: Vec<&Value>, shape: &Shape, path: &str) {
+        let leaves = resolve(root, nodes);
+        match shape {
+            Shape::Any | Shape::Unsupported => {}
+            Shape::Seq(element) => {
+                let items = leaves.iter().filter_map(|l| l.get("items")).collect();
+                assert_matches_schema(root, items, element, &format!("{path}[]"));
+            }
+            Shape::Map(fields) => {
+                let properties: BTreeSet<&str> = leaves
+                    .iter()
+                    .filter_map(|l| l.get("properties").and_then(Value::as_object))
+                    .flat_map(|p| p.keys().map(String::as_str))
+                    .collect();
+                let known: BTreeSet<&str> = fields
+                    .iter()
+                    .filter(|(_, shape)| !matches!(shape, Shape::Unsupported))
+                    .map(|(name, _)| *name)
+                    // Accepted as a serde alias, which the schema does not list.
+                    .filter(|name| !(path == ".spec.model" && *name == "max_tokens"))
+                    .collect();
+                assert_eq!(
+                    known, properties,
+                    "fields of '{path}' differ from the schema"
+                );
+
+                for (name, shape) in fields.iter() {
+                    let children = leaves
+                        .iter()
+                        .filter_map(|l| l.pointer(&format!("/properties/{name}")))
+                        .collect();
+                    assert_matches_schema(root, children, shape, &format!("{path}.{name}"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn known_fields_match_the_schema() {
+        let schema = crate::agent::agent_schema();
+        assert_matches_schema(&schema, vec![&schema], &ROOT, "");
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("temprature", "temperature"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
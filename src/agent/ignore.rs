@@ -0,0 +1,142 @@
+//! `.agnxignore`: directories under the agents directory that are not loaded.
+//!
+//! One pattern per line, in a subset of `.gitignore` syntax:
+//!
+//! - Blank lines and lines starting with `#` are ignored.
+//! - `*` matches within a path segment, `?` matches one character and `**` matches
+//!   across segments.
+//! - A pattern without a `/` (other than a trailing one) matches a directory name at
+//!   any depth; otherwise it matches the path relative to the agents directory.
+//! - Ignoring a directory ignores everything below it.
+
+use std::fs;
+use std::path::{Component, Path};
+
+pub const IGNORE_FILE: &str = ".agnxignore";
+
+/// Patterns read from an `.agnxignore` file.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    glob: String,
+    /// Matched against the whole relative path rather than a single name.
+    anchored: bool,
+}
+
+impl IgnoreRules {
+    /// Read `<agents_dir>/.agnxignore`; a missing file means nothing is ignored.
+    pub fn load(agents_dir: &Path) -> Self {
+        fs::read_to_string(agents_dir.join(IGNORE_FILE))
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let patterns = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let line = line.trim_end_matches('/');
+                let anchored = line.contains('/');
+                Pattern {
+                    glob: line.trim_start_matches('/').to_string(),
+                    anchored,
+                }
+            })
+            .filter(|p| !p.glob.is_empty())
+            .collect();
+        Self { patterns }
+    }
+
+    /// Whether `relative` (a directory relative to the agents directory) or one of its
+    /// ancestors is ignored.
+    pub fn is_ignored(&self, relative: &Path) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        let segments: Vec<&str> = relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+
+        (1..=segments.len()).any(|len| {
+            let prefix = segments[..len].join("/");
+            self.patterns.iter().any(|p| {
+                if p.anchored {
+                    glob_match(&p.glob, &prefix)
+                } else {
+                    glob_match(&p.glob, segments[len - 1])
+                }
+            })
+        })
+    }
+}
+
+/// Match `text` against a glob supporting `*`, `?` and `**`.
+fn glob_match(glob: &str, text: &str) -> bool {
+    fn matches(glob: &[char], text: &[char]) -> bool {
+        match glob {
+            [] => text.is_empty(),
+            ['*', '*', rest @ ..] => {
+                let rest = rest.strip_prefix(&['/']).unwrap_or(rest);
+                (0..=text.len()).any(|i| matches(rest, &text[i..]))
+            }
+            ['*', rest @ ..] => {
+                let segment = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+                (0..=segment).any(|i| matches(rest, &text[i..]))
+            }
+            ['?', rest @ ..] => {
+                text.first().is_some_and(|&c| c != '/') && matches(rest, &text[1..])
+            }
+            [c, rest @ ..] => text.first() == Some(c) && matches(rest, &text[1..]),
+        }
+    }
+
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches(&glob, &text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_names_paths_and_globs() {
+        let rules = IgnoreRules::parse(
+            "# work in progress
+wip-*
+/platform/experimental/
+**/drafts
+",
+        );
+        let ignored = |p: &str| rules.is_ignored(Path::new(p));
+
+        assert!(ignored("wip-search"));
+        assert!(ignored("team/wip-bot"));
+        assert!(ignored("platform/experimental"));
+        assert!(ignored("platform/experimental/agent"));
+        assert!(ignored("a/b/drafts/c"));
+        assert!(!ignored("team/search"));
+        assert!(!ignored("other/platform/experimental"));
+        assert!(!ignored("wip"));
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("a*c", "abbc"));
+        assert!(!glob_match("a*c", "ab/c"));
+        assert!(glob_match("a/**/c", "a/b/d/c"));
+        assert!(glob_match("a/**/c", "a/c"));
+        assert!(glob_match("?b", "ab"));
+        assert!(!glob_match("?b", "/b"));
+    }
+}
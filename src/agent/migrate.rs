@@ -0,0 +1,257 @@
+//! Rewriting `agent.yaml` to the newest `apiVersion`.
+//!
+//! Changes are applied as text edits at the locations of the affected values, so
+//! comments, key order and formatting elsewhere in the file are kept.
+
+use serde_json::Value;
+
+use super::source_map::{SourceMap, SourceSpan};
+use super::version::{self, ApiVersion, Rewrite, VersionError};
+
+/// The result of migrating one document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub from: &'static str,
+    pub to: &'static str,
+    /// The document has no `apiVersion` of its own and inherits it through
+    /// `extends`; only its legacy field forms are rewritten.
+    pub inherited: bool,
+    /// The rewritten document; equal to the input when there was nothing to do.
+    pub text: String,
+    /// One line per change, e.g. `spec.model.max_tokens -> max_output_tokens`.
+    pub changes: Vec<String>,
+}
+
+impl Migration {
+    pub fn is_noop(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Why a document could not be migrated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrateError {
+    Yaml(String),
+    /// No `apiVersion` and no `extends` to inherit one from.
+    MissingApiVersion,
+    UnsupportedApiVersion(String),
+    /// A field that cannot be rewritten automatically.
+    Conflict {
+        field: String,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrateError::Yaml(error) => write!(f, "invalid YAML: {error}"),
+            MigrateError::MissingApiVersion => {
+                write!(f, "no apiVersion and no extends to inherit one from")
+            }
+            MigrateError::UnsupportedApiVersion(value) => {
+                write!(f, "unsupported apiVersion '{value}'")
+            }
+            MigrateError::Conflict { field, reason } => {
+                write!(f, "cannot rewrite '{field}': {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrateError {}
+
+/// Rewrite `text` to [`ApiVersion::LATEST`].
+///
+/// A document without `apiVersion` that inherits one through `extends` keeps
+/// inheriting it, but its legacy field forms are still rewritten: the parent may be
+/// migrated alongside it, and the newer forms are accepted by every version.
+pub fn migrate(text: &str) -> Result<Migration, MigrateError> {
+    let doc: Value = serde_saphyr::from_str(text)
+        .map_err(|e| MigrateError::Yaml(e.without_snippet().to_string()))?;
+    let to = ApiVersion::LATEST;
+    let (from, inherited) = match version::detect(&doc) {
+        Ok(from) => (from, false),
+        Err(VersionError::Missing) if doc.get("extends").is_some() => (to, true),
+        Err(VersionError::Missing) => return Err(MigrateError::MissingApiVersion),
+        Err(VersionError::Unsupported(value)) => {
+            return Err(MigrateError::UnsupportedApiVersion(value));
+        }
+    };
+    let mut migration = Migration {
+        from: from.as_str(),
+        to: to.as_str(),
+        inherited,
+        text: text.to_string(),
+        changes: Vec::new(),
+    };
+    let source = SourceMap::parse(text);
+    let conflict = |field: &str, reason: &str| MigrateError::Conflict {
+        field: field.to_string(),
+        reason: reason.to_string(),
+    };
+    let mut edits = Vec::new();
+    if from != to {
+        let span = source
+            .value_span(&["apiVersion"])
+            .ok_or_else(|| conflict("apiVersion", "not a plain value"))?;
+        edits.push((scalar_range(text, span), to.as_str().to_string()));
+        migration
+            .changes
+            .push(format!("apiVersion: {from} -> {to}"));
+    }
+
+    for legacy in version::legacy_fields(to, &doc, &source) {
+        let span = legacy
+            .span
+            .filter(|s| s.line == s.end_line)
+            .ok_or_else(|| conflict(&legacy.field, "not on a single line"))?;
+        let range = match legacy.rewrite {
+            Rewrite::RenameKey(_) => {
+                offset(text, span.line, span.column)..offset(text, span.end_line, span.end_column)
+            }
+            Rewrite::WrapValue(_) => scalar_range(text, span),
+        };
+        let original = &text[range.clone()];
+        let replacement = match legacy.rewrite {
+            Rewrite::RenameKey(key) => {
+                let parent = legacy.field.rsplit_once('.').map_or("", |(p, _)| p);
+                let siblings: Vec<&str> = parent.split('.').collect();
+                if source.keys(&siblings).iter().any(|(k, _)| *k == key) {
+                    return Err(conflict(&legacy.field, &format!("'{key}' is also set")));
+                }
+                key.to_string()
+            }
+            Rewrite::WrapValue(key) => format!("{{ {key}: {original} }}"),
+        };
+        migration
+            .changes
+            .push(format!("{}: {original} -> {replacement}", legacy.field));
+        edits.push((range, replacement));
+    }
+
+    // Apply from the end of the file so earlier offsets stay valid.
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    for (range, replacement) in edits {
+        migration.text.replace_range(range, &replacement);
+    }
+    Ok(migration)
+}
+
+/// Byte range of the scalar at a single-line `span`.
+///
+/// Spans of quoted scalars can run on to the end of the line, so the range is cut at
+/// the closing quote, or for plain scalars before any trailing comment.
+fn scalar_range(text: &str, span: SourceSpan) -> std::ops::Range<usize> {
+    let start = offset(text, span.line, span.column);
+    let value = &text[start..offset(text, span.end_line, span.end_column)];
+    let len = match value.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let mut escaped = false;
+            value[1..]
+                .char_indices()
+                .find(|&(_, c)| {
+                    let closes = c == quote && !escaped;
+                    escaped = quote == '"' && c == '\\' && !escaped;
+                    closes
+                })
+                .map_or(value.len(), |(i, _)| i + 2)
+        }
+        _ => value.find(" #").unwrap_or(value.len()),
+    };
+    start..start + value[..len].trim_end().len()
+}
+
+/// Byte offset of a 1-indexed line and character column.
+fn offset(text: &str, line: u32, column: u32) -> usize {
+    let mut start = 0;
+    for (index, content) in text.split_inclusive('\n').enumerate() {
+        if index + 1 == line as usize {
+            return start
+                + content
+                    .char_indices()
+                    .nth(column as usize - 1)
+                    .map_or(content.len(), |(i, _)| i);
+        }
+        start += content.len();
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_legacy_forms_and_keeps_comments() {
+        let text = "# Support agent
+apiVersion: agnx/v1alpha1 # bumped by migrate
+kind: Agent
+metadata:
+  name: support
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+    max_tokens: 1024 # output budget
+  system_prompt: ./SYSTEM_PROMPT.md
+  instructions:
+    inline: Be brief.
+";
+        let migration = migrate(text).unwrap();
+        assert_eq!(migration.from, "agnx/v1alpha1");
+        assert_eq!(migration.to, "agnx/v1");
+        assert_eq!(
+            migration.text,
+            "# Support agent
+apiVersion: agnx/v1 # bumped by migrate
+kind: Agent
+metadata:
+  name: support
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+    max_output_tokens: 1024 # output budget
+  system_prompt: { file: ./SYSTEM_PROMPT.md }
+  instructions:
+    inline: Be brief.
+"
+        );
+        assert_eq!(migration.changes.len(), 3);
+
+        let again = migrate(&migration.text).unwrap();
+        assert!(again.is_noop());
+        assert_eq!(again.text, migration.text);
+    }
+
+    #[test]
+    fn handles_quoting_and_refuses_ambiguous_rewrites() {
+        let text = "apiVersion: agnx/v1beta1
+spec:
+  model:
+    max_tokens: 1
+    max_output_tokens: 2
+";
+        assert!(matches!(
+            migrate(text),
+            Err(MigrateError::Conflict { field, .. }) if field == "spec.model.max_tokens"
+        ));
+        assert_eq!(
+            migrate("apiVersion: 'agnx/v1beta1' # pinned\nspec:\n  instructions: \"I.md\" # c\n")
+                .unwrap()
+                .text,
+            "apiVersion: agnx/v1 # pinned\nspec:\n  instructions: { file: \"I.md\" } # c\n"
+        );
+        assert_eq!(
+            migrate("kind: Agent\n"),
+            Err(MigrateError::MissingApiVersion)
+        );
+        let child = migrate("extends: ../base\nspec:\n  system_prompt: ./S.md\n").unwrap();
+        assert!(child.inherited);
+        assert_eq!(
+            child.text,
+            "extends: ../base\nspec:\n  system_prompt: { file: ./S.md }\n"
+        );
+    }
+}
@@ -1,14 +1,47 @@
 //! Agent loading and registry types for Agnx Agent Format (AAF).
 //!
 //! This module is responsible for parsing `agent.yaml`, loading referenced Markdown files,
-//! and maintaining an in-memory registry (`AgentStore`) used by the HTTP API.
+//! and maintaining an in-memory registry (`AgentRegistry`) used by the HTTP API.
 
 pub const API_VERSION_V1ALPHA1: &str = "agnx/v1alpha1";
+pub const API_VERSION_V1BETA1: &str = "agnx/v1beta1";
+pub const API_VERSION_V1: &str = "agnx/v1";
 pub const KIND_AGENT: &str = "Agent";
+/// Namespace of agents directly under the agents directory.
+pub const DEFAULT_NAMESPACE: &str = "default";
 
+mod bundle;
+mod cache;
+mod deploy;
+mod diagnostic;
 mod error;
+mod extends;
+mod fields;
+mod ignore;
+mod migrate;
 mod provider;
+mod revision;
+mod schema;
+mod selector;
+mod skill;
+mod source_map;
 mod spec;
 mod store;
+mod template;
+mod tool;
+mod version;
+mod watch;
 
-pub use store::{AgentStore, log_scan_warnings, resolve_agents_dir};
+pub use deploy::{DeployError, DeployStatus};
+pub use diagnostic::{Diagnostic, DiagnosticCode, Severity};
+pub use fields::check_model_aliases;
+pub use migrate::{MigrateError, migrate};
+pub use provider::Provider;
+pub use schema::agent_schema;
+pub use selector::LabelSelector;
+pub use spec::{AgentSpec, DEFAULT_MAX_DEPTH, LoadOptions, ModelConfig, Prompt, PromptSource};
+pub use store::{
+    AgentRegistry, AgentScanWarning, AgentStore, log_scan_warnings, resolve_agents_dir,
+};
+pub use template::RenderContext;
+pub use watch::watch_agents_dir;
@@ -1,4 +1,6 @@
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::Deserialize;
+use std::borrow::Cow;
 
 /// Supported model providers.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -22,24 +24,73 @@ impl Provider {
     }
 }
 
+/// Optional `spec.model` parameters that not every provider accepts, and the providers
+/// that do. Parameters missing from this table are accepted by all of them.
+const PARAMETER_SUPPORT: &[(&str, &[&str])] = &[
+    ("top_k", &["anthropic", "ollama", "openrouter"]),
+    ("seed", &["ollama", "openai", "openrouter"]),
+    ("presence_penalty", &["ollama", "openai", "openrouter"]),
+    ("frequency_penalty", &["ollama", "openai", "openrouter"]),
+    ("reasoning_effort", &["openai", "openrouter"]),
+];
+
+impl Provider {
+    /// Whether requests to this provider can carry the `spec.model` parameter.
+    /// Custom providers are assumed to accept everything.
+    pub fn supports(&self, parameter: &str) -> bool {
+        if let Provider::Other(_) = self {
+            return true;
+        }
+        Self::supporting(parameter).is_none_or(|providers| providers.contains(&self.as_str()))
+    }
+
+    /// The built-in providers that accept `parameter`, or `None` if all of them do.
+    pub fn supporting(parameter: &str) -> Option<&'static [&'static str]> {
+        PARAMETER_SUPPORT
+            .iter()
+            .find(|(name, _)| *name == parameter)
+            .map(|(_, providers)| *providers)
+    }
+}
+
 impl std::fmt::Display for Provider {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.as_str())
     }
 }
 
+impl From<&str> for Provider {
+    fn from(s: &str) -> Self {
+        match s {
+            "anthropic" => Provider::Anthropic,
+            "ollama" => Provider::Ollama,
+            "openai" => Provider::OpenAI,
+            "openrouter" => Provider::OpenRouter,
+            other => Provider::Other(other.to_string()),
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for Provider {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        Ok(match s.as_str() {
-            "anthropic" => Provider::Anthropic,
-            "ollama" => Provider::Ollama,
-            "openai" => Provider::OpenAI,
-            "openrouter" => Provider::OpenRouter,
-            other => Provider::Other(other.to_string()),
+        Ok(Provider::from(s.as_str()))
+    }
+}
+
+impl JsonSchema for Provider {
+    fn schema_name() -> Cow<'static, str> {
+        "Provider".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "description": "Model provider. Unknown names are accepted as custom providers.",
+            "examples": ["anthropic", "ollama", "openai", "openrouter"],
         })
     }
 }
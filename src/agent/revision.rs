@@ -0,0 +1,124 @@
+//! Revisions: which exact definition an agent was loaded from.
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+/// A content hash over `agent.yaml`, its resolved model, its `extends` parents and every
+/// file it references, with the time it was loaded.
+///
+/// Reloading unchanged files gives the same hash, so it can be used as a strong `ETag`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    /// Hex-encoded SHA-256.
+    pub hash: String,
+    pub loaded_at: DateTime<Utc>,
+}
+
+impl Revision {
+    /// The revision as a strong HTTP entity tag, including the quotes.
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.hash)
+    }
+
+    /// Whether an `If-Match` header value lists this revision. `*` matches any revision;
+    /// weak tags never match.
+    pub fn matches(&self, header: &str) -> bool {
+        self.listed_in(header, |tag| tag)
+    }
+
+    /// Whether an `If-None-Match` header value lists this revision, comparing weak tags
+    /// (`W/"..."`) by their value.
+    pub fn matches_weak(&self, header: &str) -> bool {
+        self.listed_in(header, |tag| tag.strip_prefix("W/").unwrap_or(tag))
+    }
+
+    fn listed_in(&self, header: &str, normalize: fn(&str) -> &str) -> bool {
+        let etag = self.etag();
+        header
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || normalize(tag) == etag)
+    }
+}
+
+/// Builds a [`Revision`] from the files an agent is loaded from.
+pub(super) struct RevisionHasher(Sha256);
+
+impl RevisionHasher {
+    pub fn new() -> Self {
+        Self(Sha256::new())
+    }
+
+    /// Add one file. `name` is part of the hash, so renaming a referenced file or moving
+    /// content between files gives a new revision.
+    pub fn add(&mut self, name: &str, content: Option<&[u8]>) {
+        self.0.update((name.len() as u64).to_le_bytes());
+        self.0.update(name.as_bytes());
+        match content {
+            Some(content) => {
+                self.0.update([1]);
+                self.0.update((content.len() as u64).to_le_bytes());
+                self.0.update(content);
+            }
+            // A missing file still counts, so creating it changes the revision.
+            None => self.0.update([0]),
+        }
+    }
+
+    pub fn finish(self) -> Revision {
+        let hash = self
+            .0
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Revision {
+            hash,
+            loaded_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn revision(files: &[(&str, Option<&str>)]) -> Revision {
+        let mut hasher = RevisionHasher::new();
+        for (name, content) in files {
+            hasher.add(name, content.map(str::as_bytes));
+        }
+        hasher.finish()
+    }
+
+    #[test]
+    fn hash_depends_on_names_and_contents() {
+        let base = revision(&[("agent.yaml", Some("a")), ("P.md", Some("b"))]);
+        assert_eq!(base.hash.len(), 64);
+        assert_eq!(
+            base.hash,
+            revision(&[("agent.yaml", Some("a")), ("P.md", Some("b"))]).hash
+        );
+        for other in [
+            revision(&[("agent.yaml", Some("a")), ("P.md", Some("c"))]),
+            revision(&[("agent.yaml", Some("a")), ("Q.md", Some("b"))]),
+            revision(&[("agent.yaml", Some("ab")), ("P.md", Some(""))]),
+            revision(&[("agent.yaml", Some("a")), ("P.md", None)]),
+        ] {
+            assert_ne!(base.hash, other.hash);
+        }
+    }
+
+    #[test]
+    fn matches_entity_tag_lists() {
+        let rev = revision(&[("agent.yaml", Some("a"))]);
+        let etag = rev.etag();
+        assert!(rev.matches(&etag));
+        assert!(rev.matches(&format!("\"other\", {etag}")));
+        assert!(rev.matches("*"));
+        assert!(!rev.matches("\"other\""));
+        assert!(!rev.matches(&rev.hash));
+        assert!(!rev.matches(&format!("W/{etag}")));
+        assert!(rev.matches_weak(&format!("\"other\", W/{etag}")));
+    }
+}
@@ -0,0 +1,47 @@
+//! JSON Schema for `agent.yaml`, generated from the raw document types.
+
+use schemars::JsonSchema;
+use serde_json::{Value, json};
+
+use super::extends::EXTENDS_KEY;
+use super::version::{RawAgentSpec, V1Alpha1Spec, V1Beta1Spec};
+
+/// `agent.yaml` in any supported apiVersion.
+#[allow(dead_code)] // Only used for its schema.
+#[derive(JsonSchema)]
+#[serde(untagged)]
+enum AgentDocument {
+    Latest(RawAgentSpec),
+    V1Beta1(V1Beta1Spec),
+    V1Alpha1(V1Alpha1Spec),
+}
+
+/// JSON Schema describing `agent.yaml`.
+///
+/// Documents that use `extends` may leave out anything their parents provide, so for
+/// them only `extends` itself is checked.
+pub fn agent_schema() -> Value {
+    let mut schema = schemars::schema_for!(AgentDocument).to_value();
+    let versions = schema
+        .as_object_mut()
+        .and_then(|root| root.remove("anyOf"))
+        .unwrap_or_default();
+    schema["title"] = json!("Agnx agent definition (agent.yaml)");
+    schema["anyOf"] = json!([
+        {
+            "type": "object",
+            "required": [EXTENDS_KEY],
+            "properties": {
+                EXTENDS_KEY: {
+                    "description": "Parent agent to inherit from: a path to its directory or its metadata.name.",
+                    "type": "string",
+                },
+            },
+        },
+    ]);
+    if let (Some(alternatives), Value::Array(versions)) = (schema["anyOf"].as_array_mut(), versions)
+    {
+        alternatives.extend(versions);
+    }
+    schema
+}
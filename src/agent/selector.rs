@@ -0,0 +1,221 @@
+//! Kubernetes-style label selectors over `metadata.labels`.
+//!
+//! A selector is a comma-separated list of requirements, all of which must hold:
+//!
+//! - `key=value` or `key==value`, and `key!=value` (which also matches agents without
+//!   the label)
+//! - `key in (a,b)` and `key notin (a,b)` (which also matches agents without the label)
+//! - `key` (the label is set) and `!key` (it is not)
+
+use std::collections::HashMap;
+
+/// A parsed label selector. The empty selector matches every agent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelSelector {
+    requirements: Vec<Requirement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Requirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    In(String, Vec<String>),
+    NotIn(String, Vec<String>),
+    Exists(String),
+    NotExists(String),
+}
+
+/// A selector that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorError {
+    /// The requirement that failed to parse.
+    pub requirement: String,
+    pub message: &'static str,
+}
+
+impl std::fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid label selector requirement '{}': {}",
+            self.requirement, self.message
+        )
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+impl LabelSelector {
+    pub fn parse(selector: &str) -> Result<Self, SelectorError> {
+        let requirements = split_requirements(selector)
+            .into_iter()
+            .map(str::trim)
+            .filter(|term| !term.is_empty())
+            .map(parse_requirement)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { requirements })
+    }
+
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.requirements.iter().all(|requirement| {
+            let value = |key: &String| labels.get(key).map(String::as_str);
+            match requirement {
+                Requirement::Equals(key, expected) => value(key) == Some(expected),
+                Requirement::NotEquals(key, expected) => value(key) != Some(expected),
+                Requirement::In(key, values) => {
+                    value(key).is_some_and(|v| values.iter().any(|e| e == v))
+                }
+                Requirement::NotIn(key, values) => {
+                    value(key).is_none_or(|v| values.iter().all(|e| e != v))
+                }
+                Requirement::Exists(key) => labels.contains_key(key),
+                Requirement::NotExists(key) => !labels.contains_key(key),
+            }
+        })
+    }
+}
+
+/// Split on commas that are not inside a `( ... )` value list.
+fn split_requirements(selector: &str) -> Vec<&str> {
+    let mut terms = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in selector.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                terms.push(&selector[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    terms.push(&selector[start..]);
+    terms
+}
+
+fn parse_requirement(term: &str) -> Result<Requirement, SelectorError> {
+    let error = |message| SelectorError {
+        requirement: term.to_string(),
+        message,
+    };
+    let key = |key: &str| {
+        let key = key.trim();
+        if is_valid_key(key) {
+            Ok(key.to_string())
+        } else {
+            Err(error(
+                "label keys may only contain letters, digits, '-', '_', '.' and '/'",
+            ))
+        }
+    };
+    let value = |value: &str| {
+        let value = value.trim();
+        if value.chars().all(is_value_char) {
+            Ok(value.to_string())
+        } else {
+            Err(error(
+                "label values may only contain letters, digits, '-', '_' and '.'",
+            ))
+        }
+    };
+
+    if let Some(open) = term.find('(') {
+        let Some(list) = term[open + 1..].strip_suffix(')') else {
+            return Err(error("expected a value list ending in ')'"));
+        };
+        let mut words = term[..open].split_whitespace();
+        let (Some(name), Some(operator), None) = (words.next(), words.next(), words.next()) else {
+            return Err(error("expected 'key in (...)' or 'key notin (...)'"));
+        };
+        let values = list.split(',').map(value).collect::<Result<Vec<_>, _>>()?;
+        return match operator {
+            "in" => Ok(Requirement::In(key(name)?, values)),
+            "notin" => Ok(Requirement::NotIn(key(name)?, values)),
+            _ => Err(error("expected 'in' or 'notin' before the value list")),
+        };
+    }
+    if let Some((name, expected)) = term.split_once("!=") {
+        return Ok(Requirement::NotEquals(key(name)?, value(expected)?));
+    }
+    if let Some((name, expected)) = term.split_once('=') {
+        let expected = expected.strip_prefix('=').unwrap_or(expected);
+        return Ok(Requirement::Equals(key(name)?, value(expected)?));
+    }
+    match term.strip_prefix('!') {
+        Some(name) => Ok(Requirement::NotExists(key(name)?)),
+        None => Ok(Requirement::Exists(key(term)?)),
+    }
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| is_value_char(c) || c == '/')
+}
+
+fn is_value_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn matches_all_operators() {
+        let selector =
+            LabelSelector::parse("tier=premium, domain!=legal,env in (prod, staging),!beta")
+                .unwrap();
+        assert!(selector.matches(&labels(&[("tier", "premium"), ("env", "prod")])));
+        assert!(selector.matches(&labels(&[
+            ("tier", "premium"),
+            ("domain", "support"),
+            ("env", "staging"),
+        ])));
+        assert!(!selector.matches(&labels(&[
+            ("tier", "premium"),
+            ("domain", "legal"),
+            ("env", "prod")
+        ])));
+        assert!(!selector.matches(&labels(&[("tier", "premium"), ("env", "dev")])));
+        assert!(!selector.matches(&labels(&[
+            ("tier", "premium"),
+            ("env", "prod"),
+            ("beta", "")
+        ])));
+        assert!(!selector.matches(&labels(&[("tier", "free"), ("env", "prod")])));
+
+        let selector = LabelSelector::parse("team,tier==free,env notin (prod)").unwrap();
+        assert!(selector.matches(&labels(&[("team", "core"), ("tier", "free")])));
+        assert!(!selector.matches(&labels(&[("tier", "free")])));
+        assert!(!selector.matches(&labels(&[
+            ("team", "core"),
+            ("tier", "free"),
+            ("env", "prod")
+        ])));
+
+        assert!(LabelSelector::parse("").unwrap().matches(&HashMap::new()));
+    }
+
+    #[test]
+    fn rejects_malformed_requirements() {
+        for selector in [
+            "env in (prod",
+            "env within (prod)",
+            "in (prod)",
+            "=x",
+            "tier=a b",
+            "a b",
+        ] {
+            let error = LabelSelector::parse(selector).unwrap_err();
+            assert_eq!(error.requirement, selector, "{selector}");
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Skills: directories with a `SKILL.md`, found under `spec.skills_dir` or listed in
+//! `spec.skills`.
+//!
+//! `SKILL.md` may start with YAML frontmatter between `---` lines (optionally after a
+//! Markdown title) declaring `name`, `description`, `version`, `allowed_tools` and
+//! `triggers`. A skill's ID is its frontmatter `name`, or else its directory name.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::error::AgentLoadWarning;
+use super::revision::RevisionHasher;
+use super::source_map::{SourceMap, SourceSpan};
+use super::version::RawSkill;
+
+pub const SKILL_FILE: &str = "SKILL.md";
+/// `spec.skills_dir` when it is not set.
+pub const DEFAULT_SKILLS_DIR: &str = "./skills/";
+/// Subdirectories of a skill that are listed in its inventory.
+const RESOURCE_DIRS: [&str; 3] = ["references", "scripts", "assets"];
+
+/// A skill available to an agent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skill {
+    /// Frontmatter `name`, or the directory name.
+    pub id: String,
+    pub dir: PathBuf,
+    pub frontmatter: SkillFrontmatter,
+    /// `SKILL.md` without its frontmatter.
+    pub instructions: String,
+    /// Files under `references/`, relative to the skill directory.
+    pub references: Vec<String>,
+    /// Files under `scripts/`, relative to the skill directory.
+    pub scripts: Vec<String>,
+    /// Files under `assets/`, relative to the skill directory.
+    pub assets: Vec<String>,
+}
+
+/// The YAML frontmatter of `SKILL.md`. Other keys (such as `author`) are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct SkillFrontmatter {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    #[serde(default)]
+    pub triggers: Vec<SkillTrigger>,
+}
+
+/// When a skill activates: on input matching `pattern`, or on a cron `schedule`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SkillTrigger {
+    pub pattern: Option<String>,
+    pub schedule: Option<String>,
+}
+
+/// Where skills are looked for, and who is asking.
+pub(super) struct Discovery<'a> {
+    pub agent: &'a str,
+    pub agent_dir: &'a Path,
+    pub manifest: &'a Path,
+    pub source: &'a SourceMap,
+}
+
+impl Discovery<'_> {
+    /// Every skill directly under `skills_dir`, in directory name order, followed by the
+    /// `skills[].path` entries. A directory reached both ways is loaded once; a second
+    /// skill with an ID that is already taken is skipped with a warning.
+    pub fn run(
+        &self,
+        skills_dir: Option<&str>,
+        entries: &[RawSkill],
+        revision: &mut RevisionHasher,
+        warnings: &mut Vec<AgentLoadWarning>,
+    ) -> Vec<Skill> {
+        let mut dirs: Vec<(PathBuf, Option<usize>)> = Vec::new();
+        let root = self
+            .agent_dir
+            .join(skills_dir.unwrap_or(DEFAULT_SKILLS_DIR));
+        match fs::read_dir(&root) {
+            Ok(read) => {
+                let mut found: Vec<PathBuf> = read
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| path.join(SKILL_FILE).is_file())
+                    .collect();
+                found.sort();
+                dirs.extend(found.into_iter().map(|dir| (dir, None)));
+            }
+            // Only a directory that was asked for is expected to exist.
+            Err(e) if skills_dir.is_some() => warnings.push(AgentLoadWarning::MissingFile {
+                agent: self.agent.to_string(),
+                field: "skills_dir",
+                path: root,
+                error: e.to_string(),
+                manifest: self.manifest.to_path_buf(),
+                span: self.source.value_span(&["spec", "skills_dir"]),
+            }),
+            Err(_) => {}
+        }
+        for (index, entry) in entries.iter().enumerate() {
+            if let Some(path) = &entry.path {
+                dirs.push((self.agent_dir.join(path), Some(index)));
+            }
+        }
+
+        let mut seen = Vec::new();
+        let mut ids: HashMap<String, PathBuf> = HashMap::new();
+        let mut skills = Vec::new();
+        for (dir, entry) in dirs {
+            let key = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+
+            let span = entry.and_then(|index| {
+                self.source
+                    .value_span(&["spec", "skills", &index.to_string(), "path"])
+            });
+            let skill_file = dir.join(SKILL_FILE);
+            let text = match fs::read_to_string(&skill_file) {
+                Ok(text) => text,
+                Err(e) => {
+                    warnings.push(self.invalid(&skill_file, e.to_string(), span));
+                    continue;
+                }
+            };
+            revision.add(
+                &skill_file
+                    .strip_prefix(self.agent_dir)
+                    .unwrap_or(&skill_file)
+                    .display()
+                    .to_string(),
+                Some(text.as_bytes()),
+            );
+
+            let (frontmatter, instructions) = match parse(&text) {
+                Ok(parsed) => parsed,
+                Err(error) => {
+                    warnings.push(self.invalid(&skill_file, error, span));
+                    (SkillFrontmatter::default(), text.clone())
+                }
+            };
+            let id = match frontmatter.name.clone() {
+                Some(name) => name,
+                None => dir
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            };
+            if let Some(existing) = ids.get(&id) {
+                warnings.push(AgentLoadWarning::DuplicateSkill {
+                    agent: self.agent.to_string(),
+                    id,
+                    path: skill_file,
+                    existing: existing.join(SKILL_FILE),
+                });
+                continue;
+            }
+            ids.insert(id.clone(), dir.clone());
+
+            let [references, scripts, assets] = RESOURCE_DIRS.map(|sub| inventory(&dir, sub));
+            skills.push(Skill {
+                id,
+                dir,
+                frontmatter,
+                instructions,
+                references,
+                scripts,
+                assets,
+            });
+        }
+        skills
+    }
+
+    fn invalid(&self, path: &Path, error: String, span: Option<SourceSpan>) -> AgentLoadWarning {
+        AgentLoadWarning::InvalidSkill {
+            agent: self.agent.to_string(),
+            path: path.to_path_buf(),
+            error,
+            manifest: self.manifest.to_path_buf(),
+            span,
+        }
+    }
+}
+
+/// Split `SKILL.md` into its frontmatter and the rest. Text without frontmatter is all
+/// instructions.
+fn parse(text: &str) -> Result<(SkillFrontmatter, String), String> {
+    let mut offset = 0;
+    let mut lines = text.split_inclusive('\n');
+    let opening = loop {
+        let Some(line) = lines.next() else {
+            return Ok((SkillFrontmatter::default(), text.to_string()));
+        };
+        let trimmed = line.trim();
+        if trimmed == "---" {
+            break offset;
+        }
+        if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            return Ok((SkillFrontmatter::default(), text.to_string()));
+        }
+        offset += line.len();
+    };
+
+    let start = opening
+        + text[opening..]
+            .find('\n')
+            .map_or(text.len() - opening, |i| i + 1);
+    let mut end = start;
+    for line in text[start..].split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let yaml = &text[start..end];
+            let frontmatter = if yaml.trim().is_empty() {
+                SkillFrontmatter::default()
+            } else {
+                serde_saphyr::from_str(yaml)
+                    .map_err(|e| format!("invalid frontmatter: {}", e.without_snippet()))?
+            };
+            let rest = &text[end + line.len()..];
+            let instructions = format!("{}{}", &text[..opening], rest.trim_start_matches('\n'));
+            return Ok((frontmatter, instructions));
+        }
+        end += line.len();
+    }
+    Err("frontmatter is not closed with '---'".to_string())
+}
+
+/// Files below `dir/sub`, relative to `dir` with `/` separators, sorted.
+fn inventory(dir: &Path, sub: &str) -> Vec<String> {
+    fn walk(dir: &Path, current: &Path, files: &mut Vec<String>) {
+        let Ok(entries) = fs::read_dir(current) else {
+            return;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.is_dir() {
+                walk(dir, &path, files);
+            } else if let Ok(relative) = path.strip_prefix(dir) {
+                let segments: Vec<_> = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect();
+                files.push(segments.join("/"));
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(dir, &dir.join(sub), &mut files);
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontmatter_after_an_optional_title() {
+        let (frontmatter, instructions) = parse(
+            "# Task Extraction
+
+---
+name: task-extraction
+description: Extract tasks
+allowed_tools: [calculator]
+triggers:
+  - pattern: \"extract tasks *\"
+  - schedule: \"0 9 * * 1\"
+---
+
+## Goal
+",
+        )
+        .unwrap();
+        assert_eq!(frontmatter.name.as_deref(), Some("task-extraction"));
+        assert_eq!(frontmatter.allowed_tools, ["calculator"]);
+        assert_eq!(
+            frontmatter.triggers[1].schedule.as_deref(),
+            Some("0 9 * * 1")
+        );
+        assert_eq!(instructions, "# Task Extraction\n\n## Goal\n");
+
+        let (frontmatter, instructions) = parse("Just do it.\n---\n").unwrap();
+        assert_eq!(frontmatter, SkillFrontmatter::default());
+        assert_eq!(instructions, "Just do it.\n---\n");
+
+        assert!(parse("---\nname: x\n").unwrap_err().contains("not closed"));
+        assert!(
+            parse("---\nallowed_tools: calculator\n---\n")
+                .unwrap_err()
+                .starts_with("invalid frontmatter")
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! Line/column lookup for values in a YAML document.
+//!
+//! The typed AAF structs are deserialized without location information. When validation
+//! needs to point at a field, we parse the same text a second time into a lightweight
+//! tree of spanned nodes and look the field up by its key path.
+
+use serde::Serialize;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_saphyr::{Location, Spanned};
+
+/// A 1-indexed region within a source file. The end position is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SourceSpan {
+    pub line: u32,
+    pub column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+/// Spanned view of a YAML document, used to locate fields for diagnostics.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    root: Option<Spanned<Node>>,
+    text: String,
+}
+
+impl SourceMap {
+    /// Parse `text`. Unparseable documents produce an empty map rather than an error,
+    /// since the typed deserialization already reports those.
+    pub fn parse(text: &str) -> Self {
+        Self {
+            root: serde_saphyr::from_str(text).ok(),
+            text: text.to_string(),
+        }
+    }
+
+    /// Whether `text` parsed as YAML.
+    pub fn is_parsed(&self) -> bool {
+        self.root.is_some()
+    }
+
+    /// Location of the value at `path` (e.g. `["spec", "model", "temperature"]`).
+    ///
+    /// Sequence elements are addressed by their index (`"0"`, `"1"`, ...).
+    pub fn value_span(&self, path: &[&str]) -> Option<SourceSpan> {
+        let node = self.lookup(path)?;
+        self.span(&node.referenced)
+    }
+
+    /// Keys of the mapping at `path`, with their locations. Empty if `path` is not a mapping.
+    pub fn keys(&self, path: &[&str]) -> Vec<(&str, Option<SourceSpan>)> {
+        match self.lookup(path).map(|n| &n.value) {
+            Some(Node::Map(entries)) => entries
+                .iter()
+                .map(|(k, _)| (k.value.0.as_str(), self.span(&k.referenced)))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Number of elements in the sequence at `path`; 0 if `path` is not a sequence.
+    pub fn seq_len(&self, path: &[&str]) -> usize {
+        match self.lookup(path).map(|n| &n.value) {
+            Some(Node::Seq(items)) => items.len(),
+            _ => 0,
+        }
+    }
+
+    fn lookup(&self, path: &[&str]) -> Option<&Spanned<Node>> {
+        let mut node = self.root.as_ref()?;
+        for segment in path {
+            node = match &node.value {
+                Node::Map(entries) => entries
+                    .iter()
+                    .find(|(k, _)| k.value.0 == *segment)
+                    .map(|(_, v)| v)?,
+                Node::Seq(items) => items.get(segment.parse::<usize>().ok()?)?,
+                Node::Scalar => return None,
+            };
+        }
+        Some(node)
+    }
+
+    fn span(&self, location: &Location) -> Option<SourceSpan> {
+        if location.line() == 0 {
+            return None;
+        }
+
+        let line = location.line() as u32;
+        let column = location.column() as u32;
+        let (mut end_line, mut end_column) = (line, column);
+        let start = location.span().offset();
+        for c in self.text.chars().skip(start).take(location.span().len()) {
+            if c == '\n' {
+                end_line += 1;
+                end_column = 1;
+            } else {
+                end_column += 1;
+            }
+        }
+
+        Some(SourceSpan {
+            line,
+            column,
+            end_line,
+            end_column,
+        })
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    Map(Vec<(Spanned<Key>, Spanned<Node>)>),
+    Seq(Vec<Spanned<Node>>),
+    Scalar,
+}
+
+/// Mapping key rendered as a string; non-string scalar keys are stringified.
+#[derive(Debug)]
+struct Key(String);
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = Key;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a scalar mapping key")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Key, E> {
+                Ok(Key(v.to_string()))
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> Result<Key, E> {
+                Ok(Key(v.to_string()))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Key, E> {
+                Ok(Key(v.to_string()))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Key, E> {
+                Ok(Key(v.to_string()))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Key, E> {
+                Ok(Key(v.to_string()))
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Key, E> {
+                Ok(Key(String::new()))
+            }
+        }
+
+        deserializer.deserialize_any(KeyVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct NodeVisitor;
+
+        impl<'de> Visitor<'de> for NodeVisitor {
+            type Value = Node;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("any YAML node")
+            }
+
+            fn visit_str<E: de::Error>(self, _: &str) -> Result<Node, E> {
+                Ok(Node::Scalar)
+            }
+
+            fn visit_bool<E: de::Error>(self, _: bool) -> Result<Node, E> {
+                Ok(Node::Scalar)
+            }
+
+            fn visit_i64<E: de::Error>(self, _: i64) -> Result<Node, E> {
+                Ok(Node::Scalar)
+            }
+
+            fn visit_u64<E: de::Error>(self, _: u64) -> Result<Node, E> {
+                Ok(Node::Scalar)
+            }
+
+            fn visit_f64<E: de::Error>(self, _: f64) -> Result<Node, E> {
+                Ok(Node::Scalar)
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Node, E> {
+                Ok(Node::Scalar)
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<Node, E> {
+                Ok(Node::Scalar)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Node::Map(entries))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+                let mut items = Vec::new();
+                while let Some(item) = seq.next_element()? {
+                    items.push(item);
+                }
+                Ok(Node::Seq(items))
+            }
+        }
+
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "apiVersion: agnx/v1alpha1
+metadata:
+  name: my-agent
+spec:
+  model:
+    temperature: 3.5
+  bootstrap:
+    - path: ./SOUL.md
+";
+
+    #[test]
+    fn locates_nested_values() {
+        let map = SourceMap::parse(DOC);
+
+        assert_eq!(
+            map.value_span(&["spec", "model", "temperature"]),
+            Some(SourceSpan {
+                line: 6,
+                column: 18,
+                end_line: 6,
+                end_column: 21,
+            })
+        );
+        assert_eq!(
+            map.value_span(&["spec", "bootstrap", "0", "path"])
+                .map(|s| (s.line, s.column)),
+            Some((8, 13))
+        );
+    }
+
+    #[test]
+    fn lists_keys_and_sequence_lengths() {
+        let map = SourceMap::parse(DOC);
+
+        let keys: Vec<_> = map
+            .keys(&["spec"])
+            .into_iter()
+            .map(|(k, span)| (k, span.map(|s| (s.line, s.column))))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![("model", Some((5, 3))), ("bootstrap", Some((7, 3)))]
+        );
+        assert_eq!(map.seq_len(&["spec", "bootstrap"]), 1);
+        assert!(map.keys(&["apiVersion"]).is_empty());
+    }
+
+    #[test]
+    fn missing_paths_and_invalid_documents_have_no_span() {
+        assert!(
+            SourceMap::parse(DOC)
+                .value_span(&["spec", "nope"])
+                .is_none()
+        );
+        assert!(SourceMap::parse("a: [").value_span(&["a"]).is_none());
+    }
+}
@@ -1,11 +1,29 @@
+use schemars::JsonSchema;
 use serde::Deserialize;
-use std::collections::HashMap;
+use serde::de::DeserializeOwned;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::Path;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 
+use super::diagnostic::{Diagnostic, DiagnosticCode, Severity};
 use super::error::{AgentLoadError, AgentLoadWarning};
+use super::extends::{self, AgentParent, ExtendsError};
+use super::fields::{self, FieldIssue, FieldIssueKind};
 use super::provider::Provider;
-use super::{API_VERSION_V1ALPHA1, KIND_AGENT};
+use super::revision::{Revision, RevisionHasher};
+use super::skill::{self, Skill};
+use super::source_map::{SourceMap, SourceSpan};
+use super::template::{self, RenderContext};
+use super::tool::{self, ToolSpec};
+use super::version::{
+    self, ApiVersion, RawAgentSpec, RawModel, RawPrompt, V1Alpha1Spec, V1Beta1Spec,
+};
+use super::{DEFAULT_NAMESPACE, KIND_AGENT};
+use crate::interpolate::{
+    InterpolationError, InterpolationErrorKind, NO_INTERPOLATE_DIRECTIVE, interpolate_env,
+};
 
 /// An agent specification loaded from an agent.yaml file.
 #[derive(Debug, Clone)]
@@ -13,15 +31,70 @@ pub struct AgentSpec {
     pub api_version: String,
     pub kind: String,
     pub metadata: AgentMetadata,
+    /// `metadata.namespace`, or the namespace [`AgentStore::scan`](super::AgentStore::scan)
+    /// derives from the agent's directory.
+    pub namespace: String,
     pub model: ModelConfig,
-    pub system_prompt: Option<String>,
-    pub instructions: Option<String>,
+    pub system_prompt: Option<Prompt>,
+    pub instructions: Option<Prompt>,
+    /// Files injected into context on the first turn of a session.
+    pub bootstrap: Vec<BootstrapFile>,
+    /// Skills from `skills_dir` and `skills`, in discovery order. Empty for agents loaded
+    /// without their referenced files.
+    pub skills: Vec<Skill>,
+    /// `spec.tools`, in declaration order.
+    pub tools: Vec<ToolSpec>,
+    /// Inheritance chain from `extends:`, nearest parent first.
+    pub parents: Vec<AgentParent>,
+    /// Which files this agent was loaded from. Agents loaded without their referenced
+    /// files (the lazy-loading index) only hash `agent.yaml` and its parents.
+    pub revision: Revision,
+}
+
+/// A system prompt or instructions block, with the form it was declared in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prompt {
+    pub source: PromptSource,
+    /// Prompt text, or `None` if the referenced file could not be read.
+    pub content: Option<String>,
+}
+
+/// A `spec.bootstrap` entry and its loaded contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootstrapFile {
+    /// Path relative to the agent directory, as written.
+    pub path: String,
+    pub max_tokens: Option<u32>,
+    pub required: bool,
+    /// File contents, truncated to `max_tokens`, or `None` if the file could not be read.
+    pub content: Option<String>,
+    /// Size of the file on disk in bytes, before truncation.
+    pub size: Option<u64>,
+    pub truncated: bool,
+}
+
+/// Where a prompt's text comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptSource {
+    /// Written directly in agent.yaml (`{inline: ...}`).
+    Inline,
+    /// Path relative to the agent directory, as written (`./X.md` or `{file: ./X.md}`).
+    File(String),
+}
+
+/// Whether loading reads the files an agent references.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Files {
+    Read,
+    Skip,
 }
 
 /// Agent metadata from the AAF spec.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct AgentMetadata {
     pub name: String,
+    /// Namespace to serve the agent under; derived from its directory when unset.
+    pub namespace: Option<String>,
     pub description: Option<String>,
     pub version: Option<String>,
     #[serde(default)]
@@ -29,7 +102,7 @@ pub struct AgentMetadata {
 }
 
 /// Model configuration from the AAF spec.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct ModelConfig {
     pub provider: Provider,
     pub name: String,
@@ -46,109 +119,957 @@ pub struct ModelConfig {
     #[serde(default, alias = "max_tokens")]
     pub max_output_tokens: Option<u32>,
     pub base_url: Option<String>,
+    /// Nucleus sampling: only tokens within this cumulative probability (0-1).
+    pub top_p: Option<f32>,
+    /// Only sample from this many most likely tokens.
+    pub top_k: Option<u32>,
+    /// Sequences that end generation when produced.
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// Seed for deterministic sampling, where the provider supports it.
+    pub seed: Option<i64>,
+    /// Penalty (-2 to 2) for tokens that already appeared at all.
+    pub presence_penalty: Option<f32>,
+    /// Penalty (-2 to 2) proportional to how often tokens already appeared.
+    pub frequency_penalty: Option<f32>,
+    /// How much reasoning models think before answering.
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Provider-specific request parameters, passed through as-is.
+    #[serde(default)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl ModelConfig {
+    /// A model with every optional parameter left to the provider's defaults.
+    pub fn new(provider: Provider, name: impl Into<String>) -> Self {
+        Self {
+            provider,
+            name: name.into(),
+            temperature: None,
+            max_input_tokens: None,
+            max_output_tokens: None,
+            base_url: None,
+            top_p: None,
+            top_k: None,
+            stop: Vec::new(),
+            seed: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            reasoning_effort: None,
+            extra: HashMap::new(),
+        }
+    }
+}
+
+/// `spec.model.reasoning_effort` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
 }
 
-/// Raw YAML structure for parsing agent.yaml files.
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct RawAgentSpec {
-    api_version: String,
-    kind: String,
-    metadata: AgentMetadata,
-    spec: RawAgentSpecBody,
+impl ReasoningEffort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReasoningEffort::Minimal => "minimal",
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+}
+
+/// Settings from `agnx.yaml` that affect how agent definitions are interpreted.
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    /// Named model aliases that `spec.model` may refer to.
+    pub models: HashMap<String, ModelConfig>,
+    /// Treat unknown fields as errors rather than warnings.
+    pub strict: bool,
+    /// How many directory levels below the agents directory are searched for agents.
+    pub max_depth: usize,
+    /// Load agents on first access and keep at most this many in memory, instead of
+    /// loading every agent up front.
+    pub lazy_cache_size: Option<NonZeroUsize>,
+    /// Directory of agents deployed through the admin API. Their definitions are not
+    /// trusted: environment variables are not expanded, file references must stay in the
+    /// agent's directory and `extends` may only name other agents in this directory.
+    pub sandbox_root: Option<PathBuf>,
 }
 
-#[derive(Debug, Deserialize)]
-struct RawAgentSpecBody {
-    model: ModelConfig,
-    system_prompt: Option<String>,
-    instructions: Option<String>,
+/// Default for [`LoadOptions::max_depth`]: enough for `agents/<team>/<group>/<agent>/`.
+pub const DEFAULT_MAX_DEPTH: usize = 3;
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            models: HashMap::new(),
+            strict: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            lazy_cache_size: None,
+            sandbox_root: None,
+        }
+    }
 }
 
 impl AgentSpec {
     /// Load an agent and return non-fatal warnings (e.g., missing referenced markdown files).
+    ///
+    /// All validation problems are collected and returned together as diagnostics, so a
+    /// single run reports every error in the file.
     pub fn load_with_warnings(
         agent_dir: &Path,
+        options: &LoadOptions,
+    ) -> Result<(Self, Vec<AgentLoadWarning>), AgentLoadError> {
+        Self::load_with_lookup(agent_dir, options, &|_| None)
+    }
+
+    /// Like [`AgentSpec::load_with_warnings`], resolving `extends: <name>` through `lookup`,
+    /// which maps agent names to their directories.
+    pub fn load_with_lookup(
+        agent_dir: &Path,
+        options: &LoadOptions,
+        lookup: &dyn Fn(&str) -> Option<PathBuf>,
+    ) -> Result<(Self, Vec<AgentLoadWarning>), AgentLoadError> {
+        Self::load_inner(agent_dir, options, lookup, Files::Read)
+    }
+
+    /// Load and validate `agent.yaml` without keeping the files it references.
+    ///
+    /// The files are only read to compute the same revision as a full load. Prompts and
+    /// bootstrap files are listed without content, skills are left out, and problems
+    /// with them (missing files, unknown template variables) are not reported. Used to index
+    /// agents when [`LoadOptions::lazy_cache_size`] is set.
+    pub(super) fn load_manifest(
+        agent_dir: &Path,
+        options: &LoadOptions,
+        lookup: &dyn Fn(&str) -> Option<PathBuf>,
+    ) -> Result<(Self, Vec<AgentLoadWarning>), AgentLoadError> {
+        Self::load_inner(agent_dir, options, lookup, Files::Skip)
+    }
+
+    /// Load an agent from `agent.yaml` contents that are not on disk (yet), as if they
+    /// were in `agent_dir`.
+    pub(super) fn load_from_str(
+        agent_dir: &Path,
+        yaml_content: &str,
+        options: &LoadOptions,
+        lookup: &dyn Fn(&str) -> Option<PathBuf>,
+    ) -> Result<(Self, Vec<AgentLoadWarning>), AgentLoadError> {
+        Self::load_text(agent_dir, yaml_content, options, lookup, Files::Read)
+    }
+
+    fn load_inner(
+        agent_dir: &Path,
+        options: &LoadOptions,
+        lookup: &dyn Fn(&str) -> Option<PathBuf>,
+        files: Files,
     ) -> Result<(Self, Vec<AgentLoadWarning>), AgentLoadError> {
         let yaml_path = agent_dir.join("agent.yaml");
-        let yaml_content = fs::read_to_string(&yaml_path)?;
+        let yaml_content = fs::read_to_string(&yaml_path).map_err(|error| AgentLoadError::Io {
+            path: yaml_path.clone(),
+            error,
+        })?;
+        Self::load_text(agent_dir, &yaml_content, options, lookup, files)
+    }
 
-        let raw: RawAgentSpec =
-            serde_saphyr::from_str(&yaml_content).map_err(AgentLoadError::Yaml)?;
+    fn load_text(
+        agent_dir: &Path,
+        yaml_content: &str,
+        options: &LoadOptions,
+        lookup: &dyn Fn(&str) -> Option<PathBuf>,
+        files: Files,
+    ) -> Result<(Self, Vec<AgentLoadWarning>), AgentLoadError> {
+        let yaml_path = agent_dir.join("agent.yaml");
+        // The revision covers the file as written, so it does not depend on (or reveal)
+        // the values of environment variables.
+        let on_disk = yaml_content;
+        let sandbox = options
+            .sandbox_root
+            .as_deref()
+            .filter(|root| agent_dir.starts_with(root));
+        let yaml_content = match sandbox {
+            Some(_) => Cow::Borrowed(yaml_content),
+            None => interpolate_env(yaml_content)
+                .map_err(|e| AgentLoadError::Validation(vec![env_diagnostic(&yaml_path, &e)]))?,
+        };
 
-        // Validate apiVersion
-        if raw.api_version != API_VERSION_V1ALPHA1 {
-            return Err(AgentLoadError::Validation(format!(
-                "unsupported apiVersion '{}', expected '{API_VERSION_V1ALPHA1}'",
-                raw.api_version
-            )));
-        }
+        let parse_error = |e| AgentLoadError::Validation(vec![yaml_diagnostic(&yaml_path, &e)]);
+        let mut doc: serde_json::Value =
+            serde_saphyr::from_str(&yaml_content).map_err(parse_error)?;
+        let source = source_map(on_disk, &yaml_content);
 
-        // Validate kind
-        if raw.kind != KIND_AGENT {
-            return Err(AgentLoadError::Validation(format!(
-                "unsupported kind '{}', expected '{KIND_AGENT}'",
-                raw.kind
-            )));
+        if sandbox.is_some() {
+            let escaping: Vec<Diagnostic> = extends::escaping_references(&mut doc)
+                .into_iter()
+                .map(|(keys, path)| {
+                    let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+                    Diagnostic::error(
+                        DiagnosticCode::PathOutsideAgent,
+                        &yaml_path,
+                        format!("'{path}' is outside the agent directory"),
+                    )
+                    .with_span(source.value_span(&keys))
+                    .with_hint("deployed agents can only reference files in their own directory")
+                })
+                .collect();
+            if !escaping.is_empty() {
+                return Err(AgentLoadError::Validation(escaping));
+            }
         }
 
+        let extends_error = |e: ExtendsError| {
+            let code = match e {
+                ExtendsError::Cycle(_) => DiagnosticCode::InheritanceCycle,
+                _ => DiagnosticCode::InvalidExtends,
+            };
+            AgentLoadError::Validation(vec![
+                Diagnostic::error(code, &yaml_path, e.to_string())
+                    .with_span(source.value_span(&[extends::EXTENDS_KEY])),
+            ])
+        };
+        let (doc, parents, merged) = match extends::take_extends(&mut doc).map_err(extends_error)? {
+            None => (doc, Vec::new(), false),
+            Some(reference) => {
+                let (merged, parents) =
+                    extends::resolve(doc, reference, agent_dir, lookup, sandbox)
+                        .map_err(extends_error)?;
+                (merged, parents, true)
+            }
+        };
+
+        let api_version = version::detect(&doc).map_err(|e| {
+            AgentLoadError::Validation(vec![
+                Diagnostic::error(
+                    DiagnosticCode::UnsupportedApiVersion,
+                    &yaml_path,
+                    e.to_string(),
+                )
+                .with_span(source.value_span(&["apiVersion"]))
+                .with_hint(format!(
+                    "supported versions: {}",
+                    version::supported_versions()
+                )),
+            ])
+        })?;
+
         let mut warnings = Vec::new();
+        let mut removed = Vec::new();
+        let agent_name = doc
+            .pointer("/metadata/name")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        for legacy in version::legacy_fields(api_version, &doc, &source) {
+            match legacy.removed_in {
+                Some(version) => removed.push(
+                    Diagnostic::error(
+                        DiagnosticCode::RemovedField,
+                        &yaml_path,
+                        format!("'{}' was removed in {version}", legacy.field),
+                    )
+                    .with_span(legacy.span)
+                    .with_hint(format!(
+                        "use {} instead, or run `agnx migrate`",
+                        legacy.replacement
+                    )),
+                ),
+                None => warnings.push(AgentLoadWarning::DeprecatedField {
+                    agent: agent_name.clone(),
+                    field: legacy.field,
+                    replacement: legacy.replacement,
+                    deprecated_in: legacy.deprecated_in.as_str(),
+                    manifest: yaml_path.clone(),
+                    span: legacy.span,
+                }),
+            }
+        }
+        if !removed.is_empty() {
+            return Err(AgentLoadError::Validation(removed));
+        }
+
+        let document = if merged {
+            Document::Merged(doc)
+        } else {
+            Document::Text(&yaml_content)
+        };
+        let raw: RawAgentSpec = match api_version {
+            ApiVersion::V1Alpha1 => document.parse::<V1Alpha1Spec>(&yaml_path)?.into(),
+            ApiVersion::V1Beta1 => document.parse::<V1Beta1Spec>(&yaml_path)?.into(),
+            ApiVersion::V1 => document.parse::<RawAgentSpec>(&yaml_path)?,
+        };
+
+        let mut errors = Vec::new();
+        let mut issues: Vec<(PathBuf, FieldIssue)> = fields::check(&source)
+            .into_iter()
+            .map(|issue| (yaml_path.clone(), issue))
+            .collect();
+        if options.strict {
+            // Parents are merged into this agent, so their unknown fields are its too.
+            for parent in &parents {
+                let parent_path = parent.dir.join("agent.yaml");
+                let Ok(text) = fs::read_to_string(&parent_path) else {
+                    continue;
+                };
+                let expanded = interpolate_env(&text).unwrap_or(Cow::Borrowed(&text));
+                issues.extend(
+                    fields::check(&source_map(&text, &expanded))
+                        .into_iter()
+                        .filter(|issue| matches!(issue.kind, FieldIssueKind::Unknown { .. }))
+                        .map(|issue| (parent_path.clone(), issue)),
+                );
+            }
+        }
+        for (manifest, issue) in issues {
+            let warning = match issue.kind {
+                FieldIssueKind::Unknown { suggestion } => AgentLoadWarning::UnknownField {
+                    agent: raw.metadata.name.clone(),
+                    field: issue.field,
+                    suggestion,
+                    manifest,
+                    span: issue.span,
+                },
+                FieldIssueKind::Unsupported => AgentLoadWarning::UnsupportedField {
+                    agent: raw.metadata.name.clone(),
+                    field: issue.field,
+                    manifest,
+                    span: issue.span,
+                },
+            };
+            if options.strict && matches!(warning, AgentLoadWarning::UnknownField { .. }) {
+                let mut error = warning.to_diagnostic();
+                error.severity = Severity::Error;
+                errors.push(error);
+            } else {
+                warnings.push(warning);
+            }
+        }
+
+        let model = match resolve_model(&raw.spec.model, options) {
+            Ok(model) => Some(model),
+            Err(error) => {
+                errors.push(error.at(&yaml_path, &source));
+                None
+            }
+        };
+        errors.extend(validate(&raw, model.as_ref(), &yaml_path, &source));
+        let tools = tool::resolve(
+            &raw.spec.tools,
+            agent_dir,
+            files == Files::Read,
+            &yaml_path,
+            &source,
+        )
+        .unwrap_or_else(|tool_errors| {
+            errors.extend(tool_errors);
+            Vec::new()
+        });
+        let Some(model) = model.filter(|_| errors.is_empty()) else {
+            return Err(AgentLoadError::Validation(errors));
+        };
+        warnings.extend(unsupported_parameters(
+            &raw.metadata.name,
+            &model,
+            &yaml_path,
+            &source,
+        ));
+
+        let mut revision = RevisionHasher::new();
+        revision.add("agent.yaml", Some(on_disk.as_bytes()));
+        revision.add("model", Some(&model_revision_bytes(&model)));
+        for (index, parent) in parents.iter().enumerate() {
+            let text = fs::read(parent.dir.join("agent.yaml")).ok();
+            revision.add(&format!("extends[{index}]"), text.as_deref());
+        }
+        for tool in &tools {
+            if let ToolSpec::Cli { readme, .. } = tool {
+                revision.add(readme, fs::read(agent_dir.join(readme)).ok().as_deref());
+            }
+        }
 
-        // Load system_prompt markdown if specified
-        let system_prompt = if let Some(ref path) = raw.spec.system_prompt {
-            let full_path = agent_dir.join(path);
-            match fs::read_to_string(&full_path) {
+        let mut load_prompt = |field: &'static str, prompt: Option<RawPrompt>| {
+            let path = match prompt? {
+                RawPrompt::Inline(text) => {
+                    return Some(Prompt {
+                        source: PromptSource::Inline,
+                        content: Some(text),
+                    });
+                }
+                RawPrompt::File(path) => path,
+            };
+            let full_path = agent_dir.join(&path);
+            let read = fs::read_to_string(&full_path);
+            revision.add(&path, read.as_ref().ok().map(String::as_bytes));
+            if files == Files::Skip {
+                return Some(Prompt {
+                    source: PromptSource::File(path),
+                    content: None,
+                });
+            }
+            let content = match read {
                 Ok(content) => Some(content),
                 Err(e) => {
                     warnings.push(AgentLoadWarning::MissingFile {
                         agent: raw.metadata.name.clone(),
-                        field: "system_prompt",
+                        field,
                         path: full_path,
                         error: e.to_string(),
+                        manifest: yaml_path.clone(),
+                        span: source
+                            .value_span(&["spec", field, "file"])
+                            .or_else(|| source.value_span(&["spec", field])),
                     });
                     None
                 }
-            }
-        } else {
-            None
+            };
+            Some(Prompt {
+                source: PromptSource::File(path),
+                content,
+            })
         };
 
-        // Load instructions markdown if specified
-        let instructions = if let Some(ref path) = raw.spec.instructions {
-            let full_path = agent_dir.join(path);
-            match fs::read_to_string(&full_path) {
-                Ok(content) => Some(content),
+        let system_prompt = load_prompt("system_prompt", raw.spec.system_prompt);
+        let instructions = load_prompt("instructions", raw.spec.instructions);
+
+        if let Some(Prompt {
+            source: prompt_source,
+            content: Some(content),
+        }) = &instructions
+        {
+            for unknown in template::unknown_variables(content) {
+                let (path, span) = match prompt_source {
+                    PromptSource::File(path) => (
+                        agent_dir.join(path),
+                        Some(SourceSpan {
+                            line: unknown.line,
+                            column: unknown.column,
+                            end_line: unknown.line,
+                            end_column: unknown.column + unknown.name.len() as u32 + 3,
+                        }),
+                    ),
+                    PromptSource::Inline => (
+                        yaml_path.clone(),
+                        source.value_span(&["spec", "instructions", "inline"]),
+                    ),
+                };
+                warnings.push(AgentLoadWarning::UnknownTemplateVariable {
+                    agent: raw.metadata.name.clone(),
+                    field: "instructions",
+                    variable: unknown.name,
+                    path,
+                    span,
+                });
+            }
+        }
+
+        let mut missing_required = Vec::new();
+        let mut bootstrap = Vec::with_capacity(raw.spec.bootstrap.len());
+        for (index, entry) in raw.spec.bootstrap.into_iter().enumerate() {
+            let full_path = agent_dir.join(&entry.path);
+            let span = source.value_span(&["spec", "bootstrap", &index.to_string(), "path"]);
+            let read = fs::read_to_string(&full_path);
+            revision.add(&entry.path, read.as_ref().ok().map(String::as_bytes));
+            let read = match files {
+                Files::Read => read.map(Some),
+                Files::Skip => Ok(None),
+            };
+            let (content, size, truncated) = match read {
+                Ok(None) => (None, None, false),
+                Ok(Some(content)) => {
+                    let size = content.len() as u64;
+                    let (content, truncated) = truncate_to_tokens(content, entry.max_tokens);
+                    (Some(content), Some(size), truncated)
+                }
+                Err(e) if entry.required => {
+                    missing_required.push(
+                        Diagnostic::error(
+                            DiagnosticCode::MissingRequiredFile,
+                            &yaml_path,
+                            format!(
+                                "required bootstrap file '{}' could not be read: {e}",
+                                full_path.display()
+                            ),
+                        )
+                        .with_span(span)
+                        .with_hint("create the file or set `required: false`"),
+                    );
+                    continue;
+                }
                 Err(e) => {
                     warnings.push(AgentLoadWarning::MissingFile {
                         agent: raw.metadata.name.clone(),
-                        field: "instructions",
+                        field: "bootstrap",
                         path: full_path,
                         error: e.to_string(),
+                        manifest: yaml_path.clone(),
+                        span,
                     });
-                    None
+                    (None, None, false)
                 }
+            };
+            bootstrap.push(BootstrapFile {
+                path: entry.path,
+                max_tokens: entry.max_tokens,
+                required: entry.required,
+                content,
+                size,
+                truncated,
+            });
+        }
+        if !missing_required.is_empty() {
+            return Err(AgentLoadError::Validation(missing_required));
+        }
+
+        // Skills are discovered in both modes so the revision covers the same files.
+        let mut skill_warnings = Vec::new();
+        let skills = skill::Discovery {
+            agent: &raw.metadata.name,
+            agent_dir,
+            manifest: &yaml_path,
+            source: &source,
+        }
+        .run(
+            raw.spec.skills_dir.as_deref(),
+            &raw.spec.skills,
+            &mut revision,
+            &mut skill_warnings,
+        );
+        let skills = match files {
+            Files::Read => {
+                warnings.append(&mut skill_warnings);
+                skills
             }
-        } else {
-            None
+            Files::Skip => Vec::new(),
         };
 
         Ok((
             AgentSpec {
                 api_version: raw.api_version,
                 kind: raw.kind,
+                namespace: raw
+                    .metadata
+                    .namespace
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string()),
                 metadata: raw.metadata,
-                model: raw.spec.model,
+                model,
                 system_prompt,
                 instructions,
+                bootstrap,
+                skills,
+                tools,
+                parents,
+                revision: revision.finish(),
             },
             warnings,
         ))
     }
+
+    /// Instructions with template variables filled in for one request.
+    pub fn render_instructions(&self, ctx: &RenderContext) -> Option<String> {
+        let content = self.instructions.as_ref()?.content.as_deref()?;
+        Some(template::render(content, ctx))
+    }
+}
+
+/// The document to deserialize: the file as written, or the result of merging it over
+/// its parents.
+enum Document<'a> {
+    Text(&'a str),
+    Merged(serde_json::Value),
+}
+
+impl Document<'_> {
+    fn parse<T: DeserializeOwned>(self, yaml_path: &Path) -> Result<T, AgentLoadError> {
+        match self {
+            // Parsing the text keeps line and column information in errors.
+            Document::Text(text) => serde_saphyr::from_str(text)
+                .map_err(|e| AgentLoadError::Validation(vec![yaml_diagnostic(yaml_path, &e)])),
+            Document::Merged(doc) => serde_json::from_value(doc).map_err(|e| {
+                AgentLoadError::Validation(vec![Diagnostic::error(
+                    DiagnosticCode::YamlParse,
+                    yaml_path,
+                    format!("after merging parent definitions: {e}"),
+                )])
+            }),
+        }
+    }
+}
+
+/// Why a `spec.model` reference could not be resolved.
+struct UnknownModel {
+    reference: String,
+    aliases: Vec<String>,
+}
+
+impl UnknownModel {
+    fn at(self, yaml_path: &Path, source: &SourceMap) -> Diagnostic {
+        let hint = if self.aliases.is_empty() {
+            "use 'provider/name' or define the alias under `models:` in agnx.yaml".to_string()
+        } else {
+            format!(
+                "use 'provider/name' or one of the aliases from agnx.yaml: {}",
+                self.aliases.join(", ")
+            )
+        };
+        Diagnostic::error(
+            DiagnosticCode::UnknownModel,
+            yaml_path,
+            format!("unknown model '{}'", self.reference),
+        )
+        .with_span(source.value_span(&["spec", "model"]))
+        .with_hint(hint)
+    }
+}
+
+/// Resolve `spec.model` to a full model configuration.
+///
+/// A string is looked up in the configured aliases first, then read as `provider/name`
+/// (split at the first `/`, so `openrouter/anthropic/claude-sonnet-4` works).
+fn resolve_model(model: &RawModel, options: &LoadOptions) -> Result<ModelConfig, UnknownModel> {
+    let reference = match model {
+        RawModel::Config(config) => return Ok(config.clone()),
+        RawModel::Reference(reference) => reference,
+    };
+    if let Some(alias) = options.models.get(reference) {
+        return Ok(alias.clone());
+    }
+    match reference.split_once('/') {
+        Some((provider, name)) if !provider.is_empty() => {
+            Ok(ModelConfig::new(Provider::from(provider), name))
+        }
+        _ => {
+            let mut aliases: Vec<String> = options.models.keys().cloned().collect();
+            aliases.sort();
+            Err(UnknownModel {
+                reference: reference.clone(),
+                aliases,
+            })
+        }
+    }
+}
+
+/// Location of a model field, or of `spec.model` itself when it is an alias or shorthand.
+fn model_span(source: &SourceMap, field: &str) -> Option<SourceSpan> {
+    source
+        .value_span(&["spec", "model", field])
+        .or_else(|| source.value_span(&["spec", "model"]))
+}
+
+/// Check AAF rules that the YAML structure alone does not enforce.
+fn validate(
+    raw: &RawAgentSpec,
+    model: Option<&ModelConfig>,
+    yaml_path: &Path,
+    source: &SourceMap,
+) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+
+    if raw.kind != KIND_AGENT {
+        errors.push(
+            Diagnostic::error(
+                DiagnosticCode::UnsupportedKind,
+                yaml_path,
+                format!("unsupported kind '{}'", raw.kind),
+            )
+            .with_span(source.value_span(&["kind"]))
+            .with_hint(format!("expected '{KIND_AGENT}'")),
+        );
+    }
+
+    if !is_valid_agent_name(&raw.metadata.name) {
+        errors.push(
+            Diagnostic::error(
+                DiagnosticCode::InvalidName,
+                yaml_path,
+                format!("invalid metadata.name '{}'", raw.metadata.name),
+            )
+            .with_span(source.value_span(&["metadata", "name"]))
+            .with_hint(agent_name_hint(&raw.metadata.name)),
+        );
+    }
+
+    if let Some(namespace) = &raw.metadata.namespace
+        && !is_valid_namespace(namespace)
+    {
+        errors.push(
+            Diagnostic::error(
+                DiagnosticCode::InvalidNamespace,
+                yaml_path,
+                format!("invalid metadata.namespace '{namespace}'"),
+            )
+            .with_span(source.value_span(&["metadata", "namespace"]))
+            .with_hint(NAMESPACE_HINT),
+        );
+    }
+
+    let Some(model) = model else {
+        return errors;
+    };
+
+    if model.name.trim().is_empty() {
+        errors.push(
+            Diagnostic::error(
+                DiagnosticCode::EmptyModelName,
+                yaml_path,
+                "spec.model.name must not be empty",
+            )
+            .with_span(model_span(source, "name")),
+        );
+    }
+
+    if let Some(t) = model.temperature
+        && !(0.0..=2.0).contains(&t)
+    {
+        errors.push(
+            Diagnostic::error(
+                DiagnosticCode::TemperatureOutOfRange,
+                yaml_path,
+                format!("spec.model.temperature {t} is out of range"),
+            )
+            .with_span(model_span(source, "temperature"))
+            .with_hint("temperature must be between 0 and 2"),
+        );
+    }
+
+    let mut invalid = |field: &str, message: String, hint: &str| {
+        errors.push(
+            Diagnostic::error(DiagnosticCode::InvalidModelParameter, yaml_path, message)
+                .with_span(model_span(source, field))
+                .with_hint(hint),
+        );
+    };
+    if let Some(p) = model.top_p
+        && !(0.0..=1.0).contains(&p)
+    {
+        invalid(
+            "top_p",
+            format!("spec.model.top_p {p} is out of range"),
+            "top_p must be between 0 and 1",
+        );
+    }
+    if model.top_k == Some(0) {
+        invalid(
+            "top_k",
+            "spec.model.top_k must not be 0".to_string(),
+            "top_k must be at least 1",
+        );
+    }
+    for (field, penalty) in [
+        ("presence_penalty", model.presence_penalty),
+        ("frequency_penalty", model.frequency_penalty),
+    ] {
+        if let Some(penalty) = penalty
+            && !(-2.0..=2.0).contains(&penalty)
+        {
+            invalid(
+                field,
+                format!("spec.model.{field} {penalty} is out of range"),
+                "penalties must be between -2 and 2",
+            );
+        }
+    }
+    if model.stop.iter().any(String::is_empty) {
+        invalid(
+            "stop",
+            "spec.model.stop contains an empty sequence".to_string(),
+            "remove the empty entry",
+        );
+    }
+
+    errors
+}
+
+/// Warnings for model parameters that the provider does not accept and that would be
+/// dropped from requests.
+fn unsupported_parameters(
+    agent: &str,
+    model: &ModelConfig,
+    yaml_path: &Path,
+    source: &SourceMap,
+) -> Vec<AgentLoadWarning> {
+    let set = [
+        ("top_k", model.top_k.is_some()),
+        ("seed", model.seed.is_some()),
+        ("presence_penalty", model.presence_penalty.is_some()),
+        ("frequency_penalty", model.frequency_penalty.is_some()),
+        ("reasoning_effort", model.reasoning_effort.is_some()),
+    ];
+    set.into_iter()
+        .filter(|(parameter, set)| *set && !model.provider.supports(parameter))
+        .map(|(parameter, _)| AgentLoadWarning::UnsupportedParameter {
+            agent: agent.to_string(),
+            provider: model.provider.to_string(),
+            parameter,
+            manifest: yaml_path.to_path_buf(),
+            span: model_span(source, parameter),
+        })
+        .collect()
+}
+
+/// Rough bytes-per-token ratio used to apply `max_tokens` without a tokenizer.
+const BYTES_PER_TOKEN: usize = 4;
+
+/// Cut `text` to roughly `max_tokens` tokens, preferring to end on a line boundary.
+fn truncate_to_tokens(mut text: String, max_tokens: Option<u32>) -> (String, bool) {
+    let Some(max_tokens) = max_tokens else {
+        return (text, false);
+    };
+    let limit = (max_tokens as usize).saturating_mul(BYTES_PER_TOKEN);
+    if text.len() <= limit {
+        return (text, false);
+    }
+
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    if let Some(newline) = text[..end].rfind('\n') {
+        end = newline + 1;
+    }
+    text.truncate(end);
+    (text, true)
+}
+
+/// Locations for diagnostics, in the file as written: an expanded value may span
+/// several lines and would shift everything after it. Values never change the
+/// document's structure, so both texts have the same fields; a file that only parses
+/// once expanded falls back to the expanded text.
+fn source_map(on_disk: &str, expanded: &str) -> SourceMap {
+    let source = SourceMap::parse(on_disk);
+    if source.is_parsed() || on_disk == expanded {
+        source
+    } else {
+        SourceMap::parse(expanded)
+    }
+}
+
+/// The resolved model, as served in agent responses. Aliases from `agnx.yaml` and
+/// `${VAR}` values change it without changing any file, so it is hashed separately.
+fn model_revision_bytes(model: &ModelConfig) -> Vec<u8> {
+    let extra: BTreeMap<_, _> = model.extra.iter().collect();
+    let value = serde_json::json!({
+        "provider": model.provider.as_str(),
+        "name": model.name,
+        "temperature": model.temperature,
+        "max_input_tokens": model.max_input_tokens,
+        "max_output_tokens": model.max_output_tokens,
+        "base_url": model.base_url,
+        "top_p": model.top_p,
+        "top_k": model.top_k,
+        "stop": model.stop,
+        "seed": model.seed,
+        "presence_penalty": model.presence_penalty,
+        "frequency_penalty": model.frequency_penalty,
+        "reasoning_effort": model.reasoning_effort.map(|effort| effort.as_str()),
+        "extra": extra,
+    });
+    value.to_string().into_bytes()
+}
+
+const MAX_NAME_LEN: usize = 63;
+
+/// Names that would collide with the static segments of `/agents/{name}/spec` and
+/// `/agents/{name}/chat`, which take precedence over `/agents/{namespace}/{name}`.
+const RESERVED_AGENT_NAMES: &[&str] = &["spec", "chat"];
+
+/// Agent names are used in URLs and directory names, so keep them to a safe subset.
+pub(super) fn is_valid_agent_name(name: &str) -> bool {
+    is_valid_name_segment(name) && !RESERVED_AGENT_NAMES.contains(&name)
+}
+
+/// Hint for an invalid agent name.
+pub(super) fn agent_name_hint(name: &str) -> String {
+    if RESERVED_AGENT_NAMES.contains(&name) {
+        format!(
+            "'{name}' is reserved by the HTTP API (/agents/{{namespace}}/{name} would be \
+             routed to /agents/{{name}}/{name}); choose another name"
+        )
+    } else {
+        format!("use 1-{MAX_NAME_LEN} letters, digits, '-' or '_', starting with a letter or digit")
+    }
+}
+
+fn is_valid_name_segment(name: &str) -> bool {
+    name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub(super) const NAMESPACE_HINT: &str =
+    "use '.'-separated segments of letters, digits, '-' or '_', e.g. 'platform.search'";
+
+/// Namespaces are one or more agent-name-like segments separated by `.`.
+pub(super) fn is_valid_namespace(namespace: &str) -> bool {
+    namespace.split('.').all(is_valid_name_segment)
+}
+
+fn env_diagnostic(yaml_path: &Path, error: &InterpolationError) -> Diagnostic {
+    let message = match &error.kind {
+        InterpolationErrorKind::Unset => {
+            format!("environment variable '{}' is not set", error.variable)
+        }
+        InterpolationErrorKind::Required(message) if !message.is_empty() => {
+            format!("environment variable '{}': {message}", error.variable)
+        }
+        InterpolationErrorKind::Required(_) => {
+            format!("environment variable '{}' is required", error.variable)
+        }
+        InterpolationErrorKind::Unterminated => {
+            format!("unterminated reference '{}'", error.variable)
+        }
+        InterpolationErrorKind::Unquotable => format!(
+            "the value of environment variable '{}' cannot be written here",
+            error.variable
+        ),
+    };
+    let span = SourceSpan {
+        line: error.line,
+        column: error.column,
+        end_line: error.line,
+        end_column: error.column + error.variable.len() as u32 + 3,
+    };
+
+    Diagnostic::error(DiagnosticCode::EnvInterpolation, yaml_path, message)
+        .with_span(Some(span))
+        .with_hint(match error.kind {
+            InterpolationErrorKind::Unquotable => {
+                "put the reference in double quotes, e.g. key: \"${VAR}\"".to_string()
+            }
+            _ => format!(
+                "set the variable, use ${{VAR:-default}}, escape as $${{, or add '# {NO_INTERPOLATE_DIRECTIVE}' at the top of the file"
+            ),
+        })
+}
+
+fn yaml_diagnostic(yaml_path: &Path, error: &serde_saphyr::Error) -> Diagnostic {
+    let error = error.without_snippet();
+    let message = match error {
+        serde_saphyr::Error::Message { msg, .. } => msg.clone(),
+        other => other.to_string(),
+    };
+    let span = error.location().map(|l| SourceSpan {
+        line: l.line() as u32,
+        column: l.column() as u32,
+        end_line: l.line() as u32,
+        end_column: l.column() as u32 + l.span().len().max(1) as u32,
+    });
+
+    Diagnostic::error(DiagnosticCode::YamlParse, yaml_path, message).with_span(span)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agent::API_VERSION_V1ALPHA1;
     use tempfile::TempDir;
 
     fn write_yaml(dir: &Path, contents: &str) {
@@ -174,7 +1095,8 @@ spec:
 "#,
         );
 
-        let (agent, warnings) = AgentSpec::load_with_warnings(&agent_dir).unwrap();
+        let (agent, warnings) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
         assert!(warnings.is_empty());
         assert_eq!(agent.api_version, API_VERSION_V1ALPHA1);
         assert_eq!(agent.kind, KIND_AGENT);
@@ -212,13 +1134,61 @@ spec:
         )
         .unwrap();
 
-        let (agent, _) = AgentSpec::load_with_warnings(&agent_dir).unwrap();
+        let (agent, _) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
         assert_eq!(agent.metadata.name, "test-agent");
         assert_eq!(agent.metadata.description, Some("A test agent".to_string()));
         assert_eq!(agent.metadata.version, Some("1.0.0".to_string()));
         assert_eq!(
             agent.system_prompt,
-            Some("You are a helpful assistant.".to_string())
+            Some(Prompt {
+                source: PromptSource::File("./SYSTEM_PROMPT.md".to_string()),
+                content: Some("You are a helpful assistant.".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn load_agent_with_inline_and_tagged_file_prompts() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+  system_prompt:
+    inline: |
+      You are a helpful assistant.
+  instructions:
+    file: ./INSTRUCTIONS.md
+"#,
+        );
+        fs::write(agent_dir.join("INSTRUCTIONS.md"), "Be brief.").unwrap();
+
+        let (agent, warnings) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(
+            agent.system_prompt,
+            Some(Prompt {
+                source: PromptSource::Inline,
+                content: Some("You are a helpful assistant.\n".to_string()),
+            })
+        );
+        assert_eq!(
+            agent.instructions,
+            Some(Prompt {
+                source: PromptSource::File("./INSTRUCTIONS.md".to_string()),
+                content: Some("Be brief.".to_string()),
+            })
         );
     }
 
@@ -242,8 +1212,14 @@ spec:
 "#,
         );
 
-        let (agent, warnings) = AgentSpec::load_with_warnings(&agent_dir).unwrap();
-        assert!(agent.system_prompt.is_none());
+        let (agent, warnings) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
+        let prompt = agent.system_prompt.unwrap();
+        assert_eq!(
+            prompt.source,
+            PromptSource::File("./MISSING.md".to_string())
+        );
+        assert!(prompt.content.is_none());
         assert_eq!(warnings.len(), 1);
     }
 
@@ -266,10 +1242,97 @@ spec:
 "#,
         );
 
-        let err = AgentSpec::load_with_warnings(&agent_dir).unwrap_err();
+        let err = AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap_err();
         assert!(matches!(err, AgentLoadError::Validation(_)));
     }
 
+    fn versioned_agent(api_version: &str) -> String {
+        format!(
+            r#"apiVersion: {api_version}
+kind: Agent
+metadata:
+  name: versioned
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+    max_tokens: 512
+  system_prompt: ./SYSTEM_PROMPT.md
+"#
+        )
+    }
+
+    #[test]
+    fn older_versions_convert_to_the_same_spec() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(tmp.path().join("SYSTEM_PROMPT.md"), "Be nice.").unwrap();
+
+        write_yaml(tmp.path(), &versioned_agent("agnx/v1alpha1"));
+        let (alpha, warnings) =
+            AgentSpec::load_with_warnings(tmp.path(), &LoadOptions::default()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(alpha.model.max_output_tokens, Some(512));
+
+        write_yaml(tmp.path(), &versioned_agent("agnx/v1beta1"));
+        let (beta, warnings) =
+            AgentSpec::load_with_warnings(tmp.path(), &LoadOptions::default()).unwrap();
+        assert_eq!(beta.model.max_output_tokens, Some(512));
+        assert_eq!(beta.system_prompt, alpha.system_prompt);
+        let fields: Vec<_> = warnings
+            .iter()
+            .map(|w| match w {
+                AgentLoadWarning::DeprecatedField { field, span, .. } => {
+                    (field.as_str(), span.map(|s| (s.line, s.column)))
+                }
+                other => panic!("unexpected warning {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("spec.model.max_tokens", Some((9, 5))),
+                ("spec.system_prompt", Some((10, 18))),
+            ]
+        );
+    }
+
+    #[test]
+    fn v1_rejects_removed_forms() {
+        let tmp = TempDir::new().unwrap();
+        write_yaml(tmp.path(), &versioned_agent("agnx/v1"));
+
+        let err = AgentSpec::load_with_warnings(tmp.path(), &LoadOptions::default()).unwrap_err();
+        let AgentLoadError::Validation(diagnostics) = err else {
+            panic!("expected validation error");
+        };
+        assert_eq!(diagnostics.len(), 2);
+        assert!(
+            diagnostics
+                .iter()
+                .all(|d| d.code == DiagnosticCode::RemovedField)
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "'spec.model.max_tokens' was removed in agnx/v1"
+        );
+
+        fs::write(tmp.path().join("SYSTEM_PROMPT.md"), "Be nice.").unwrap();
+        write_yaml(
+            tmp.path(),
+            &versioned_agent("agnx/v1")
+                .replace("max_tokens", "max_output_tokens")
+                .replace("./SYSTEM_PROMPT.md", "{ file: ./SYSTEM_PROMPT.md }"),
+        );
+        let (agent, warnings) =
+            AgentSpec::load_with_warnings(tmp.path(), &LoadOptions::default()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(agent.api_version, "agnx/v1");
+        assert_eq!(
+            agent.system_prompt.unwrap().content.as_deref(),
+            Some("Be nice.")
+        );
+    }
+
     #[test]
     fn load_agent_with_all_model_options() {
         let tmp = TempDir::new().unwrap();
@@ -295,7 +1358,8 @@ spec:
 "#,
         );
 
-        let (agent, _) = AgentSpec::load_with_warnings(&agent_dir).unwrap();
+        let (agent, _) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
         assert_eq!(agent.model.provider, Provider::OpenRouter);
         assert_eq!(agent.model.temperature, Some(0.7));
         assert_eq!(agent.model.max_output_tokens, Some(4096));
@@ -312,4 +1376,902 @@ spec:
             Some(&"premium".to_string())
         );
     }
+
+    #[test]
+    fn generation_parameters_are_validated_and_checked_against_the_provider() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+        let yaml = r#"apiVersion: agnx/v1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model:
+    provider: anthropic
+    name: claude-sonnet-4
+    top_p: 0.9
+    top_k: 40
+    stop: ["END"]
+    seed: 7
+    reasoning_effort: high
+    extra:
+      metadata: {user_id: abc}
+"#;
+        write_yaml(&agent_dir, yaml);
+
+        let (agent, warnings) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
+        assert_eq!(agent.model.top_p, Some(0.9));
+        assert_eq!(agent.model.top_k, Some(40));
+        assert_eq!(agent.model.stop, ["END"]);
+        assert_eq!(agent.model.reasoning_effort, Some(ReasoningEffort::High));
+        assert_eq!(agent.model.extra["metadata"]["user_id"], "abc");
+        let unsupported: Vec<(String, Option<u32>)> = warnings
+            .iter()
+            .map(|w| w.to_diagnostic())
+            .map(|d| {
+                assert_eq!(d.code, DiagnosticCode::UnsupportedParameter);
+                (d.message, d.span.map(|s| s.line))
+            })
+            .collect();
+        assert_eq!(
+            unsupported,
+            [
+                (
+                    "spec.model.seed is not supported by provider 'anthropic' and will be ignored"
+                        .to_string(),
+                    Some(12)
+                ),
+                (
+                    "spec.model.reasoning_effort is not supported by provider 'anthropic' and will be ignored"
+                        .to_string(),
+                    Some(13)
+                ),
+            ]
+        );
+
+        write_yaml(
+            &agent_dir,
+            &yaml
+                .replace("provider: anthropic", "provider: my-gateway")
+                .replace("top_p: 0.9", "top_p: 1.5")
+                .replace("top_k: 40", "top_k: 0")
+                .replace("[\"END\"]", "[\"\"]"),
+        );
+        let Err(AgentLoadError::Validation(errors)) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default())
+        else {
+            panic!("expected validation errors");
+        };
+        let codes: Vec<_> = errors
+            .iter()
+            .map(|e| (e.code, e.span.map(|s| s.line)))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (DiagnosticCode::InvalidModelParameter, Some(9)),
+                (DiagnosticCode::InvalidModelParameter, Some(10)),
+                (DiagnosticCode::InvalidModelParameter, Some(11)),
+            ]
+        );
+    }
+
+    #[test]
+    fn load_agent_reports_all_validation_errors_with_locations() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1alpha1
+kind: Tool
+metadata:
+  name: "bad name!"
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+    temperature: 2.5
+"#,
+        );
+
+        let AgentLoadError::Validation(diagnostics) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap_err()
+        else {
+            panic!("expected validation error");
+        };
+
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.code, d.span.map(|s| (s.line, s.column))))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (DiagnosticCode::UnsupportedKind, Some((2, 7))),
+                (DiagnosticCode::InvalidName, Some((4, 9))),
+                (DiagnosticCode::TemperatureOutOfRange, Some((9, 18))),
+            ]
+        );
+        assert!(
+            diagnostics
+                .iter()
+                .all(|d| d.path == agent_dir.join("agent.yaml") && d.hint.is_some())
+        );
+    }
+
+    #[test]
+    fn load_agent_yaml_error_is_located_diagnostic() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+
+        write_yaml(&agent_dir, "apiVersion: agnx/v1alpha1\nkind: [\n");
+
+        let err = AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap_err();
+        let diagnostics = err.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::YamlParse);
+        assert!(diagnostics[0].span.is_some());
+    }
+
+    #[test]
+    fn missing_file_warning_points_at_reference() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+  instructions: ./MISSING.md
+"#,
+        );
+
+        let (_, warnings) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
+        let d = warnings[0].to_diagnostic();
+        assert_eq!(d.code, DiagnosticCode::MissingFile);
+        assert_eq!(d.path, agent_dir.join("agent.yaml"));
+        assert_eq!(d.span.map(|s| (s.line, s.column)), Some((9, 17)));
+    }
+
+    #[test]
+    fn agent_name_character_set() {
+        assert!(is_valid_agent_name("my-agent_2"));
+        assert!(is_valid_agent_name("user_12345"));
+        assert!(!is_valid_agent_name(""));
+        assert!(!is_valid_agent_name("-leading"));
+        assert!(!is_valid_agent_name("has space"));
+        assert!(!is_valid_agent_name("slash/name"));
+        assert!(!is_valid_agent_name(&"a".repeat(64)));
+        assert!(!is_valid_agent_name("spec"));
+        assert!(!is_valid_agent_name("chat"));
+        assert!(is_valid_namespace("chat.spec"));
+    }
+
+    #[test]
+    fn load_agent_expands_env_vars() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model:
+    provider: ${AGNX_TEST_UNSET_PROVIDER:-ollama}
+    name: ${AGNX_TEST_UNSET_MODEL:-llama3.2}
+"#,
+        );
+
+        let (agent, _) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
+        assert_eq!(agent.model.provider, Provider::Ollama);
+        assert_eq!(agent.model.name, "llama3.2");
+    }
+
+    #[test]
+    fn load_agent_missing_env_var_is_located_error() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model:
+    provider: openrouter
+    name: ${AGNX_TEST_UNSET_MODEL}
+"#,
+        );
+
+        let diagnostics = AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default())
+            .unwrap_err()
+            .diagnostics();
+        assert_eq!(diagnostics[0].code, DiagnosticCode::EnvInterpolation);
+        assert!(diagnostics[0].message.contains("AGNX_TEST_UNSET_MODEL"));
+        assert_eq!(diagnostics[0].path, agent_dir.join("agent.yaml"));
+        assert_eq!(
+            diagnostics[0].span.map(|s| (s.line, s.column)),
+            Some((8, 11))
+        );
+    }
+
+    #[test]
+    fn unknown_instruction_variables_warn_and_known_ones_render() {
+        use chrono::{TimeZone, Utc};
+
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+  instructions: ./INSTRUCTIONS.md
+"#,
+        );
+        fs::write(
+            agent_dir.join("INSTRUCTIONS.md"),
+            "Greet ${user.name} on ${date}.\nNever print ${secret} or $${date}.\n",
+        )
+        .unwrap();
+
+        let (agent, warnings) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
+        assert_eq!(
+            warnings,
+            vec![AgentLoadWarning::UnknownTemplateVariable {
+                agent: "test-agent".to_string(),
+                field: "instructions",
+                variable: "secret".to_string(),
+                path: agent_dir.join("./INSTRUCTIONS.md"),
+                span: Some(SourceSpan {
+                    line: 2,
+                    column: 13,
+                    end_line: 2,
+                    end_column: 22,
+                }),
+            }]
+        );
+
+        let ctx = RenderContext {
+            user_name: Some("Ana"),
+            timezone: chrono_tz::UTC,
+            now: Utc.with_ymd_and_hms(2026, 1, 11, 9, 0, 0).unwrap(),
+            data_dir: Path::new(".agnx"),
+        };
+        assert_eq!(
+            agent.render_instructions(&ctx).as_deref(),
+            Some("Greet Ana on 2026-01-11.\nNever print ${secret} or ${date}.\n")
+        );
+        // The stored instructions stay unrendered.
+        assert!(
+            agent
+                .instructions
+                .unwrap()
+                .content
+                .unwrap()
+                .contains("${date}")
+        );
+    }
+
+    #[test]
+    fn inline_instructions_escape_template_variables_like_files() {
+        use chrono::{TimeZone, Utc};
+
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model: openai/gpt-4o
+  instructions:
+    inline: Greet ${user.name} on ${date}; write $${date} and $${user.name} literally.
+"#,
+        );
+
+        let (agent, warnings) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let ctx = RenderContext {
+            user_name: Some("Ana"),
+            timezone: chrono_tz::UTC,
+            now: Utc.with_ymd_and_hms(2026, 1, 11, 9, 0, 0).unwrap(),
+            data_dir: Path::new(".agnx"),
+        };
+        assert_eq!(
+            agent.render_instructions(&ctx).as_deref(),
+            Some("Greet Ana on 2026-01-11; write ${date} and ${user.name} literally.")
+        );
+    }
+
+    #[test]
+    fn load_agent_bootstrap_files() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+  bootstrap:
+    - path: ./SOUL.md
+      required: true
+    - path: ./USER.md
+      max_tokens: 3
+    - path: ./MISSING.md
+"#,
+        );
+        fs::write(agent_dir.join("SOUL.md"), "Be calm.").unwrap();
+        fs::write(agent_dir.join("USER.md"), "Name: Ana\nTimezone: UTC\n").unwrap();
+
+        let (agent, warnings) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
+        assert_eq!(agent.bootstrap.len(), 3);
+
+        assert_eq!(agent.bootstrap[0].content.as_deref(), Some("Be calm."));
+        assert_eq!(agent.bootstrap[0].size, Some(8));
+        assert!(!agent.bootstrap[0].truncated);
+
+        // 3 tokens ~ 12 bytes, cut back to the last full line.
+        assert_eq!(agent.bootstrap[1].content.as_deref(), Some("Name: Ana\n"));
+        assert_eq!(agent.bootstrap[1].size, Some(24));
+        assert!(agent.bootstrap[1].truncated);
+
+        assert!(agent.bootstrap[2].content.is_none());
+        assert_eq!(warnings.len(), 1);
+        let d = warnings[0].to_diagnostic();
+        assert_eq!(d.code, DiagnosticCode::MissingFile);
+        assert_eq!(d.span.map(|s| (s.line, s.column)), Some((14, 13)));
+    }
+
+    #[test]
+    fn spans_point_into_the_file_as_written() {
+        let text = "spec:\n  instructions:\n    inline: |\n      ${MULTI}\n  model:\n    temprature: 1\n";
+        let expanded = crate::interpolate::interpolate_with(text, |name| {
+            (name == "MULTI").then(|| "one\ntwo\nthree".to_string())
+        })
+        .unwrap();
+        assert_eq!(expanded.lines().count(), 8);
+
+        let span = source_map(text, &expanded).value_span(&["spec", "model", "temprature"]);
+        assert_eq!(span.map(|s| (s.line, s.column)), Some((6, 17)));
+        // A file that only parses once expanded still gets spans.
+        let text = "spec: {model: ${MODEL}}\n";
+        let expanded = text.replace("${MODEL}", "m");
+        assert!(!SourceMap::parse(text).is_parsed());
+        assert!(
+            source_map(text, &expanded)
+                .value_span(&["spec", "model"])
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn revision_covers_manifest_and_referenced_files() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model: openrouter/anthropic/claude-sonnet-4
+  system_prompt:
+    file: ./SYSTEM_PROMPT.md
+  bootstrap:
+    - path: ./NOTES.md
+"#,
+        );
+        fs::write(agent_dir.join("SYSTEM_PROMPT.md"), "Be helpful.").unwrap();
+        let revision = || {
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default())
+                .unwrap()
+                .0
+                .revision
+                .hash
+        };
+
+        let first = revision();
+        assert_eq!(revision(), first);
+
+        fs::write(agent_dir.join("SYSTEM_PROMPT.md"), "Be brief.").unwrap();
+        let edited = revision();
+        assert_ne!(edited, first);
+
+        // Creating an optional file that was missing is a change too.
+        fs::write(agent_dir.join("NOTES.md"), "").unwrap();
+        assert_ne!(revision(), edited);
+
+        // The manifest is hashed as written, before environment variables are expanded:
+        // a deployed copy, which is not expanded, has the same revision.
+        let manifest = fs::read_to_string(agent_dir.join("agent.yaml")).unwrap();
+        fs::write(
+            agent_dir.join("agent.yaml"),
+            manifest.replace(
+                "  name: test-agent\n",
+                "  name: test-agent\n  description: ${AGNX_TEST_UNSET_DESCRIPTION:-An agent}\n",
+            ),
+        )
+        .unwrap();
+        let sandboxed = LoadOptions {
+            sandbox_root: Some(tmp.path().to_path_buf()),
+            ..LoadOptions::default()
+        };
+        let (agent, _) = AgentSpec::load_with_warnings(&agent_dir, &sandboxed).unwrap();
+        assert_eq!(
+            agent.metadata.description.as_deref(),
+            Some("${AGNX_TEST_UNSET_DESCRIPTION:-An agent}")
+        );
+        assert_eq!(agent.revision.hash, revision());
+
+        // The resolved model is served too, so changing an alias is a new revision.
+        let manifest = fs::read_to_string(agent_dir.join("agent.yaml")).unwrap();
+        fs::write(
+            agent_dir.join("agent.yaml"),
+            manifest.replace("openrouter/anthropic/claude-sonnet-4", "fast"),
+        )
+        .unwrap();
+        let with_alias = |name: &str| {
+            let options = LoadOptions {
+                models: HashMap::from([(
+                    "fast".to_string(),
+                    ModelConfig::new(Provider::OpenAI, name),
+                )]),
+                ..LoadOptions::default()
+            };
+            AgentSpec::load_with_warnings(&agent_dir, &options)
+                .unwrap()
+                .0
+                .revision
+                .hash
+        };
+        assert_eq!(with_alias("gpt-4o"), with_alias("gpt-4o"));
+        assert_ne!(with_alias("gpt-4o"), with_alias("gpt-4o-mini"));
+    }
+
+    #[test]
+    fn load_agent_discovers_skills() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        let skill = |dir: &Path, text: &str| {
+            fs::create_dir_all(dir).unwrap();
+            fs::write(dir.join("SKILL.md"), text).unwrap();
+        };
+        let example = Path::new(env!("CARGO_MANIFEST_DIR")).join("docs/examples/skills");
+        let extraction = agent_dir.join("skills/task-extraction");
+        skill(
+            &extraction,
+            &fs::read_to_string(example.join("task-extraction/SKILL.md")).unwrap(),
+        );
+        fs::create_dir_all(extraction.join("references/more")).unwrap();
+        fs::write(extraction.join("references/examples.md"), "").unwrap();
+        fs::write(extraction.join("references/more/edge-cases.md"), "").unwrap();
+        skill(
+            &agent_dir.join("skills/broken"),
+            "---
+name: [broken
+---
+",
+        );
+        skill(
+            &agent_dir.join("skills/notes"),
+            "Take notes.
+",
+        );
+        fs::create_dir_all(agent_dir.join("skills/not-a-skill")).unwrap();
+        skill(
+            &tmp.path().join("shared/other-extraction"),
+            "---
+name: task-extraction
+---
+",
+        );
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model: openrouter/some-model
+  skills:
+    - path: ./skills/notes/
+    - path: ../shared/other-extraction
+    - path: ../shared/missing
+"#,
+        );
+
+        let (agent, warnings) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
+        let ids: Vec<&str> = agent.skills.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["broken", "notes", "task-extraction"]);
+        let extraction = &agent.skills[2];
+        assert_eq!(
+            extraction.frontmatter.description.as_deref(),
+            Some("Extract actionable tasks from a message or conversation")
+        );
+        assert_eq!(extraction.frontmatter.allowed_tools, ["calculator"]);
+        assert_eq!(extraction.frontmatter.triggers.len(), 2);
+        assert_eq!(
+            extraction.references,
+            ["references/examples.md", "references/more/edge-cases.md"]
+        );
+        assert!(extraction.scripts.is_empty());
+        assert_eq!(agent.skills[1].instructions, "Take notes.\n");
+
+        let diagnostics: Vec<_> = warnings
+            .iter()
+            .map(|w| {
+                let d = w.to_diagnostic();
+                (d.code, d.span.map(|s| s.line))
+            })
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                (DiagnosticCode::InvalidSkill, None),
+                (DiagnosticCode::DuplicateSkill, None),
+                (DiagnosticCode::InvalidSkill, Some(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn load_agent_resolves_tools() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir_all(agent_dir.join("bin")).unwrap();
+        fs::write(agent_dir.join("bin/lint"), "").unwrap();
+        fs::write(agent_dir.join("LINT.md"), "Run it.").unwrap();
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model: openrouter/some-model
+  tools:
+    - name: github
+      type: mcp
+      server: npx @modelcontextprotocol/server-github
+      config:
+        token: secret
+    - name: lint
+      type: cli
+      command: ./bin/lint --fix
+      readme: ./LINT.md
+    - name: search
+      type: cli
+      command: rg -n
+      readme: ./LINT.md
+    - name: calculator
+      type: builtin
+"#,
+        );
+
+        let (agent, _) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
+        let tools: Vec<_> = agent.tools.iter().map(|t| (t.name(), t.kind())).collect();
+        assert_eq!(
+            tools,
+            [
+                ("github", "mcp"),
+                ("lint", "cli"),
+                ("search", "cli"),
+                ("calculator", "builtin")
+            ]
+        );
+        let ToolSpec::Mcp { config, .. } = &agent.tools[0] else {
+            panic!("expected an mcp tool");
+        };
+        assert_eq!(config["token"], "secret");
+
+        // READMEs are part of the revision.
+        fs::write(agent_dir.join("LINT.md"), "Run it twice.").unwrap();
+        let (edited, _) =
+            AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default()).unwrap();
+        assert_ne!(edited.revision.hash, agent.revision.hash);
+    }
+
+    #[test]
+    fn load_agent_invalid_tools_are_errors() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model: openrouter/some-model
+  tools:
+    - name: github
+      type: mcp
+    - name: lint
+      type: cli
+      command: ./bin/lint
+      readme: ./LINT.md
+    - name: browser
+      type: builtin
+    - name: shell
+      type: plugin
+    - name: calculator
+      type: builtin
+    - name: calculator
+      type: builtin
+    - name: shell
+      type: mcp
+      server: ./bin/shell-mcp
+"#,
+        );
+
+        let diagnostics = AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default())
+            .unwrap_err()
+            .diagnostics();
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.code, d.span.map(|s| s.line)))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (DiagnosticCode::InvalidTool, Some(8)),
+                (DiagnosticCode::InvalidTool, Some(12)),
+                (DiagnosticCode::InvalidTool, Some(13)),
+                (DiagnosticCode::InvalidTool, Some(14)),
+                (DiagnosticCode::InvalidTool, Some(17)),
+                (DiagnosticCode::DuplicateTool, Some(20)),
+                (DiagnosticCode::DuplicateTool, Some(22)),
+            ]
+        );
+        assert_eq!(
+            diagnostics[3].hint.as_deref(),
+            Some("builtin tools: calculator, current_time")
+        );
+    }
+
+    #[test]
+    fn load_agent_missing_required_bootstrap_is_error() {
+        let tmp = TempDir::new().unwrap();
+        let agent_dir = tmp.path().join("test-agent");
+        fs::create_dir(&agent_dir).unwrap();
+
+        write_yaml(
+            &agent_dir,
+            r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+  bootstrap:
+    - path: ./IDENTITY.md
+      required: true
+"#,
+        );
+
+        let diagnostics = AgentSpec::load_with_warnings(&agent_dir, &LoadOptions::default())
+            .unwrap_err()
+            .diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::MissingRequiredFile);
+        assert_eq!(
+            diagnostics[0].span.map(|s| (s.line, s.column)),
+            Some((10, 13))
+        );
+    }
+
+    fn model_agent(model: &str) -> TempDir {
+        let tmp = TempDir::new().unwrap();
+        write_yaml(
+            tmp.path(),
+            &format!(
+                r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model: {model}
+"#
+            ),
+        );
+        tmp
+    }
+
+    #[test]
+    fn load_agent_model_alias_and_shorthand() {
+        let options = LoadOptions {
+            models: HashMap::from([(
+                "fast".to_string(),
+                ModelConfig {
+                    temperature: Some(0.2),
+                    max_output_tokens: Some(512),
+                    base_url: Some("http://localhost:11434".to_string()),
+                    ..ModelConfig::new(Provider::Ollama, "llama3.2")
+                },
+            )]),
+            ..LoadOptions::default()
+        };
+
+        let tmp = model_agent("fast");
+        let (agent, _) = AgentSpec::load_with_warnings(tmp.path(), &options).unwrap();
+        assert_eq!(agent.model.provider, Provider::Ollama);
+        assert_eq!(agent.model.name, "llama3.2");
+        assert_eq!(agent.model.max_output_tokens, Some(512));
+
+        let tmp = model_agent("openrouter/anthropic/claude-sonnet-4");
+        let (agent, _) = AgentSpec::load_with_warnings(tmp.path(), &options).unwrap();
+        assert_eq!(agent.model.provider, Provider::OpenRouter);
+        assert_eq!(agent.model.name, "anthropic/claude-sonnet-4");
+        assert_eq!(agent.model.temperature, None);
+    }
+
+    #[test]
+    fn load_agent_unknown_model_alias_is_error() {
+        let options = LoadOptions {
+            models: HashMap::from([(
+                "fast".to_string(),
+                ModelConfig {
+                    temperature: Some(3.0),
+                    ..ModelConfig::new(Provider::OpenRouter, "anthropic/claude-haiku-4")
+                },
+            )]),
+            ..LoadOptions::default()
+        };
+
+        let tmp = model_agent("smart");
+        let diagnostics = AgentSpec::load_with_warnings(tmp.path(), &options)
+            .unwrap_err()
+            .diagnostics();
+        assert_eq!(diagnostics[0].code, DiagnosticCode::UnknownModel);
+        assert_eq!(
+            diagnostics[0].hint.as_deref(),
+            Some("use 'provider/name' or one of the aliases from agnx.yaml: fast")
+        );
+        assert_eq!(
+            diagnostics[0].span.map(|s| (s.line, s.column)),
+            Some((6, 10))
+        );
+
+        // Problems in the alias itself are reported at the reference.
+        let tmp = model_agent("fast");
+        let diagnostics = AgentSpec::load_with_warnings(tmp.path(), &options)
+            .unwrap_err()
+            .diagnostics();
+        assert_eq!(diagnostics[0].code, DiagnosticCode::TemperatureOutOfRange);
+        assert_eq!(
+            diagnostics[0].span.map(|s| (s.line, s.column)),
+            Some((6, 10))
+        );
+    }
+
+    #[test]
+    fn unknown_fields_warn_by_default_and_fail_in_strict_mode() {
+        let tmp = TempDir::new().unwrap();
+        write_yaml(
+            tmp.path(),
+            r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: test-agent
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+    temprature: 0.2
+  memory:
+    short_term:
+      backend: buffer
+"#,
+        );
+
+        let (_, warnings) =
+            AgentSpec::load_with_warnings(tmp.path(), &LoadOptions::default()).unwrap();
+        let diagnostics: Vec<Diagnostic> = warnings.iter().map(|w| w.to_diagnostic()).collect();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::UnknownField);
+        assert_eq!(
+            diagnostics[0].message,
+            "unknown field 'spec.model.temprature'"
+        );
+        assert_eq!(
+            diagnostics[0].hint.as_deref(),
+            Some("did you mean 'temperature'?")
+        );
+        assert_eq!(diagnostics[1].code, DiagnosticCode::UnsupportedField);
+
+        let strict = LoadOptions {
+            strict: true,
+            ..LoadOptions::default()
+        };
+        let diagnostics = AgentSpec::load_with_warnings(tmp.path(), &strict)
+            .unwrap_err()
+            .diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::UnknownField);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(
+            diagnostics[0].span.map(|s| (s.line, s.column)),
+            Some((9, 5))
+        );
+    }
+
+    #[test]
+    fn parents_do_not_pass_on_namespaces_and_are_checked_in_strict_mode() {
+        let tmp = TempDir::new().unwrap();
+        let (base, child) = (tmp.path().join("base"), tmp.path().join("child"));
+        fs::create_dir_all(&base).unwrap();
+        fs::create_dir_all(&child).unwrap();
+        write_yaml(
+            &base,
+            "apiVersion: agnx/v1\nkind: Agent\nmetadata:\n  name: base\n  namespace: platform\nspec:\n  model: openrouter/some-model\n  temprature: 0.2\n",
+        );
+        write_yaml(&child, "extends: ../base\nmetadata:\n  name: child\n");
+
+        let (agent, _) = AgentSpec::load_with_warnings(&child, &LoadOptions::default()).unwrap();
+        assert_eq!(agent.namespace, DEFAULT_NAMESPACE);
+
+        let strict = LoadOptions {
+            strict: true,
+            ..LoadOptions::default()
+        };
+        let diagnostics = AgentSpec::load_with_warnings(&child, &strict)
+            .unwrap_err()
+            .diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::UnknownField);
+        assert_eq!(diagnostics[0].path, base.join("agent.yaml"));
+        assert_eq!(
+            diagnostics[0].span.map(|s| (s.line, s.column)),
+            Some((8, 3))
+        );
+    }
 }
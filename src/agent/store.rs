@@ -1,101 +1,623 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
-use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
 
+use super::DEFAULT_NAMESPACE;
+use super::cache::{AgentCache, CacheStats};
+use super::diagnostic::{Diagnostic, DiagnosticCode};
 use super::error::{AgentLoadError, AgentLoadWarning};
-use super::spec::AgentSpec;
+use super::extends;
+use super::ignore::IgnoreRules;
+use super::spec::{AgentSpec, LoadOptions, NAMESPACE_HINT, is_valid_namespace};
 
 /// Store for loaded agents, shared across request handlers.
-#[derive(Debug, Clone)]
+///
+/// An `AgentStore` is an immutable snapshot. Reloads build a new store and swap it into
+/// the [`AgentRegistry`], so holders of an older snapshot are never affected.
+///
+/// Agents are keyed by `namespace/name`; names only need to be unique within a namespace.
+///
+/// With [`LoadOptions::lazy_cache_size`], `agents` is only an index: the definitions are
+/// validated but the contents of the files they reference are not kept. Full agents are
+/// loaded by [`AgentStore::get`] and kept in an LRU cache. An agent whose full load fails
+/// is left out of [`AgentStore::iter`] as well until it is reloaded, so listing and
+/// getting agents agree.
+#[derive(Debug, Clone, Default)]
 pub struct AgentStore {
-    agents: Arc<HashMap<String, AgentSpec>>,
+    agents: Arc<HashMap<String, Arc<AgentSpec>>>,
+    /// Agent directory -> `namespace/name`, used to map file changes back to agents.
+    dirs: Arc<HashMap<PathBuf, String>>,
+    /// The agents directory that was scanned.
+    root: Arc<PathBuf>,
+    /// Directory of agents deployed through the admin API, scanned after `root`.
+    dynamic_root: Option<Arc<PathBuf>>,
+    /// Options the agents were loaded with, reused on reload.
+    options: Arc<LoadOptions>,
+    /// Fully loaded agents, in lazy mode.
+    cache: Option<Arc<AgentCache>>,
 }
 
 impl AgentStore {
+    /// `path` relative to the agents directory (or the deployed agents directory) it is
+    /// in, so responses do not reveal where agents live on the server. Paths outside
+    /// both, such as a parent reached through `../`, are relative to the agents
+    /// directory.
+    pub fn relative_path(&self, path: &Path) -> String {
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let path = canonical(path);
+        let root = canonical(&self.root);
+        let base = self
+            .dynamic_root
+            .as_deref()
+            .map(|dir| canonical(dir))
+            .filter(|dir| path.starts_with(dir))
+            .unwrap_or(root);
+        let common = path
+            .components()
+            .zip(base.components())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let up = base.components().count() - common;
+        std::iter::repeat_n(Component::ParentDir, up)
+            .chain(path.components().skip(common))
+            .collect::<PathBuf>()
+            .display()
+            .to_string()
+    }
+
     /// Scan a directory for agent subdirectories and load all valid agents.
-    pub fn scan(agents_dir: &Path) -> AgentScanReport {
-        let mut agents = HashMap::new();
+    ///
+    /// Directories are searched up to [`LoadOptions::max_depth`] levels deep, skipping
+    /// those matched by `.agnxignore`. A directory containing `agent.yaml` is an agent;
+    /// its subdirectories are not searched.
+    pub fn scan(agents_dir: &Path, options: &LoadOptions) -> AgentScanReport {
+        Self::scan_all(agents_dir, None, options)
+    }
+
+    /// Like [`AgentStore::scan`], also loading agents deployed through the admin API
+    /// from `dynamic_dir`. On name clashes, agents from `agents_dir` win.
+    pub fn scan_all(
+        agents_dir: &Path,
+        dynamic_dir: Option<&Path>,
+        options: &LoadOptions,
+    ) -> AgentScanReport {
+        let empty = AgentStore {
+            root: Arc::new(agents_dir.to_path_buf()),
+            dynamic_root: dynamic_dir.map(|dir| Arc::new(dir.to_path_buf())),
+            options: Arc::new(LoadOptions {
+                sandbox_root: dynamic_dir.map(Path::to_path_buf),
+                ..options.clone()
+            }),
+            cache: options
+                .lazy_cache_size
+                .map(|capacity| Arc::new(AgentCache::new(capacity))),
+            ..AgentStore::default()
+        };
         let mut warnings = Vec::new();
 
         if !agents_dir.exists() {
             warnings.push(AgentScanWarning::AgentsDirMissing {
                 path: agents_dir.to_path_buf(),
             });
-            return AgentScanReport {
-                store: AgentStore {
-                    agents: Arc::new(agents),
-                },
-                warnings,
-            };
         }
 
-        let entries = match fs::read_dir(agents_dir) {
-            Ok(e) => e,
-            Err(e) => {
+        let mut paths = Vec::new();
+        // The dynamic directory is created on the first deployment, so it may be missing.
+        for root in empty.roots().filter(|root| root.exists()) {
+            let ignore = IgnoreRules::load(root);
+            let mut found = Vec::new();
+            if let Err(e) = discover(root, Path::new(""), options.max_depth, &ignore, &mut found) {
                 warnings.push(AgentScanWarning::AgentsDirReadError {
-                    path: agents_dir.to_path_buf(),
+                    path: root.to_path_buf(),
                     error: e.to_string(),
                 });
-                return AgentScanReport {
-                    store: AgentStore {
-                        agents: Arc::new(agents),
-                    },
-                    warnings,
-                };
+                continue;
             }
+            // Sort so that duplicate-name resolution does not depend on directory order.
+            found.sort();
+            paths.append(&mut found);
+        }
+
+        let mut report = empty.reload_dirs(paths.iter().map(PathBuf::as_path));
+        warnings.append(&mut report.warnings);
+        report.warnings = warnings;
+        report
+    }
+
+    /// Scan the agents directories again from scratch.
+    pub fn rescan(&self) -> AgentScanReport {
+        Self::scan_all(
+            &self.root,
+            self.dynamic_root.as_deref().map(PathBuf::as_path),
+            &self.options,
+        )
+    }
+
+    /// The agents directory, then the dynamic agents directory if there is one.
+    fn roots(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(self.root.as_path())
+            .chain(self.dynamic_root.as_deref().map(PathBuf::as_path))
+    }
+
+    /// Build a new store with the given agent directories re-loaded from disk.
+    ///
+    /// Directories that no longer hold a discoverable agent (no `agent.yaml`, ignored,
+    /// or too deep) are treated as removed. If an agent that is already loaded fails to
+    /// load, its last good version is kept.
+    pub fn reload_dirs<'a>(
+        &self,
+        agent_dirs: impl IntoIterator<Item = &'a Path>,
+    ) -> AgentScanReport {
+        let mut agents = (*self.agents).clone();
+        let mut dirs = (*self.dirs).clone();
+        let mut warnings = Vec::new();
+        let mut reloaded = Vec::new();
+        let roots: Vec<(&Path, IgnoreRules)> = self
+            .roots()
+            .map(|root| (root, IgnoreRules::load(root)))
+            .collect();
+        let load = if self.cache.is_some() {
+            AgentSpec::load_manifest
+        } else {
+            AgentSpec::load_with_lookup
         };
 
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if !path.is_dir() {
-                continue;
+        // Agents that extend a changed agent are reloaded with it, after it.
+        let mut targets: Vec<PathBuf> = agent_dirs.into_iter().map(Path::to_path_buf).collect();
+        let changed: Vec<PathBuf> = targets.iter().map(|d| extends::canonical(d)).collect();
+        for (dir, key) in dirs.iter() {
+            let extends_changed = agents
+                .get(key)
+                .is_some_and(|a| a.parents.iter().any(|p| changed.contains(&p.dir)));
+            if extends_changed && !targets.contains(dir) {
+                targets.push(dir.clone());
             }
+        }
 
-            let yaml_path = path.join("agent.yaml");
-            if !yaml_path.exists() {
+        for dir in extends::dependency_order(&targets) {
+            let dir = dir.as_path();
+            let previous = dirs.get(dir).cloned();
+
+            reloaded.extend(previous.clone());
+
+            let Some(derived) = self.discoverable(dir, &roots) else {
+                if let Some(key) = previous {
+                    dirs.remove(dir);
+                    agents.remove(&key);
+                }
                 continue;
-            }
+            };
 
-            match AgentSpec::load_with_warnings(&path) {
+            let loaded = load(dir, &self.options, &|name| {
+                dir_of(&dirs, derived.as_deref(), name)
+            })
+            .and_then(|(mut agent, agent_warnings)| {
+                if agent.metadata.namespace.is_none() {
+                    agent.namespace = derived_namespace(dir, derived.as_deref())?;
+                }
+                Ok((agent, agent_warnings))
+            });
+            match loaded {
                 Ok((agent, agent_warnings)) => {
-                    let name = agent.metadata.name.clone();
-                    agents.insert(name, agent);
+                    let key = agent_key(&agent.namespace, &agent.metadata.name);
+                    if let Some(existing) = owner_of(&dirs, &key, dir) {
+                        warnings.push(AgentScanWarning::DuplicateName {
+                            name: agent.metadata.name.clone(),
+                            namespace: agent.namespace.clone(),
+                            path: dir.to_path_buf(),
+                            existing,
+                        });
+                        continue;
+                    }
+                    if let Some(old) = previous.filter(|old| *old != key) {
+                        agents.remove(&old);
+                    }
+                    dirs.insert(dir.to_path_buf(), key.clone());
+                    reloaded.push(key.clone());
+                    agents.insert(key, Arc::new(agent));
                     for w in agent_warnings {
                         warnings.push(AgentScanWarning::AgentWarning(w));
                     }
                 }
-                Err(e) => {
-                    warnings.push(AgentScanWarning::InvalidAgent {
-                        path: path.to_path_buf(),
+                Err(e) => match previous {
+                    Some(key) => warnings.push(AgentScanWarning::ReloadFailed {
+                        agent: display_name(&key).to_string(),
+                        path: dir.to_path_buf(),
                         error: e,
-                    });
-                }
+                    }),
+                    None => warnings.push(AgentScanWarning::InvalidAgent {
+                        path: dir.to_path_buf(),
+                        error: e,
+                    }),
+                },
             }
         }
 
+        if let Some(cache) = &self.cache {
+            cache.invalidate(&reloaded);
+        }
         AgentScanReport {
             store: AgentStore {
                 agents: Arc::new(agents),
+                dirs: Arc::new(dirs),
+                root: self.root.clone(),
+                dynamic_root: self.dynamic_root.clone(),
+                options: self.options.clone(),
+                cache: self.cache.clone(),
             },
             warnings,
         }
     }
 
+    /// If `dir` holds an agent that a scan would find, the namespace implied by its
+    /// location: `Some(None)` for agents directly under the agents directory.
+    fn discoverable(&self, dir: &Path, roots: &[(&Path, IgnoreRules)]) -> Option<Option<String>> {
+        let (root, ignore, relative) = roots
+            .iter()
+            .find_map(|(root, ignore)| Some((root, ignore, dir.strip_prefix(root).ok()?)))?;
+        let depth = relative.components().count();
+        if depth == 0 || depth > self.options.max_depth || ignore.is_ignored(relative) {
+            return None;
+        }
+        if !dir.join("agent.yaml").is_file() {
+            return None;
+        }
+        // Agents nested inside another agent's directory are not discovered.
+        let parents: Vec<&Path> = relative.ancestors().skip(1).collect();
+        if parents
+            .iter()
+            .any(|p| !p.as_os_str().is_empty() && root.join(p).join("agent.yaml").is_file())
+        {
+            return None;
+        }
+
+        let segments: Vec<String> = relative
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        Some((!segments.is_empty()).then(|| segments.join(".")))
+    }
+
+    pub(super) fn dynamic_root(&self) -> Option<&Path> {
+        self.dynamic_root.as_deref().map(PathBuf::as_path)
+    }
+
+    pub(super) fn options(&self) -> &LoadOptions {
+        &self.options
+    }
+
+    /// Directory of the agent `namespace/name`.
+    pub(super) fn agent_dir(&self, namespace: &str, name: &str) -> Option<&Path> {
+        let key = agent_key(namespace, name);
+        self.dirs
+            .iter()
+            .find(|(_, k)| **k == key)
+            .map(|(dir, _)| dir.as_path())
+    }
+
+    /// Directory of the agent that `extends: <name>` refers to from `namespace`.
+    pub(super) fn extends_lookup(&self, namespace: &str, name: &str) -> Option<PathBuf> {
+        dir_of(&self.dirs, Some(namespace), name)
+    }
+
+    /// How deep scans and reloads look for agent directories.
+    pub(super) fn max_depth(&self) -> usize {
+        self.options.max_depth
+    }
+
     /// Get the number of loaded agents.
     pub fn len(&self) -> usize {
-        self.agents.len()
+        self.iter().count()
     }
 
-    /// Iterate over all agents.
+    /// Iterate over all agents, keyed by `namespace/name`.
+    ///
+    /// In lazy mode these come from the index, so prompt and bootstrap contents are not
+    /// loaded; use [`AgentStore::get`] for the full agent.
     pub fn iter(&self) -> impl Iterator<Item = (&String, &AgentSpec)> {
-        self.agents.iter()
+        self.agents
+            .iter()
+            .filter(|(key, _)| !self.has_failed(key))
+            .map(|(key, agent)| (key, agent.as_ref()))
+    }
+
+    /// Like [`AgentStore::iter`], with the directory each agent was loaded from.
+    pub fn iter_dirs(&self) -> impl Iterator<Item = (&Path, &AgentSpec)> {
+        self.dirs
+            .iter()
+            .filter(|(_, key)| !self.has_failed(key))
+            .filter_map(|(dir, key)| Some((dir.as_path(), self.agents.get(key)?.as_ref())))
+    }
+
+    /// Whether a lazily indexed agent failed to load in full.
+    fn has_failed(&self, key: &str) -> bool {
+        self.cache
+            .as_ref()
+            .is_some_and(|cache| cache.has_failed(key))
+    }
+
+    /// Get an agent by name: from the default namespace if it has one by that name,
+    /// otherwise the only agent with that name in any namespace.
+    pub fn get(&self, name: &str) -> Option<Arc<AgentSpec>> {
+        if let Some(agent) = self.get_in(DEFAULT_NAMESPACE, name) {
+            return Some(agent);
+        }
+        let mut matches = self.iter().filter(|(_, a)| a.metadata.name == name);
+        match (matches.next(), matches.next()) {
+            (Some((_, agent)), None) => self.get_in(&agent.namespace, name),
+            _ => None,
+        }
+    }
+
+    /// Get an agent by namespace and name.
+    pub fn get_in(&self, namespace: &str, name: &str) -> Option<Arc<AgentSpec>> {
+        let key = agent_key(namespace, name);
+        let indexed = self.agents.get(&key)?;
+        let Some(cache) = &self.cache else {
+            return Some(indexed.clone());
+        };
+        if cache.has_failed(&key) {
+            return None;
+        }
+        if let Some(agent) = cache.get(&key) {
+            return Some(agent);
+        }
+
+        let (dir, _) = self.dirs.iter().find(|(_, k)| **k == key)?;
+        match AgentSpec::load_with_lookup(dir, &self.options, &|name| {
+            dir_of(&self.dirs, Some(&indexed.namespace), name)
+        }) {
+            Ok((mut agent, agent_warnings)) => {
+                agent.namespace = indexed.namespace.clone();
+                let agent = Arc::new(agent);
+                cache.insert(key, agent.clone());
+                let warnings: Vec<AgentScanWarning> = agent_warnings
+                    .into_iter()
+                    .map(AgentScanWarning::AgentWarning)
+                    .collect();
+                log_scan_warnings(&warnings);
+                Some(agent)
+            }
+            Err(error) => {
+                cache.fail(key);
+                log_scan_warnings(&[AgentScanWarning::InvalidAgent {
+                    path: dir.clone(),
+                    error,
+                }]);
+                None
+            }
+        }
+    }
+
+    /// [`AgentStore::get`] for async handlers: in lazy mode the agent may have to be
+    /// read from disk, which is done on the blocking thread pool.
+    pub async fn fetch(&self, name: &str) -> Option<Arc<AgentSpec>> {
+        let name = name.to_string();
+        self.blocking(move |store| store.get(&name)).await
+    }
+
+    /// [`AgentStore::get_in`] for async handlers, like [`AgentStore::fetch`].
+    pub async fn fetch_in(&self, namespace: &str, name: &str) -> Option<Arc<AgentSpec>> {
+        let (namespace, name) = (namespace.to_string(), name.to_string());
+        self.blocking(move |store| store.get_in(&namespace, &name))
+            .await
+    }
+
+    async fn blocking(
+        &self,
+        get: impl FnOnce(&AgentStore) -> Option<Arc<AgentSpec>> + Send + 'static,
+    ) -> Option<Arc<AgentSpec>> {
+        if self.cache.is_none() {
+            return get(self);
+        }
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || get(&store))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Hit/miss counters of the agent cache, if agents are loaded lazily.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Namespaces that have an agent called `name`, sorted.
+    pub fn namespaces_of(&self, name: &str) -> Vec<&str> {
+        let mut namespaces: Vec<&str> = self
+            .iter()
+            .filter(|(_, a)| a.metadata.name == name)
+            .map(|(_, a)| a.namespace.as_str())
+            .collect();
+        namespaces.sort();
+        namespaces
+    }
+}
+
+/// Store key for an agent.
+fn agent_key(namespace: &str, name: &str) -> String {
+    format!("{namespace}/{name}")
+}
+
+/// How an agent is referred to in messages: its name, qualified by its namespace
+/// unless that is the default one.
+fn display_name(key: &str) -> &str {
+    key.strip_prefix(DEFAULT_NAMESPACE)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .unwrap_or(key)
+}
+
+/// Collect agent directories below `dir` (at `relative` from the agents directory).
+fn discover(
+    dir: &Path,
+    relative: &Path,
+    levels: usize,
+    ignore: &IgnoreRules,
+    found: &mut Vec<PathBuf>,
+) -> std::io::Result<()> {
+    if levels == 0 {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let relative = relative.join(entry.file_name());
+        if !path.is_dir() || ignore.is_ignored(&relative) {
+            continue;
+        }
+        if path.join("agent.yaml").exists() {
+            found.push(path);
+        } else {
+            // Unreadable nested directories are skipped rather than failing the scan.
+            let _ = discover(&path, &relative, levels - 1, ignore, found);
+        }
     }
+    Ok(())
+}
+
+/// The namespace for an agent without `metadata.namespace`, checked for validity.
+fn derived_namespace(dir: &Path, derived: Option<&str>) -> Result<String, AgentLoadError> {
+    let Some(namespace) = derived else {
+        return Ok(DEFAULT_NAMESPACE.to_string());
+    };
+    if is_valid_namespace(namespace) {
+        return Ok(namespace.to_string());
+    }
+    Err(AgentLoadError::Validation(vec![
+        Diagnostic::error(
+            DiagnosticCode::InvalidNamespace,
+            &dir.join("agent.yaml"),
+            format!("directory implies invalid namespace '{namespace}'"),
+        )
+        .with_hint(format!(
+            "rename the parent directories or set metadata.namespace; {NAMESPACE_HINT}"
+        )),
+    ]))
+}
 
-    /// Get an agent by name.
-    pub fn get(&self, name: &str) -> Option<&AgentSpec> {
-        self.agents.get(name)
+/// Directory of the loaded agent called `name`, for `extends: <name>`.
+///
+/// Prefers the extending agent's namespace, then the default namespace, then an agent
+/// with that name in any namespace, as long as there is only one.
+fn dir_of(dirs: &HashMap<PathBuf, String>, namespace: Option<&str>, name: &str) -> Option<PathBuf> {
+    let find = |key: &str| {
+        dirs.iter()
+            .find(|(_, k)| k.as_str() == key)
+            .map(|(d, _)| d.clone())
+    };
+    if let Some(dir) = find(&agent_key(namespace.unwrap_or(DEFAULT_NAMESPACE), name)) {
+        return Some(dir);
     }
+    if let Some(dir) = find(&agent_key(DEFAULT_NAMESPACE, name)) {
+        return Some(dir);
+    }
+    let mut matches = dirs
+        .iter()
+        .filter(|(_, k)| k.split_once('/').is_some_and(|(_, n)| n == name));
+    match (matches.next(), matches.next()) {
+        (Some((dir, _)), None) => Some(dir.clone()),
+        _ => None,
+    }
+}
+
+/// Directory (other than `dir`) that already provides the agent with store key `key`.
+fn owner_of(dirs: &HashMap<PathBuf, String>, key: &str, dir: &Path) -> Option<PathBuf> {
+    dirs.iter()
+        .find(|(d, k)| k.as_str() == key && d.as_path() != dir)
+        .map(|(d, _)| d.clone())
+}
+
+/// Shared handle to the current [`AgentStore`] snapshot.
+///
+/// Request handlers take a snapshot once per request; reloads atomically swap in a new
+/// store, so in-flight requests keep reading the snapshot they started with.
+#[derive(Debug, Clone, Default)]
+pub struct AgentRegistry {
+    current: Arc<RwLock<AgentStore>>,
+    /// Serializes everything that builds a new store from the current one (reloads,
+    /// rescans and admin API deployments), so that no update is lost to another one
+    /// swapping in a store computed from an older snapshot.
+    updates: Arc<Mutex<()>>,
+}
+
+impl AgentRegistry {
+    pub fn new(store: AgentStore) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(store)),
+            updates: Arc::default(),
+        }
+    }
+
+    /// Hold off other updates until the guard is dropped.
+    pub(super) fn lock_updates(&self) -> MutexGuard<'_, ()> {
+        self.updates.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Get the current snapshot of loaded agents.
+    pub fn snapshot(&self) -> AgentStore {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Scan the agents directory from scratch and swap in the resulting store.
+    pub fn rescan(&self) -> Vec<AgentScanWarning> {
+        let _guard = self.lock_updates();
+        let report = self.snapshot().rescan();
+        self.swap(report)
+    }
+
+    /// Re-load the given agent directories and swap in the resulting store.
+    pub fn reload_dirs<'a>(
+        &self,
+        agent_dirs: impl IntoIterator<Item = &'a Path>,
+    ) -> Vec<AgentScanWarning> {
+        let guard = self.lock_updates();
+        self.reload_dirs_locked(&guard, agent_dirs)
+    }
+
+    /// [`AgentRegistry::reload_dirs`] for callers that already hold the update lock.
+    pub(super) fn reload_dirs_locked<'a>(
+        &self,
+        _guard: &MutexGuard<'_, ()>,
+        agent_dirs: impl IntoIterator<Item = &'a Path>,
+    ) -> Vec<AgentScanWarning> {
+        let report = self.snapshot().reload_dirs(agent_dirs);
+        self.swap(report)
+    }
+
+    fn swap(&self, report: AgentScanReport) -> Vec<AgentScanWarning> {
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = report.store;
+        report.warnings
+    }
+}
+
+/// Map changed file paths to the directories, up to `max_depth` levels below
+/// `agents_dir`, that may be the agent containing them.
+///
+/// Every ancestor is a candidate, since an agent may have been added or removed at any
+/// level; [`AgentStore::reload_dirs`] skips the ones that are not agents.
+pub(super) fn affected_agent_dirs<'a>(
+    agents_dir: &Path,
+    watch_root: &Path,
+    max_depth: usize,
+    paths: impl IntoIterator<Item = &'a PathBuf>,
+) -> BTreeSet<PathBuf> {
+    let mut dirs = BTreeSet::new();
+    for relative in paths
+        .into_iter()
+        .filter_map(|p| p.strip_prefix(watch_root).ok())
+    {
+        let mut dir = agents_dir.to_path_buf();
+        for component in relative.components().take(max_depth) {
+            dir.push(component);
+            dirs.insert(dir.clone());
+        }
+    }
+    dirs
 }
 
 /// Non-fatal issues encountered while scanning the agents directory.
@@ -112,7 +634,82 @@ pub enum AgentScanWarning {
         path: PathBuf,
         error: AgentLoadError,
     },
+    ReloadFailed {
+        agent: String,
+        path: PathBuf,
+        error: AgentLoadError,
+    },
+    /// Two agent directories declare the same `metadata.name` in one namespace; the
+    /// first one (by path) is kept.
+    DuplicateName {
+        name: String,
+        namespace: String,
+        path: PathBuf,
+        existing: PathBuf,
+    },
     AgentWarning(AgentLoadWarning),
+    /// The agent's Ollama server does not have its model.
+    MissingModel {
+        agent: String,
+        path: PathBuf,
+        model: String,
+        base_url: String,
+    },
+}
+
+impl AgentScanWarning {
+    /// Structured diagnostics describing this warning.
+    ///
+    /// Directory-level and cross-agent problems are reported as errors, since they
+    /// indicate agents that will not be served as written.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            AgentScanWarning::AgentsDirMissing { path } => vec![Diagnostic::error(
+                DiagnosticCode::AgentsDirMissing,
+                path,
+                "agents directory does not exist",
+            )],
+            AgentScanWarning::AgentsDirReadError { path, error } => vec![Diagnostic::error(
+                DiagnosticCode::ReadError,
+                path,
+                format!("failed to read agents directory: {error}"),
+            )],
+            AgentScanWarning::InvalidAgent { error, .. }
+            | AgentScanWarning::ReloadFailed { error, .. } => error.diagnostics(),
+            AgentScanWarning::DuplicateName {
+                name,
+                namespace,
+                path,
+                existing,
+            } => vec![
+                Diagnostic::error(
+                    DiagnosticCode::DuplicateName,
+                    &path.join("agent.yaml"),
+                    format!("agent name '{name}' is already used in namespace '{namespace}'"),
+                )
+                .with_hint(format!(
+                    "'{name}' is also defined in {}; agent names must be unique within a namespace",
+                    existing.join("agent.yaml").display()
+                )),
+            ],
+            AgentScanWarning::AgentWarning(w) => vec![w.to_diagnostic()],
+            AgentScanWarning::MissingModel {
+                path,
+                model,
+                base_url,
+                ..
+            } => vec![
+                Diagnostic::warning(
+                    DiagnosticCode::MissingModel,
+                    &path.join("agent.yaml"),
+                    format!("model '{model}' is not available on the Ollama server at {base_url}"),
+                )
+                .with_hint(format!(
+                    "pull it with `agnx models pull` or `ollama pull {model}`"
+                )),
+            ],
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -125,12 +722,7 @@ pub struct AgentScanReport {
 ///
 /// If `agents_dir` is relative, it is resolved relative to the config file directory.
 pub fn resolve_agents_dir(config_path: &Path, agents_dir: &Path) -> PathBuf {
-    if agents_dir.is_absolute() {
-        return agents_dir.to_path_buf();
-    }
-
-    let config_dir = config_path.parent().unwrap_or_else(|| Path::new("."));
-    config_dir.join(agents_dir)
+    crate::config::resolve_relative(config_path, agents_dir)
 }
 
 /// Log non-fatal warnings produced by agent scanning.
@@ -146,22 +738,116 @@ pub fn log_scan_warnings(warnings: &[AgentScanWarning]) {
                 warn!(path = %path.display(), error = %error, "Failed to read agents directory");
             }
             AgentScanWarning::InvalidAgent { path, error } => {
-                warn!(path = %path.display(), error = %error, "Skipping invalid agent");
+                for d in error.diagnostics() {
+                    warn!(path = %path.display(), error = %d, "Skipping invalid agent");
+                }
+            }
+            AgentScanWarning::ReloadFailed { agent, path, error } => {
+                for d in error.diagnostics() {
+                    warn!(
+                        agent = %agent,
+                        path = %path.display(),
+                        error = %d,
+                        "Failed to reload agent, keeping last good version"
+                    );
+                }
             }
-            AgentScanWarning::AgentWarning(AgentLoadWarning::MissingFile {
+            AgentScanWarning::MissingModel {
                 agent,
-                field,
+                model,
+                base_url,
+                ..
+            } => {
+                warn!(
+                    agent = %agent,
+                    model = %model,
+                    base_url = %base_url,
+                    "Model is not available on the Ollama server; pull it with `agnx models pull`"
+                );
+            }
+            AgentScanWarning::DuplicateName {
+                name,
+                namespace,
                 path,
-                error,
-            }) => {
+                existing,
+            } => {
+                warn!(
+                    agent = %name,
+                    namespace = %namespace,
+                    path = %path.display(),
+                    existing = %existing.display(),
+                    "Skipping agent with duplicate name"
+                );
+            }
+            AgentScanWarning::AgentWarning(
+                w @ AgentLoadWarning::MissingFile {
+                    agent, field, path, ..
+                },
+            ) => {
                 warn!(
                     agent = %agent,
                     field = %field,
                     path = %path.display(),
-                    error = %error,
+                    error = %w.to_diagnostic(),
                     "Missing referenced agent file"
                 );
             }
+            AgentScanWarning::AgentWarning(
+                w @ (AgentLoadWarning::UnknownField { agent, field, .. }
+                | AgentLoadWarning::UnsupportedField { agent, field, .. }),
+            ) => {
+                warn!(
+                    agent = %agent,
+                    field = %field,
+                    error = %w.to_diagnostic(),
+                    "Ignoring agent field"
+                );
+            }
+            AgentScanWarning::AgentWarning(
+                w @ AgentLoadWarning::DeprecatedField { agent, field, .. },
+            ) => {
+                warn!(
+                    agent = %agent,
+                    field = %field,
+                    error = %w.to_diagnostic(),
+                    "Deprecated agent field"
+                );
+            }
+            AgentScanWarning::AgentWarning(
+                w @ (AgentLoadWarning::InvalidSkill { agent, path, .. }
+                | AgentLoadWarning::DuplicateSkill { agent, path, .. }),
+            ) => {
+                warn!(
+                    agent = %agent,
+                    path = %path.display(),
+                    error = %w.to_diagnostic(),
+                    "Problem with agent skill"
+                );
+            }
+            AgentScanWarning::AgentWarning(
+                w @ AgentLoadWarning::UnsupportedParameter {
+                    agent, parameter, ..
+                },
+            ) => {
+                warn!(
+                    agent = %agent,
+                    parameter = %parameter,
+                    error = %w.to_diagnostic(),
+                    "Ignoring model parameter"
+                );
+            }
+            AgentScanWarning::AgentWarning(
+                w @ AgentLoadWarning::UnknownTemplateVariable {
+                    agent, variable, ..
+                },
+            ) => {
+                warn!(
+                    agent = %agent,
+                    variable = %variable,
+                    error = %w.to_diagnostic(),
+                    "Unknown template variable"
+                );
+            }
         }
     }
 }
@@ -170,6 +856,7 @@ pub fn log_scan_warnings(warnings: &[AgentScanWarning]) {
 mod tests {
     use super::*;
     use crate::agent::{API_VERSION_V1ALPHA1, KIND_AGENT};
+    use std::num::NonZeroUsize;
     use tempfile::TempDir;
 
     fn create_minimal_agent(dir: &Path, name: &str) {
@@ -193,7 +880,7 @@ spec:
         let agents_dir = tmp.path().join("agents");
         fs::create_dir(&agents_dir).unwrap();
 
-        let report = AgentStore::scan(&agents_dir);
+        let report = AgentStore::scan(&agents_dir, &LoadOptions::default());
         assert_eq!(report.store.len(), 0);
         assert!(report.warnings.is_empty());
     }
@@ -203,7 +890,7 @@ spec:
         let tmp = TempDir::new().unwrap();
         let agents_dir = tmp.path().join("nonexistent");
 
-        let report = AgentStore::scan(&agents_dir);
+        let report = AgentStore::scan(&agents_dir, &LoadOptions::default());
         assert_eq!(report.store.len(), 0);
         assert!(matches!(
             report.warnings.first(),
@@ -225,7 +912,7 @@ spec:
         fs::create_dir(&agent2_dir).unwrap();
         create_minimal_agent(&agent2_dir, "agent-two");
 
-        let report = AgentStore::scan(&agents_dir);
+        let report = AgentStore::scan(&agents_dir, &LoadOptions::default());
         assert_eq!(report.store.len(), 2);
         assert!(report.store.get("agent-one").is_some());
         assert!(report.store.get("agent-two").is_some());
@@ -259,7 +946,7 @@ spec:
         )
         .unwrap();
 
-        let report = AgentStore::scan(&agents_dir);
+        let report = AgentStore::scan(&agents_dir, &LoadOptions::default());
         assert_eq!(report.store.len(), 1);
         assert!(report.store.get("valid-agent").is_some());
         assert!(report.store.get("invalid-agent").is_none());
@@ -270,4 +957,495 @@ spec:
                 .any(|w| matches!(w, AgentScanWarning::InvalidAgent { .. }))
         );
     }
+
+    #[test]
+    fn reload_dirs_picks_up_edits_and_new_agents() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        let agent_dir = agents_dir.join("agent-one");
+        fs::create_dir_all(&agent_dir).unwrap();
+        create_minimal_agent(&agent_dir, "agent-one");
+
+        let registry =
+            AgentRegistry::new(AgentStore::scan(&agents_dir, &LoadOptions::default()).store);
+        let before = registry.snapshot();
+
+        fs::write(agent_dir.join("SYSTEM_PROMPT.md"), "Be brief.").unwrap();
+        fs::write(
+            agent_dir.join("agent.yaml"),
+            r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: agent-one
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+  system_prompt: ./SYSTEM_PROMPT.md
+"#,
+        )
+        .unwrap();
+        let new_dir = agents_dir.join("agent-two");
+        fs::create_dir(&new_dir).unwrap();
+        create_minimal_agent(&new_dir, "agent-two");
+
+        let warnings = registry.reload_dirs([agent_dir.as_path(), new_dir.as_path()]);
+        assert!(warnings.is_empty());
+
+        let after = registry.snapshot();
+        assert_eq!(after.len(), 2);
+        assert_eq!(
+            after
+                .get("agent-one")
+                .unwrap()
+                .system_prompt
+                .as_ref()
+                .and_then(|p| p.content.as_deref()),
+            Some("Be brief.")
+        );
+
+        // Snapshots taken before the reload are unaffected.
+        assert_eq!(before.len(), 1);
+        assert!(before.get("agent-one").unwrap().system_prompt.is_none());
+    }
+
+    #[test]
+    fn reload_dirs_keeps_last_good_version_on_broken_edit() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        let agent_dir = agents_dir.join("agent-one");
+        fs::create_dir_all(&agent_dir).unwrap();
+        create_minimal_agent(&agent_dir, "agent-one");
+
+        let registry =
+            AgentRegistry::new(AgentStore::scan(&agents_dir, &LoadOptions::default()).store);
+        fs::write(agent_dir.join("agent.yaml"), "apiVersion: [").unwrap();
+
+        let warnings = registry.reload_dirs([agent_dir.as_path()]);
+        assert!(matches!(
+            warnings.as_slice(),
+            [AgentScanWarning::ReloadFailed { agent, .. }] if agent == "agent-one"
+        ));
+        assert!(registry.snapshot().get("agent-one").is_some());
+    }
+
+    #[test]
+    fn reload_dirs_removes_deleted_and_renamed_agents() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        let one_dir = agents_dir.join("agent-one");
+        let two_dir = agents_dir.join("agent-two");
+        fs::create_dir_all(&one_dir).unwrap();
+        fs::create_dir_all(&two_dir).unwrap();
+        create_minimal_agent(&one_dir, "agent-one");
+        create_minimal_agent(&two_dir, "agent-two");
+
+        let registry =
+            AgentRegistry::new(AgentStore::scan(&agents_dir, &LoadOptions::default()).store);
+        fs::remove_dir_all(&one_dir).unwrap();
+        create_minimal_agent(&two_dir, "agent-renamed");
+
+        let warnings = registry.reload_dirs([one_dir.as_path(), two_dir.as_path()]);
+        assert!(warnings.is_empty());
+
+        let store = registry.snapshot();
+        assert_eq!(store.len(), 1);
+        assert!(store.get("agent-renamed").is_some());
+    }
+
+    #[test]
+    fn affected_agent_dirs_maps_files_to_agent_dirs() {
+        let changed = [
+            PathBuf::from("/abs/agents/agent-one/SYSTEM_PROMPT.md"),
+            PathBuf::from("/abs/agents/agent-one/agent.yaml"),
+            PathBuf::from("/abs/agents/team/agent-two"),
+            PathBuf::from("/abs/agents/a/b/c/d/e.md"),
+            PathBuf::from("/elsewhere/file.md"),
+        ];
+
+        let dirs = affected_agent_dirs(Path::new("agents"), Path::new("/abs/agents"), 2, &changed);
+        assert_eq!(
+            dirs.into_iter().collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("agents/a"),
+                PathBuf::from("agents/a/b"),
+                PathBuf::from("agents/agent-one"),
+                PathBuf::from("agents/agent-one/SYSTEM_PROMPT.md"),
+                PathBuf::from("agents/agent-one/agent.yaml"),
+                PathBuf::from("agents/team"),
+                PathBuf::from("agents/team/agent-two"),
+            ]
+        );
+    }
+
+    #[test]
+    fn agent_store_scan_keeps_first_of_duplicate_names() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        for dir in ["b-agent", "a-agent"] {
+            let agent_dir = agents_dir.join(dir);
+            fs::create_dir_all(&agent_dir).unwrap();
+            create_minimal_agent(&agent_dir, "same-name");
+        }
+
+        let report = AgentStore::scan(&agents_dir, &LoadOptions::default());
+        assert_eq!(report.store.len(), 1);
+        assert!(matches!(
+            report.warnings.as_slice(),
+            [AgentScanWarning::DuplicateName { path, existing, .. }]
+                if path.ends_with("b-agent") && existing.ends_with("a-agent")
+        ));
+
+        // A reload must not let the duplicate take over the name either.
+        let registry = AgentRegistry::new(report.store);
+        let warnings = registry.reload_dirs([agents_dir.join("b-agent").as_path()]);
+        assert!(matches!(
+            warnings.as_slice(),
+            [AgentScanWarning::DuplicateName { .. }]
+        ));
+    }
+
+    fn write_base_and_child(agents_dir: &Path) -> (PathBuf, PathBuf) {
+        let base_dir = agents_dir.join("z-base");
+        let child_dir = agents_dir.join("a-child");
+        fs::create_dir_all(&base_dir).unwrap();
+        fs::create_dir_all(&child_dir).unwrap();
+
+        fs::write(
+            base_dir.join("agent.yaml"),
+            r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: base
+  labels:
+    team: core
+    tier: free
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+    temperature: 0.7
+  instructions: ./INSTRUCTIONS.md
+"#,
+        )
+        .unwrap();
+        fs::write(base_dir.join("INSTRUCTIONS.md"), "Shared rules.").unwrap();
+        fs::write(
+            child_dir.join("agent.yaml"),
+            r#"extends: base
+metadata:
+  name: child
+  labels:
+    tier: premium
+spec:
+  model:
+    temperature: 0.2
+"#,
+        )
+        .unwrap();
+        (base_dir, child_dir)
+    }
+
+    #[test]
+    fn agent_store_scan_resolves_extends_in_dependency_order() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        let (base_dir, _) = write_base_and_child(&agents_dir);
+
+        let report = AgentStore::scan(&agents_dir, &LoadOptions::default());
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+        let child = report.store.get("child").unwrap();
+        assert_eq!(child.api_version, API_VERSION_V1ALPHA1);
+        assert_eq!(child.metadata.labels["team"], "core");
+        assert_eq!(child.metadata.labels["tier"], "premium");
+        assert_eq!(child.model.name, "anthropic/claude-sonnet-4");
+        assert_eq!(child.model.temperature, Some(0.2));
+        assert_eq!(
+            child.instructions.as_ref().unwrap().content.as_deref(),
+            Some("Shared rules.")
+        );
+        assert_eq!(child.parents.len(), 1);
+        assert_eq!(child.parents[0].name.as_deref(), Some("base"));
+        assert_eq!(child.parents[0].dir, fs::canonicalize(&base_dir).unwrap());
+    }
+
+    #[test]
+    fn reloading_a_parent_reloads_its_children() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        let (base_dir, _) = write_base_and_child(&agents_dir);
+
+        let registry =
+            AgentRegistry::new(AgentStore::scan(&agents_dir, &LoadOptions::default()).store);
+        let base_yaml = fs::read_to_string(base_dir.join("agent.yaml")).unwrap();
+        fs::write(
+            base_dir.join("agent.yaml"),
+            base_yaml.replace("claude-sonnet-4", "claude-opus-4"),
+        )
+        .unwrap();
+
+        let warnings = registry.reload_dirs([base_dir.as_path()]);
+        assert!(warnings.is_empty());
+        assert_eq!(
+            registry.snapshot().get("child").unwrap().model.name,
+            "anthropic/claude-opus-4"
+        );
+    }
+
+    #[test]
+    fn extends_cycles_are_errors() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        for (dir, parent) in [("one", "../two"), ("two", "../one")] {
+            let agent_dir = agents_dir.join(dir);
+            fs::create_dir_all(&agent_dir).unwrap();
+            create_minimal_agent(&agent_dir, dir);
+            let yaml = fs::read_to_string(agent_dir.join("agent.yaml")).unwrap();
+            fs::write(
+                agent_dir.join("agent.yaml"),
+                format!("extends: {parent}\n{yaml}"),
+            )
+            .unwrap();
+        }
+
+        let report = AgentStore::scan(&agents_dir, &LoadOptions::default());
+        assert_eq!(report.store.len(), 0);
+        assert_eq!(report.warnings.len(), 2);
+        for warning in &report.warnings {
+            let diagnostics = warning.diagnostics();
+            assert_eq!(diagnostics[0].code, DiagnosticCode::InheritanceCycle);
+            assert_eq!(
+                diagnostics[0].span.map(|s| (s.line, s.column)),
+                Some((1, 10))
+            );
+        }
+    }
+
+    fn write_agent(agents_dir: &Path, relative: &str, name: &str) -> PathBuf {
+        let dir = agents_dir.join(relative);
+        fs::create_dir_all(&dir).unwrap();
+        create_minimal_agent(&dir, name);
+        dir
+    }
+
+    #[test]
+    fn scan_discovers_nested_agents_in_namespaces() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        write_agent(&agents_dir, "support", "support");
+        write_agent(&agents_dir, "platform/search/support", "support");
+        write_agent(&agents_dir, "platform/indexer", "indexer");
+        // Below an agent directory nothing is discovered.
+        write_agent(&agents_dir, "support/nested", "nested");
+        let pinned = write_agent(&agents_dir, "misc/pinned", "pinned");
+        let yaml = fs::read_to_string(pinned.join("agent.yaml")).unwrap();
+        fs::write(
+            pinned.join("agent.yaml"),
+            yaml.replace("  name: pinned", "  name: pinned\n  namespace: ops"),
+        )
+        .unwrap();
+
+        let report = AgentStore::scan(&agents_dir, &LoadOptions::default());
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        let mut keys: Vec<&String> = report.store.iter().map(|(k, _)| k).collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "default/support",
+                "ops/pinned",
+                "platform.search/support",
+                "platform/indexer"
+            ]
+        );
+
+        let store = &report.store;
+        assert_eq!(store.get("support").unwrap().namespace, "default");
+        assert_eq!(store.get("indexer").unwrap().namespace, "platform");
+        assert!(store.get_in("platform.search", "support").is_some());
+        assert_eq!(
+            store.namespaces_of("support"),
+            ["default", "platform.search"]
+        );
+    }
+
+    #[test]
+    fn same_name_in_two_namespaces_needs_the_namespace() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        write_agent(&agents_dir, "a/bot", "bot");
+        write_agent(&agents_dir, "b/bot", "bot");
+
+        let report = AgentStore::scan(&agents_dir, &LoadOptions::default());
+        assert!(report.warnings.is_empty());
+        assert_eq!(report.store.len(), 2);
+        assert!(report.store.get("bot").is_none());
+        assert!(report.store.get_in("b", "bot").is_some());
+    }
+
+    #[test]
+    fn scan_honours_agnxignore_and_max_depth() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        write_agent(&agents_dir, "keep", "keep");
+        write_agent(&agents_dir, "wip-agent", "wip");
+        write_agent(&agents_dir, "team/drafts/draft", "draft");
+        write_agent(&agents_dir, "a/b/c/deep", "deep");
+        fs::write(
+            agents_dir.join(".agnxignore"),
+            "# not ready\nwip-*\ndrafts/\n",
+        )
+        .unwrap();
+
+        let report = AgentStore::scan(&agents_dir, &LoadOptions::default());
+        let names = |store: &AgentStore| {
+            let mut names: Vec<String> = store.iter().map(|(k, _)| k.clone()).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(&report.store), ["default/keep"]);
+
+        let options = LoadOptions {
+            max_depth: 4,
+            ..LoadOptions::default()
+        };
+        let report = AgentStore::scan(&agents_dir, &options);
+        assert_eq!(names(&report.store), ["a.b.c/deep", "default/keep"]);
+
+        // Reloading an ignored directory does not bring it back.
+        let store = report
+            .store
+            .reload_dirs([agents_dir.join("wip-agent").as_path()]);
+        assert_eq!(store.store.len(), 2);
+    }
+
+    fn lazy_options(capacity: usize) -> LoadOptions {
+        LoadOptions {
+            lazy_cache_size: NonZeroUsize::new(capacity),
+            ..LoadOptions::default()
+        }
+    }
+
+    fn write_prompted_agent(agents_dir: &Path, name: &str, prompt: &str) -> PathBuf {
+        let dir = agents_dir.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("agent.yaml"),
+            format!(
+                "apiVersion: agnx/v1
+kind: Agent
+metadata:
+  name: {name}
+  description: The {name} agent
+spec:
+  model: openrouter/some-model
+  system_prompt:
+    file: SYSTEM_PROMPT.md
+"
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("SYSTEM_PROMPT.md"), prompt).unwrap();
+        dir
+    }
+
+    fn system_prompt(agent: &AgentSpec) -> Option<&str> {
+        agent.system_prompt.as_ref()?.content.as_deref()
+    }
+
+    #[test]
+    fn lazy_store_indexes_then_loads_on_access() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        write_prompted_agent(&agents_dir, "one", "First");
+        write_prompted_agent(&agents_dir, "two", "Second");
+
+        let eager = AgentStore::scan(&agents_dir, &LoadOptions::default()).store;
+        let report = AgentStore::scan(&agents_dir, &lazy_options(1));
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        let store = report.store;
+        assert!(eager.cache_stats().is_none());
+
+        // Listing sees the same agents, but nothing has been read yet.
+        assert_eq!(store.len(), eager.len());
+        let (_, indexed) = store
+            .iter()
+            .find(|(_, a)| a.metadata.name == "one")
+            .unwrap();
+        assert_eq!(
+            indexed.metadata.description.as_deref(),
+            Some("The one agent")
+        );
+        assert_eq!(system_prompt(indexed), None);
+        // The index hashes the same files as a full load.
+        assert_eq!(
+            indexed.revision.hash,
+            eager.get("one").unwrap().revision.hash
+        );
+
+        assert_eq!(system_prompt(&store.get("one").unwrap()), Some("First"));
+        assert_eq!(system_prompt(&store.get("one").unwrap()), Some("First"));
+        assert_eq!(system_prompt(&store.get("two").unwrap()), Some("Second"));
+        assert!(store.get("three").is_none());
+        assert_eq!(
+            store.cache_stats(),
+            Some(CacheStats {
+                capacity: 1,
+                len: 1,
+                hits: 1,
+                misses: 2,
+                evictions: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn lazy_store_reload_invalidates_cached_agents() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        let dir = write_prompted_agent(&agents_dir, "one", "Before");
+
+        let registry = AgentRegistry::new(AgentStore::scan(&agents_dir, &lazy_options(10)).store);
+        assert_eq!(
+            system_prompt(&registry.snapshot().get("one").unwrap()),
+            Some("Before")
+        );
+
+        fs::write(dir.join("SYSTEM_PROMPT.md"), "After").unwrap();
+        assert!(registry.reload_dirs([dir.as_path()]).is_empty());
+        assert_eq!(
+            system_prompt(&registry.snapshot().get("one").unwrap()),
+            Some("After")
+        );
+        let stats = registry.snapshot().cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.len), (0, 2, 1));
+    }
+
+    #[test]
+    fn lazy_store_hides_agents_that_fail_to_load() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        let dir = write_prompted_agent(&agents_dir, "one", "Prompt");
+        write_prompted_agent(&agents_dir, "two", "Prompt");
+
+        let registry = AgentRegistry::new(AgentStore::scan(&agents_dir, &lazy_options(10)).store);
+        let manifest = fs::read_to_string(dir.join("agent.yaml")).unwrap();
+        fs::write(dir.join("agent.yaml"), "kind: [").unwrap();
+
+        // Listed until the full load fails, then gone from both.
+        let store = registry.snapshot();
+        assert_eq!(store.len(), 2);
+        assert!(store.get("one").is_none());
+        assert_eq!(store.len(), 1);
+        assert!(store.iter().all(|(_, a)| a.metadata.name == "two"));
+        assert!(store.namespaces_of("one").is_empty());
+
+        // Reloading the fixed agent brings it back.
+        fs::write(dir.join("agent.yaml"), manifest).unwrap();
+        assert!(registry.reload_dirs([dir.as_path()]).is_empty());
+        let store = registry.snapshot();
+        assert_eq!(store.len(), 2);
+        assert!(store.get("one").is_some());
+    }
 }
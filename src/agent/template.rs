@@ -0,0 +1,187 @@
+//! Rendering of `${...}` variables in agent instructions.
+//!
+//! Instructions are stored as written and rendered per request, so `${date}` and
+//! `${time}` are always current and `${user.*}` reflect the caller. `$${` renders as a
+//! literal `${`. Unknown variables are reported when the agent is loaded and left as
+//! written when rendering.
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use std::path::Path;
+
+/// Variables available to instructions templates.
+pub const TEMPLATE_VARIABLES: &[&str] = &[
+    "user.name",
+    "user.timezone",
+    "date",
+    "time",
+    "agnx.data_dir",
+];
+
+/// Values used to render a template for one request.
+#[derive(Debug, Clone)]
+pub struct RenderContext<'a> {
+    pub user_name: Option<&'a str>,
+    /// The user's timezone; `${date}` and `${time}` are rendered in it.
+    pub timezone: Tz,
+    pub now: DateTime<Utc>,
+    pub data_dir: &'a Path,
+}
+
+impl RenderContext<'_> {
+    fn value(&self, variable: &str) -> Option<String> {
+        let local = self.now.with_timezone(&self.timezone);
+        match variable {
+            "user.name" => Some(self.user_name.unwrap_or_default().to_string()),
+            "user.timezone" => Some(self.timezone.name().to_string()),
+            "date" => Some(local.format("%Y-%m-%d").to_string()),
+            "time" => Some(local.format("%H:%M").to_string()),
+            "agnx.data_dir" => Some(self.data_dir.display().to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// A `${...}` reference to a variable not in [`TEMPLATE_VARIABLES`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownVariable {
+    pub name: String,
+    /// 1-indexed position of the `$` within the template.
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Render `template`, substituting known variables from `ctx`.
+pub fn render(template: &str, ctx: &RenderContext) -> String {
+    let mut out = String::with_capacity(template.len());
+    for token in tokenize(template) {
+        match token {
+            Token::Text(text) => out.push_str(text),
+            Token::Variable { name, raw, .. } => match ctx.value(name) {
+                Some(value) => out.push_str(&value),
+                None => out.push_str(raw),
+            },
+        }
+    }
+    out
+}
+
+/// Variables referenced by `template` that rendering would not substitute.
+pub fn unknown_variables(template: &str) -> Vec<UnknownVariable> {
+    tokenize(template)
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Variable {
+                name, line, column, ..
+            } if !TEMPLATE_VARIABLES.contains(&name) => Some(UnknownVariable {
+                name: name.to_string(),
+                line,
+                column,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Variable {
+        name: &'a str,
+        /// The full `${...}` reference, emitted verbatim when the name is unknown.
+        raw: &'a str,
+        line: u32,
+        column: u32,
+    },
+}
+
+fn tokenize(template: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for (index, line) in template.split_inclusive('\n').enumerate() {
+        let mut rest = line;
+        while let Some(pos) = rest.find('$') {
+            let tail = &rest[pos..];
+            if let Some(after) = tail.strip_prefix("$${") {
+                tokens.push(Token::Text(&rest[..pos]));
+                tokens.push(Token::Text("${"));
+                rest = after;
+                continue;
+            }
+            let end = tail.strip_prefix("${").and_then(|body| body.find('}'));
+            let Some(end) = end else {
+                tokens.push(Token::Text(&rest[..pos + 1]));
+                rest = &tail[1..];
+                continue;
+            };
+
+            tokens.push(Token::Text(&rest[..pos]));
+            tokens.push(Token::Variable {
+                name: tail[2..end + 2].trim(),
+                raw: &tail[..end + 3],
+                line: index as u32 + 1,
+                column: (line.len() - tail.len()) as u32 + 1,
+            });
+            rest = &tail[end + 3..];
+        }
+        tokens.push(Token::Text(rest));
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn ctx() -> RenderContext<'static> {
+        RenderContext {
+            user_name: Some("Ana"),
+            timezone: chrono_tz::Asia::Jakarta,
+            now: Utc.with_ymd_and_hms(2026, 1, 11, 20, 30, 0).unwrap(),
+            data_dir: Path::new("/srv/.agnx"),
+        }
+    }
+
+    #[test]
+    fn renders_known_variables_in_user_timezone() {
+        let rendered = render(
+            "Hi ${user.name} (${user.timezone}). It is ${date} ${time}.\nData: ${agnx.data_dir}",
+            &ctx(),
+        );
+        assert_eq!(
+            rendered,
+            "Hi Ana (Asia/Jakarta). It is 2026-01-12 03:30.\nData: /srv/.agnx"
+        );
+    }
+
+    #[test]
+    fn escapes_and_unknown_variables_are_left_as_written() {
+        assert_eq!(
+            render("Use $${date} for ${date}; ${nope}, $5", &ctx()),
+            "Use ${date} for 2026-01-12; ${nope}, $5"
+        );
+        assert_eq!(render("${unterminated", &ctx()), "${unterminated");
+    }
+
+    #[test]
+    fn reports_unknown_variables_with_position() {
+        let unknown = unknown_variables("Hello ${user.name}\n  ${user.email} $${ignored}");
+        assert_eq!(
+            unknown,
+            vec![UnknownVariable {
+                name: "user.email".to_string(),
+                line: 2,
+                column: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn env_like_variables_are_reserved_from_interpolation() {
+        for name in TEMPLATE_VARIABLES.iter().filter(|name| !name.contains('.')) {
+            assert!(
+                crate::interpolate::RESERVED_NAMES.contains(name),
+                "${{{name}}} would be expanded as an environment variable"
+            );
+        }
+    }
+}
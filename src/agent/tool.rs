@@ -0,0 +1,175 @@
+//! `spec.tools`: MCP servers, CLI tools with a README, and tools built into Agnx.
+
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use super::diagnostic::{Diagnostic, DiagnosticCode};
+use super::source_map::SourceMap;
+use super::version::RawTool;
+
+/// Tools that Agnx provides itself, usable as `type: builtin`.
+pub const BUILTIN_TOOLS: &[&str] = &["calculator", "current_time"];
+
+/// A validated tool declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolSpec {
+    Mcp {
+        name: String,
+        /// Command or path that starts the MCP server.
+        server: String,
+        /// Passed to the server; may hold credentials, so it is never shown by the API.
+        config: HashMap<String, Value>,
+    },
+    Cli {
+        name: String,
+        /// Command line; a relative program path is resolved against the agent directory.
+        command: String,
+        /// README the agent reads before using the tool.
+        readme: String,
+    },
+    Builtin {
+        name: String,
+    },
+}
+
+impl ToolSpec {
+    pub fn name(&self) -> &str {
+        match self {
+            ToolSpec::Mcp { name, .. }
+            | ToolSpec::Cli { name, .. }
+            | ToolSpec::Builtin { name } => name,
+        }
+    }
+
+    /// The `type` the tool was declared with.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ToolSpec::Mcp { .. } => "mcp",
+            ToolSpec::Cli { .. } => "cli",
+            ToolSpec::Builtin { .. } => "builtin",
+        }
+    }
+}
+
+/// Check `spec.tools` and convert it into [`ToolSpec`]s, reporting every problem.
+///
+/// With `check_files`, the program and README of `cli` tools must exist relative to
+/// `agent_dir`. Programs without a `/` are looked up on `PATH` when run, so they are
+/// not checked.
+pub(super) fn resolve(
+    tools: &[RawTool],
+    agent_dir: &Path,
+    check_files: bool,
+    yaml_path: &Path,
+    source: &SourceMap,
+) -> Result<Vec<ToolSpec>, Vec<Diagnostic>> {
+    let mut errors = Vec::new();
+    let mut resolved: Vec<ToolSpec> = Vec::with_capacity(tools.len());
+    let mut seen = HashSet::new();
+    for (index, tool) in tools.iter().enumerate() {
+        let index = index.to_string();
+        // A missing field is reported at the entry it is missing from.
+        let span = |field: &str| {
+            source
+                .value_span(&["spec", "tools", &index, field])
+                .or_else(|| source.value_span(&["spec", "tools", &index]))
+        };
+        let name = tool.name.clone();
+        // Checked before the entry itself, so a duplicate of an invalid tool is reported.
+        if !seen.insert(tool.name.as_str()) {
+            errors.push(
+                Diagnostic::error(
+                    DiagnosticCode::DuplicateTool,
+                    yaml_path,
+                    format!("tool name '{name}' is declared more than once"),
+                )
+                .with_span(span("name")),
+            );
+        }
+        let mut error = |field: &str, message: String, hint: Option<String>| {
+            let diagnostic = Diagnostic::error(DiagnosticCode::InvalidTool, yaml_path, message)
+                .with_span(span(field));
+            errors.push(match hint {
+                Some(hint) => diagnostic.with_hint(hint),
+                None => diagnostic,
+            });
+        };
+        let mut required = |field: &str, value: &Option<String>| {
+            if value.is_none() {
+                error(
+                    field,
+                    format!("{} tool '{name}' needs `{field}`", tool.kind),
+                    None,
+                );
+            }
+            value.clone()
+        };
+
+        let spec = match tool.kind.as_str() {
+            "mcp" => {
+                let Some(server) = required("server", &tool.server) else {
+                    continue;
+                };
+                ToolSpec::Mcp {
+                    name: name.clone(),
+                    server,
+                    config: tool.config.clone(),
+                }
+            }
+            "cli" => {
+                let command = required("command", &tool.command);
+                let readme = required("readme", &tool.readme);
+                let (Some(command), Some(readme)) = (command, readme) else {
+                    continue;
+                };
+                if check_files {
+                    let program = command.split_whitespace().next().unwrap_or_default();
+                    for (field, path, checked) in [
+                        ("command", program, program.contains('/')),
+                        ("readme", readme.as_str(), true),
+                    ] {
+                        if checked && !agent_dir.join(path).exists() {
+                            error(
+                                field,
+                                format!("{field} '{path}' of cli tool '{name}' does not exist"),
+                                Some("paths are relative to the agent directory".to_string()),
+                            );
+                        }
+                    }
+                }
+                ToolSpec::Cli {
+                    name: name.clone(),
+                    command,
+                    readme,
+                }
+            }
+            "builtin" => {
+                if !BUILTIN_TOOLS.contains(&name.as_str()) {
+                    error(
+                        "name",
+                        format!("unknown builtin tool '{name}'"),
+                        Some(format!("builtin tools: {}", BUILTIN_TOOLS.join(", "))),
+                    );
+                    continue;
+                }
+                ToolSpec::Builtin { name: name.clone() }
+            }
+            other => {
+                error(
+                    "type",
+                    format!("tool '{name}' has unknown type '{other}'"),
+                    Some("expected mcp, cli or builtin".to_string()),
+                );
+                continue;
+            }
+        };
+        resolved.push(spec);
+    }
+
+    if errors.is_empty() {
+        Ok(resolved)
+    } else {
+        Err(errors)
+    }
+}
@@ -0,0 +1,377 @@
+//! Supported `apiVersion`s and their document layouts.
+//!
+//! The newest version is deserialized directly into [`RawAgentSpec`]; older versions
+//! have their own raw structs that convert into it, so the rest of the loader only
+//! deals with one layout.
+//!
+//! | apiVersion      | Layout                                                             |
+//! |-----------------|--------------------------------------------------------------------|
+//! | `agnx/v1alpha1` | Original layout.                                                   |
+//! | `agnx/v1beta1`  | v1alpha1 spec body; `max_tokens` and bare prompt paths deprecated. |
+//! | `agnx/v1`       | The deprecated forms are removed.                                  |
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::source_map::{SourceMap, SourceSpan};
+use super::spec::{AgentMetadata, ModelConfig};
+use super::{API_VERSION_V1, API_VERSION_V1ALPHA1, API_VERSION_V1BETA1, KIND_AGENT};
+
+/// A supported `apiVersion`, oldest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    V1Alpha1,
+    V1Beta1,
+    V1,
+}
+
+impl ApiVersion {
+    pub const LATEST: ApiVersion = ApiVersion::V1;
+    pub const ALL: [ApiVersion; 3] = [ApiVersion::V1Alpha1, ApiVersion::V1Beta1, ApiVersion::V1];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|v| v.as_str() == value)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiVersion::V1Alpha1 => API_VERSION_V1ALPHA1,
+            ApiVersion::V1Beta1 => API_VERSION_V1BETA1,
+            ApiVersion::V1 => API_VERSION_V1,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Version-independent agent document, in the layout of [`ApiVersion::LATEST`].
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "`agnx/v1` document.")]
+pub(super) struct RawAgentSpec {
+    #[schemars(extend("const" = API_VERSION_V1))]
+    pub api_version: String,
+    #[schemars(extend("const" = KIND_AGENT))]
+    pub kind: String,
+    pub metadata: AgentMetadata,
+    pub spec: RawAgentSpecBody,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(super) struct RawAgentSpecBody {
+    pub model: RawModel,
+    pub system_prompt: Option<RawPrompt>,
+    pub instructions: Option<RawPrompt>,
+    #[serde(default)]
+    pub bootstrap: Vec<RawBootstrapFile>,
+    /// Directory scanned for skills; `./skills/` by default.
+    pub skills_dir: Option<String>,
+    #[serde(default)]
+    pub skills: Vec<RawSkill>,
+    #[serde(default)]
+    pub tools: Vec<RawTool>,
+}
+
+/// An additional skill. Remote `url` skills are not supported yet, so `fields::check`
+/// reports them and only local `path`s are loaded.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(super) struct RawSkill {
+    pub path: Option<String>,
+}
+
+/// A tool declaration. `mcp` tools need `server`; `cli` tools need `command` and
+/// `readme`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(super) struct RawTool {
+    pub name: String,
+    #[serde(rename = "type")]
+    #[schemars(extend("enum" = ["mcp", "cli", "builtin"]))]
+    pub kind: String,
+    /// `mcp`: command that starts the server.
+    pub server: Option<String>,
+    /// `mcp`: settings passed to the server.
+    #[serde(default)]
+    pub config: HashMap<String, Value>,
+    /// `cli`: command line that runs the tool.
+    pub command: Option<String>,
+    /// `cli`: README describing how to use the tool.
+    pub readme: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(super) struct RawBootstrapFile {
+    pub path: String,
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// `spec.model` is an alias name, a `provider/name` shorthand, or a full mapping.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub(super) enum RawModel {
+    Reference(String),
+    Config(ModelConfig),
+}
+
+/// `{file: path}` or `{inline: text}`.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum RawPrompt {
+    File(String),
+    Inline(String),
+}
+
+/// `agnx/v1alpha1` document.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct V1Alpha1Spec {
+    #[schemars(extend("const" = API_VERSION_V1ALPHA1))]
+    api_version: String,
+    #[schemars(extend("const" = KIND_AGENT))]
+    kind: String,
+    metadata: AgentMetadata,
+    spec: V1Alpha1SpecBody,
+}
+
+/// `agnx/v1beta1` document. It only deprecates fields, so the spec body is still the
+/// v1alpha1 one.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct V1Beta1Spec {
+    #[schemars(extend("const" = API_VERSION_V1BETA1))]
+    api_version: String,
+    #[schemars(extend("const" = KIND_AGENT))]
+    kind: String,
+    metadata: AgentMetadata,
+    spec: V1Alpha1SpecBody,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct V1Alpha1SpecBody {
+    // `max_tokens` is accepted through the alias on `ModelConfig::max_output_tokens`.
+    model: RawModel,
+    system_prompt: Option<V1Alpha1Prompt>,
+    instructions: Option<V1Alpha1Prompt>,
+    #[serde(default)]
+    bootstrap: Vec<RawBootstrapFile>,
+    skills_dir: Option<String>,
+    #[serde(default)]
+    skills: Vec<RawSkill>,
+    #[serde(default)]
+    tools: Vec<RawTool>,
+}
+
+/// Prompt fields accept a bare path as well as the tagged forms.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum V1Alpha1Prompt {
+    Path(String),
+    Tagged(RawPrompt),
+}
+
+impl From<V1Alpha1Prompt> for RawPrompt {
+    fn from(prompt: V1Alpha1Prompt) -> Self {
+        match prompt {
+            V1Alpha1Prompt::Path(path) => RawPrompt::File(path),
+            V1Alpha1Prompt::Tagged(prompt) => prompt,
+        }
+    }
+}
+
+impl From<V1Alpha1SpecBody> for RawAgentSpecBody {
+    fn from(body: V1Alpha1SpecBody) -> Self {
+        RawAgentSpecBody {
+            model: body.model,
+            system_prompt: body.system_prompt.map(Into::into),
+            instructions: body.instructions.map(Into::into),
+            bootstrap: body.bootstrap,
+            skills_dir: body.skills_dir,
+            skills: body.skills,
+            tools: body.tools,
+        }
+    }
+}
+
+impl From<V1Alpha1Spec> for RawAgentSpec {
+    fn from(raw: V1Alpha1Spec) -> Self {
+        RawAgentSpec {
+            api_version: raw.api_version,
+            kind: raw.kind,
+            metadata: raw.metadata,
+            spec: raw.spec.into(),
+        }
+    }
+}
+
+impl From<V1Beta1Spec> for RawAgentSpec {
+    fn from(raw: V1Beta1Spec) -> Self {
+        RawAgentSpec {
+            api_version: raw.api_version,
+            kind: raw.kind,
+            metadata: raw.metadata,
+            spec: raw.spec.into(),
+        }
+    }
+}
+
+/// Read `apiVersion` from a document.
+pub(super) fn detect(doc: &Value) -> Result<ApiVersion, VersionError> {
+    match doc.get("apiVersion") {
+        None | Some(Value::Null) => Err(VersionError::Missing),
+        Some(Value::String(value)) => {
+            ApiVersion::parse(value).ok_or_else(|| VersionError::Unsupported(value.clone()))
+        }
+        Some(other) => Err(VersionError::Unsupported(other.to_string())),
+    }
+}
+
+#[derive(Debug)]
+pub(super) enum VersionError {
+    Missing,
+    Unsupported(String),
+}
+
+impl std::fmt::Display for VersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VersionError::Missing => write!(f, "missing apiVersion"),
+            VersionError::Unsupported(value) => write!(f, "unsupported apiVersion '{value}'"),
+        }
+    }
+}
+
+/// Comma-separated list of the supported versions, for hints.
+pub(super) fn supported_versions() -> String {
+    let versions: Vec<&str> = ApiVersion::ALL.iter().map(|v| v.as_str()).collect();
+    versions.join(", ")
+}
+
+/// A field written in a form that is deprecated in, or removed from, the document's version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct LegacyField {
+    /// Dotted path to the field, e.g. `spec.model.max_tokens`.
+    pub field: String,
+    /// How to write it instead.
+    pub replacement: &'static str,
+    /// The text edit that `agnx migrate` applies.
+    pub rewrite: Rewrite,
+    /// The version that deprecated the form.
+    pub deprecated_in: ApiVersion,
+    /// The version that removed it, if the document is already on it or later.
+    pub removed_in: Option<ApiVersion>,
+    pub span: Option<SourceSpan>,
+}
+
+/// How a legacy field is rewritten; `span` points at the key or the value respectively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Rewrite {
+    /// Rename the key, keeping the value.
+    RenameKey(&'static str),
+    /// Replace the value with a one-entry flow mapping, `{key: value}`.
+    WrapValue(&'static str),
+}
+
+/// Legacy forms used by `doc`, as judged by `version`. v1alpha1 documents report nothing.
+pub(super) fn legacy_fields(
+    version: ApiVersion,
+    doc: &Value,
+    source: &SourceMap,
+) -> Vec<LegacyField> {
+    if version < ApiVersion::V1Beta1 {
+        return Vec::new();
+    }
+    let removed_in = (version >= ApiVersion::V1).then_some(ApiVersion::V1);
+    let mut fields = Vec::new();
+
+    if doc.pointer("/spec/model/max_tokens").is_some() {
+        fields.push(LegacyField {
+            field: "spec.model.max_tokens".to_string(),
+            replacement: "max_output_tokens",
+            rewrite: Rewrite::RenameKey("max_output_tokens"),
+            deprecated_in: ApiVersion::V1Beta1,
+            removed_in,
+            span: source
+                .keys(&["spec", "model"])
+                .into_iter()
+                .find(|(key, _)| *key == "max_tokens")
+                .and_then(|(_, span)| span),
+        });
+    }
+    for field in ["system_prompt", "instructions"] {
+        if doc
+            .pointer(&format!("/spec/{field}"))
+            .is_some_and(Value::is_string)
+        {
+            fields.push(LegacyField {
+                field: format!("spec.{field}"),
+                replacement: "{file: <path>}",
+                rewrite: Rewrite::WrapValue("file"),
+                deprecated_in: ApiVersion::V1Beta1,
+                removed_in,
+                span: source.value_span(&["spec", field]),
+            });
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy(text: &str) -> Vec<(String, Option<ApiVersion>)> {
+        let doc: Value = serde_saphyr::from_str(text).unwrap();
+        let version = detect(&doc).unwrap();
+        legacy_fields(version, &doc, &SourceMap::parse(text))
+            .into_iter()
+            .map(|f| (f.field, f.removed_in))
+            .collect()
+    }
+
+    #[test]
+    fn detects_versions() {
+        let doc = |v: &str| serde_json::json!({ "apiVersion": v });
+        assert_eq!(detect(&doc("agnx/v1beta1")).unwrap(), ApiVersion::V1Beta1);
+        assert!(matches!(
+            detect(&doc("agnx/v2")),
+            Err(VersionError::Unsupported(v)) if v == "agnx/v2"
+        ));
+        assert!(matches!(
+            detect(&serde_json::json!({})),
+            Err(VersionError::Missing)
+        ));
+    }
+
+    #[test]
+    fn legacy_fields_depend_on_version() {
+        let body = "spec:
+  model:
+    max_tokens: 10
+  system_prompt: ./S.md
+  instructions:
+    inline: Hi
+";
+        assert!(legacy(&format!("apiVersion: agnx/v1alpha1\n{body}")).is_empty());
+        assert_eq!(
+            legacy(&format!("apiVersion: agnx/v1beta1\n{body}")),
+            vec![
+                ("spec.model.max_tokens".to_string(), None),
+                ("spec.system_prompt".to_string(), None),
+            ]
+        );
+        assert_eq!(
+            legacy(&format!("apiVersion: agnx/v1\n{body}")),
+            vec![
+                ("spec.model.max_tokens".to_string(), Some(ApiVersion::V1)),
+                ("spec.system_prompt".to_string(), Some(ApiVersion::V1)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use super::extends;
+use super::ignore::IGNORE_FILE;
+use super::store::{AgentRegistry, affected_agent_dirs, log_scan_warnings};
+use crate::llm::Providers;
+
+/// How long to wait for related file events to settle before reloading.
+///
+/// Editors often write a file in several steps (truncate, write, rename), so reloading on
+/// the first event would regularly observe a half-written `agent.yaml`.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Watch `agents_dir` and reload changed agents into `registry`.
+///
+/// Only agent directories that saw file events are re-loaded, off the async runtime,
+/// and only the reloaded agents are checked for missing Ollama models. The returned task owns the underlying
+/// watcher and runs until the process exits.
+pub fn watch_agents_dir(
+    registry: AgentRegistry,
+    providers: Providers,
+    agents_dir: &Path,
+) -> notify::Result<JoinHandle<()>> {
+    // Events carry absolute paths, so compare against the canonical directory but
+    // reload using the configured one to keep store keys consistent with `scan`.
+    let watch_root = agents_dir.canonicalize()?;
+    let agents_dir = agents_dir.to_path_buf();
+    let max_depth = registry.snapshot().max_depth();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<notify::Result<Event>>();
+    let mut watcher = RecommendedWatcher::new(
+        move |res| {
+            let _ = tx.send(res);
+        },
+        notify::Config::default(),
+    )?;
+    watcher.watch(&watch_root, RecursiveMode::Recursive)?;
+
+    Ok(tokio::spawn(async move {
+        let _watcher = watcher;
+
+        while let Some(first) = rx.recv().await {
+            let mut changed = BTreeSet::new();
+            collect_paths(&mut changed, first);
+
+            let settle = tokio::time::sleep(DEBOUNCE);
+            tokio::pin!(settle);
+            loop {
+                tokio::select! {
+                    _ = &mut settle => break,
+                    next = rx.recv() => match next {
+                        Some(res) => collect_paths(&mut changed, res),
+                        None => break,
+                    },
+                }
+            }
+
+            // A changed ignore file can affect any agent, so start over.
+            if changed.contains(&watch_root.join(IGNORE_FILE)) {
+                let agents = registry.clone();
+                let rescan = tokio::task::spawn_blocking(move || {
+                    let warnings = agents.rescan();
+                    (agents.snapshot(), warnings)
+                });
+                let (store, mut warnings) = match rescan.await {
+                    Ok(rescanned) => rescanned,
+                    Err(e) => {
+                        warn!(error = %e, "Agent rescan failed");
+                        continue;
+                    }
+                };
+                info!(agents = store.len(), "Rescanned agents");
+                warnings.extend(providers.missing_models(store.iter_dirs()).await);
+                log_scan_warnings(&warnings);
+                continue;
+            }
+
+            let dirs = affected_agent_dirs(&agents_dir, &watch_root, max_depth, &changed);
+            if dirs.is_empty() {
+                continue;
+            }
+
+            let agents = registry.clone();
+            let reload_dirs = dirs.clone();
+            let reload = tokio::task::spawn_blocking(move || {
+                let warnings = agents.reload_dirs(reload_dirs.iter().map(PathBuf::as_path));
+                (agents.snapshot(), warnings)
+            });
+            let (store, mut warnings) = match reload.await {
+                Ok(reloaded) => reloaded,
+                Err(e) => {
+                    warn!(error = %e, "Agent reload failed");
+                    continue;
+                }
+            };
+            info!(
+                agents = store.len(),
+                changed_dirs = dirs.len(),
+                "Reloaded agents"
+            );
+            // Agents that extend a changed agent were reloaded with it.
+            let changed: Vec<PathBuf> = dirs.iter().map(|dir| extends::canonical(dir)).collect();
+            let reloaded = store.iter_dirs().filter(|(dir, agent)| {
+                dirs.iter().any(|d| d == dir)
+                    || agent.parents.iter().any(|p| changed.contains(&p.dir))
+            });
+            warnings.extend(providers.missing_models(reloaded).await);
+            log_scan_warnings(&warnings);
+        }
+    }))
+}
+
+fn collect_paths(changed: &mut BTreeSet<PathBuf>, res: notify::Result<Event>) {
+    match res {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+            changed.extend(event.paths);
+        }
+        Ok(_) => {}
+        Err(e) => warn!(error = %e, "Agent watcher error"),
+    }
+}
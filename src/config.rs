@@ -1,36 +1,169 @@
+use schemars::JsonSchema;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
 use std::io::ErrorKind;
+use std::num::NonZeroUsize;
 use std::path::Path;
 use std::path::PathBuf;
 
-#[derive(Debug, Deserialize)]
+use crate::agent::{DEFAULT_MAX_DEPTH, Diagnostic, LoadOptions, ModelConfig, check_model_aliases};
+use crate::interpolate::{InterpolationError, interpolate_env};
+use crate::llm::MaxTokensField;
+
+/// `agnx.yaml`.
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct Config {
     #[serde(default)]
     pub server: ServerConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
     #[serde(default = "default_agents_dir")]
     pub agents_dir: PathBuf,
+    /// Where agents deployed through the admin API are stored.
+    #[serde(default = "default_dynamic_agents_dir")]
+    pub dynamic_agents_dir: PathBuf,
+    /// Runtime data root, exposed to instructions as `${agnx.data_dir}`.
+    #[serde(default = "default_data_dir")]
+    pub data_dir: PathBuf,
+    /// Named model aliases that agents can use as `spec.model: <alias>`.
+    #[serde(default)]
+    pub models: HashMap<String, ModelConfig>,
+    /// Reject agents with unknown fields instead of warning about them.
+    #[serde(default)]
+    pub strict: bool,
+    /// How many directory levels below `agents_dir` are searched for agents.
+    #[serde(default = "default_agents_max_depth")]
+    pub agents_max_depth: usize,
+    /// Index agents at startup and load each one on first access.
+    #[serde(default)]
+    pub lazy_load: bool,
+    /// With `lazy_load`, how many loaded agents are kept in memory (least recently used
+    /// are evicted first).
+    #[serde(default = "default_cache_size")]
+    pub cache_size: NonZeroUsize,
+    /// Credentials and connection settings for model providers.
+    #[serde(default)]
+    pub providers: ProvidersConfig,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             server: ServerConfig::default(),
+            admin: AdminConfig::default(),
             agents_dir: default_agents_dir(),
+            dynamic_agents_dir: default_dynamic_agents_dir(),
+            data_dir: default_data_dir(),
+            models: HashMap::new(),
+            strict: false,
+            agents_max_depth: default_agents_max_depth(),
+            lazy_load: false,
+            cache_size: default_cache_size(),
+            providers: ProvidersConfig::default(),
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ServerConfig {
     #[serde(default = "default_host")]
     pub host: String,
     #[serde(default = "default_port")]
     pub port: u16,
+    /// Seconds a request may take. Chat requests are bounded by the provider timeouts
+    /// instead.
     #[serde(default = "default_timeout")]
     pub request_timeout: u64,
 }
 
+/// `admin:` in `agnx.yaml`.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct AdminConfig {
+    /// Bearer token required by `/api/v1/admin/*`. The admin API is disabled without one.
+    pub token: Option<String>,
+}
+
+/// `providers:` in `agnx.yaml`. All providers share one connection pool.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProvidersConfig {
+    /// Seconds to wait for a connection to a provider.
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: u64,
+    /// Seconds a non-streaming completion may take.
+    #[serde(default = "default_completion_timeout")]
+    pub timeout: u64,
+    /// Seconds a streaming completion may go without sending anything.
+    #[serde(default = "default_read_timeout")]
+    pub read_timeout: u64,
+    #[serde(default)]
+    pub anthropic: ProviderEndpoint,
+    #[serde(default)]
+    pub ollama: ProviderEndpoint,
+    #[serde(default)]
+    pub openai: ProviderEndpoint,
+    #[serde(default)]
+    pub openrouter: ProviderEndpoint,
+    /// Custom providers, by the name agents use in `spec.model.provider`. They are
+    /// served through the OpenAI-compatible API at `base_url`.
+    #[serde(default)]
+    pub custom: HashMap<String, ProviderEndpoint>,
+}
+
+/// Where a provider is reached and how to authenticate.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct ProviderEndpoint {
+    /// API key; the provider's usual environment variable (e.g. `OPENROUTER_API_KEY`) is
+    /// used when unset.
+    pub api_key: Option<String>,
+    /// API root, overridden per agent by `spec.model.base_url`.
+    pub base_url: Option<String>,
+    /// Also send `api_key` and `headers` to an agent's own `spec.model.base_url`. By
+    /// default they only go to `base_url` or the provider's default API root.
+    #[serde(default)]
+    pub allow_base_url_override: bool,
+    /// Extra HTTP headers sent with every request, e.g. for a gateway in front of the
+    /// provider.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// OpenAI-compatible providers: the request field that carries `max_output_tokens`.
+    pub max_tokens_field: Option<MaxTokensField>,
+    /// OpenAI-compatible providers: whether streams ask for token usage with
+    /// `stream_options`. Turn off for servers that reject the field.
+    pub stream_usage: Option<bool>,
+    /// Ollama: how long a model stays loaded after a request, e.g. `10m` (a negative
+    /// duration keeps it loaded). Agents can override it with `spec.model.extra`.
+    pub keep_alive: Option<String>,
+}
+
+impl Default for ProvidersConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: default_connect_timeout(),
+            timeout: default_completion_timeout(),
+            read_timeout: default_read_timeout(),
+            anthropic: ProviderEndpoint::default(),
+            ollama: ProviderEndpoint::default(),
+            openai: ProviderEndpoint::default(),
+            openrouter: ProviderEndpoint::default(),
+            custom: HashMap::new(),
+        }
+    }
+}
+
+fn default_connect_timeout() -> u64 {
+    10
+}
+
+fn default_completion_timeout() -> u64 {
+    300
+}
+
+fn default_read_timeout() -> u64 {
+    60
+}
+
 fn default_host() -> String {
     "0.0.0.0".to_string()
 }
@@ -47,6 +180,33 @@ fn default_agents_dir() -> PathBuf {
     PathBuf::from(".agnx/agents")
 }
 
+fn default_dynamic_agents_dir() -> PathBuf {
+    PathBuf::from(".agnx/sys/dyn_agents")
+}
+
+fn default_agents_max_depth() -> usize {
+    DEFAULT_MAX_DEPTH
+}
+
+fn default_cache_size() -> NonZeroUsize {
+    NonZeroUsize::new(1000).expect("non-zero")
+}
+
+fn default_data_dir() -> PathBuf {
+    PathBuf::from(".agnx")
+}
+
+/// Resolve a path from the config file. Relative paths are relative to the config
+/// file's directory.
+pub fn resolve_relative(config_path: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    let config_dir = config_path.parent().unwrap_or_else(|| Path::new("."));
+    config_dir.join(path)
+}
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
@@ -65,21 +225,70 @@ impl Config {
             Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
             Err(e) => return Err(ConfigError::Io(e)),
         };
-        serde_saphyr::from_str(&contents).map_err(ConfigError::Yaml)
+        let expanded = interpolate_env(&contents).map_err(|error| ConfigError::Env {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let config = serde_saphyr::from_str(&expanded).map_err(ConfigError::Yaml)?;
+
+        // Model aliases are applied to every agent that uses them, so a typo is an error.
+        let unknown = check_model_aliases(path, &contents);
+        if !unknown.is_empty() {
+            return Err(ConfigError::UnknownFields(unknown));
+        }
+        Ok(config)
+    }
+
+    /// JSON Schema describing `agnx.yaml`.
+    pub fn schema() -> serde_json::Value {
+        let mut schema = schemars::schema_for!(Config).to_value();
+        schema["title"] = serde_json::json!("Agnx configuration (agnx.yaml)");
+        schema
+    }
+
+    /// Options for loading agent definitions under this config.
+    pub fn agent_load_options(&self) -> LoadOptions {
+        LoadOptions {
+            models: self.models.clone(),
+            strict: self.strict,
+            max_depth: self.agents_max_depth,
+            lazy_cache_size: self.lazy_load.then_some(self.cache_size),
+            sandbox_root: None,
+        }
     }
 }
 
 #[derive(Debug)]
 pub enum ConfigError {
     Io(std::io::Error),
+    Env {
+        path: PathBuf,
+        error: InterpolationError,
+    },
     Yaml(serde_saphyr::Error),
+    /// Keys under `models:` that a model does not have.
+    UnknownFields(Vec<Diagnostic>),
 }
 
 impl std::fmt::Display for ConfigError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ConfigError::Io(e) => write!(f, "failed to read config file: {e}"),
+            ConfigError::Env { path, error } => {
+                write!(
+                    f,
+                    "failed to expand config file {}: {error}",
+                    path.display()
+                )
+            }
             ConfigError::Yaml(e) => write!(f, "failed to parse config file: {e}"),
+            ConfigError::UnknownFields(diagnostics) => {
+                write!(f, "invalid config file:")?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n  {diagnostic}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -88,7 +297,9 @@ impl std::error::Error for ConfigError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             ConfigError::Io(e) => Some(e),
+            ConfigError::Env { error, .. } => Some(error),
             ConfigError::Yaml(e) => Some(e),
+            ConfigError::UnknownFields(_) => None,
         }
     }
 }
@@ -106,6 +317,7 @@ mod tests {
         assert_eq!(config.server.port, 8080);
         assert_eq!(config.server.request_timeout, 30);
         assert_eq!(config.agents_dir, PathBuf::from(".agnx/agents"));
+        assert_eq!(config.data_dir, PathBuf::from(".agnx"));
     }
 
     #[test]
@@ -175,4 +387,103 @@ server:
         ));
         assert!(io_error.to_string().contains("failed to read config file"));
     }
+
+    #[test]
+    fn test_load_expands_env_defaults() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"
+server:
+  host: "${{AGNX_TEST_UNSET_HOST:-127.0.0.1}}"
+agents_dir: ${{AGNX_TEST_UNSET_AGENTS_DIR:-./agents}}
+"#
+        )
+        .unwrap();
+
+        let config = Config::load(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.agents_dir, PathBuf::from("./agents"));
+    }
+
+    #[test]
+    fn test_load_missing_env_var_names_variable_and_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "agents_dir: ${{AGNX_TEST_UNSET_AGENTS_DIR}}").unwrap();
+
+        let err = Config::load(file.path().to_str().unwrap()).unwrap_err();
+        let message = err.to_string();
+        assert!(matches!(err, ConfigError::Env { .. }));
+        assert!(message.contains("AGNX_TEST_UNSET_AGENTS_DIR"));
+        assert!(message.contains(&file.path().display().to_string()));
+    }
+
+    #[test]
+    fn test_load_model_aliases() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"
+models:
+  fast:
+    provider: openrouter
+    name: anthropic/claude-haiku-4
+    temperature: 0.2
+    max_output_tokens: 1024
+"#
+        )
+        .unwrap();
+
+        let config = Config::load(file.path().to_str().unwrap()).unwrap();
+        let fast = &config.agent_load_options().models["fast"];
+        assert_eq!(fast.name, "anthropic/claude-haiku-4");
+        assert_eq!(fast.temperature, Some(0.2));
+        assert_eq!(fast.max_output_tokens, Some(1024));
+    }
+
+    #[test]
+    fn test_model_alias_typos_are_errors() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "models:\n  fast:\n    provider: openrouter\n    name: some/model\n    temprature: 0.2\n    extra:\n      anything: 1"
+        )
+        .unwrap();
+
+        let err = Config::load(file.path().to_str().unwrap()).unwrap_err();
+        let ConfigError::UnknownFields(diagnostics) = &err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "unknown field 'models.fast.temprature'"
+        );
+        assert_eq!(
+            diagnostics[0].span.map(|s| (s.line, s.column)),
+            Some((5, 5))
+        );
+        assert!(err.to_string().contains("did you mean 'temperature'?"));
+    }
+
+    #[test]
+    fn test_custom_providers_and_provider_typos() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "providers:\n  custom:\n    vllm:\n      base_url: http://vllm.internal:8000/v1"
+        )
+        .unwrap();
+        let config = Config::load(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(
+            config.providers.custom["vllm"].base_url.as_deref(),
+            Some("http://vllm.internal:8000/v1")
+        );
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "providers:\n  openrouterr:\n    api_key: sk-test").unwrap();
+        let err = Config::load(file.path().to_str().unwrap()).unwrap_err();
+        assert!(matches!(err, ConfigError::Yaml(_)), "{err}");
+        assert!(err.to_string().contains("openrouterr"), "{err}");
+    }
 }
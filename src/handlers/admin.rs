@@ -0,0 +1,365 @@
+use crate::agent::{AgentRegistry, DeployError, DeployStatus, Diagnostic, log_scan_warnings};
+use crate::response::{self, ProblemDetails};
+use crate::server::AppState;
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+
+/// Query parameters for removing an agent.
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteQuery {
+    /// Namespace of the agent; by default the name is resolved like `GET /agents/{name}`.
+    namespace: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DeployResponse {
+    /// `created`, `updated` or `unchanged`.
+    status: &'static str,
+    name: String,
+    namespace: String,
+    /// Revision of the agent as now loaded, also sent as the `ETag` header.
+    revision: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<Diagnostic>,
+}
+
+#[derive(Serialize)]
+pub struct ReloadResponse {
+    agents: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Serialize)]
+pub struct AgentCacheResponse {
+    /// Whether agents are loaded lazily; the counters are only present if so.
+    lazy_load: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capacity: Option<usize>,
+    /// Agents currently held in memory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cached: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hits: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    misses: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    evictions: Option<u64>,
+}
+
+/// Create or update an agent from an `agent.yaml` document sent as YAML or JSON.
+/// `If-Match` makes the update conditional on the agent's current revision. The
+/// warnings include a missing Ollama model.
+pub async fn deploy_agent(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, ProblemDetails> {
+    let text = match content_type(&headers) {
+        None | Some("application/yaml" | "application/x-yaml" | "text/yaml") => body,
+        Some("application/json") => {
+            let doc: serde_json::Value = serde_json::from_str(&body)
+                .map_err(|e| response::bad_request(format!("Invalid JSON: {e}")))?;
+            serde_saphyr::to_string(&doc).map_err(|e| response::internal_error(e.to_string()))?
+        }
+        Some(other) => {
+            return Err(response::unsupported_media_type(format!(
+                "Expected application/yaml or application/json, got '{other}'"
+            )));
+        }
+    };
+
+    let if_match = if_match(&headers);
+    let agents = state.agents.clone();
+    let mut deployment =
+        tokio::task::spawn_blocking(move || agents.deploy(&text, if_match.as_deref()))
+            .await
+            .map_err(|e| response::internal_error(e.to_string()))?
+            .map_err(deploy_problem)?;
+
+    let store = state.agents.snapshot();
+    let deployed = store.iter_dirs().filter(|(_, agent)| {
+        agent.namespace == deployment.namespace && agent.metadata.name == deployment.name
+    });
+    let missing = state.providers.missing_models(deployed).await;
+    log_scan_warnings(&missing);
+    deployment
+        .warnings
+        .extend(missing.iter().flat_map(|w| w.diagnostics()));
+
+    let status = match deployment.status {
+        DeployStatus::Created => StatusCode::CREATED,
+        DeployStatus::Updated | DeployStatus::Unchanged => StatusCode::OK,
+    };
+    let etag = deployment.revision.etag();
+    let body = DeployResponse {
+        status: deployment.status.as_str(),
+        name: deployment.name,
+        namespace: deployment.namespace,
+        revision: deployment.revision.hash,
+        warnings: deployment.warnings,
+    };
+    Ok((status, [(header::ETAG, etag)], Json(body)).into_response())
+}
+
+/// Remove an agent that was deployed through the admin API, optionally only at the
+/// revision given in `If-Match`.
+pub async fn delete_agent(
+    State(agents): State<AgentRegistry>,
+    Path(name): Path<String>,
+    Query(query): Query<DeleteQuery>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ProblemDetails> {
+    let if_match = if_match(&headers);
+    tokio::task::spawn_blocking(move || {
+        agents.undeploy(query.namespace.as_deref(), &name, if_match.as_deref())
+    })
+    .await
+    .map_err(|e| response::internal_error(e.to_string()))?
+    .map_err(deploy_problem)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Re-scan the agents directories from disk, then check every agent's Ollama model.
+pub async fn reload_agents(
+    State(state): State<AppState>,
+) -> Result<Json<ReloadResponse>, ProblemDetails> {
+    let agents = state.agents.clone();
+    let (store, mut warnings) = tokio::task::spawn_blocking(move || {
+        let warnings = agents.rescan();
+        (agents.snapshot(), warnings)
+    })
+    .await
+    .map_err(|e| response::internal_error(e.to_string()))?;
+    let count = store.len();
+    warnings.extend(state.providers.missing_models(store.iter_dirs()).await);
+    log_scan_warnings(&warnings);
+
+    Ok(Json(ReloadResponse {
+        agents: count,
+        diagnostics: warnings.iter().flat_map(|w| w.diagnostics()).collect(),
+    }))
+}
+
+pub async fn agent_cache(State(agents): State<AgentRegistry>) -> Json<AgentCacheResponse> {
+    let stats = agents.snapshot().cache_stats();
+    Json(AgentCacheResponse {
+        lazy_load: stats.is_some(),
+        capacity: stats.map(|s| s.capacity),
+        cached: stats.map(|s| s.len),
+        hits: stats.map(|s| s.hits),
+        misses: stats.map(|s| s.misses),
+        evictions: stats.map(|s| s.evictions),
+    })
+}
+
+/// The media type of the request, without parameters such as `charset`.
+fn content_type(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    Some(value.split(';').next().unwrap_or_default().trim())
+}
+
+fn if_match(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::IF_MATCH)?.to_str().ok()?;
+    Some(value.to_string())
+}
+
+fn deploy_problem(error: DeployError) -> ProblemDetails {
+    let detail = error.to_string();
+    match error {
+        DeployError::Invalid(diagnostics) => response::invalid_agent(detail, diagnostics),
+        DeployError::Static { .. } => response::conflict(detail),
+        DeployError::NotFound { .. } => response::not_found(detail),
+        DeployError::PreconditionFailed { .. } => response::precondition_failed(detail),
+        DeployError::Disabled | DeployError::Io(_) => response::internal_error(detail),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::agent::{AgentRegistry, AgentStore, LoadOptions};
+    use crate::config::ProvidersConfig;
+    use crate::llm::Providers;
+    use crate::llm::mock::{MockResponse, MockServer};
+    use crate::server::{AppState, build_app};
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode, header};
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    async fn send(app: &axum::Router, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    const AUTHORIZATION: &str = "Bearer secret";
+
+    fn app(tmp: &TempDir, admin_token: Option<&str>) -> axum::Router {
+        let store = AgentStore::scan_all(
+            &tmp.path().join("agents"),
+            Some(&tmp.path().join("dynamic")),
+            &LoadOptions::default(),
+        )
+        .store;
+        build_app(
+            AppState {
+                agents: AgentRegistry::new(store),
+                data_dir: Arc::new(tmp.path().to_path_buf()),
+                providers: Providers::new(ProvidersConfig::default()).unwrap(),
+                admin_token: admin_token.map(Arc::from),
+            },
+            30,
+        )
+    }
+
+    fn post(body: &str, content_type: &str) -> Request<Body> {
+        Request::post("/api/v1/admin/agents")
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::AUTHORIZATION, AUTHORIZATION)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn admin_routes_require_the_admin_token() {
+        let tmp = TempDir::new().unwrap();
+        let reload = |authorization: Option<&str>| {
+            let mut request = Request::post("/api/v1/admin/reload");
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let app = app(&tmp, Some("secret"));
+        let response = app.clone().oneshot(reload(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        let (status, body) = send(&app, reload(Some("Bearer wrong"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["type"], "urn:agnx:problem:forbidden");
+        let (status, _) = send(&app, reload(Some("secret"))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, reload(Some(AUTHORIZATION))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Without a configured token the admin API does not exist.
+        let (status, _) = send(&self::app(&tmp, None), reload(Some(AUTHORIZATION))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn deploy_get_and_delete_over_http() {
+        let tmp = TempDir::new().unwrap();
+        let app = app(&tmp, Some("secret"));
+
+        let json = r#"{"apiVersion": "agnx/v1", "kind": "Agent",
+            "metadata": {"name": "bot"}, "spec": {"model": "openrouter/m"}}"#;
+        let (status, body) = send(&app, post(json, "application/json")).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["status"], "created");
+        let (status, _) = send(&app, post(json, "application/json; charset=utf-8")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let get = |if_none_match: &str| {
+            Request::get("/api/v1/agents/bot")
+                .header(header::IF_NONE_MATCH, if_none_match)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(get("\"stale\"")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(etag, format!("\"{}\"", body["revision"].as_str().unwrap()));
+        let response = app.clone().oneshot(get(&etag)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let update = |if_match: &str| {
+            let mut request = post(
+                &json.replace("openrouter/m", "openrouter/n"),
+                "application/json",
+            );
+            request
+                .headers_mut()
+                .insert(header::IF_MATCH, if_match.parse().unwrap());
+            request
+        };
+        let (status, body) = send(&app, update("\"stale\"")).await;
+        assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+        assert_eq!(body["type"], "urn:agnx:problem:precondition-failed");
+        let (status, body) = send(&app, update(&etag)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "updated");
+
+        let (status, body) = send(
+            &app,
+            post(
+                "apiVersion: agnx/v1\nkind: Agent\nmetadata:\n  name: bot\nspec: {}\n",
+                "application/yaml",
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["type"], "urn:agnx:problem:invalid-agent");
+        assert_eq!(body["diagnostics"][0]["code"], "yaml-parse");
+
+        let (status, _) = send(&app, post(json, "text/plain")).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let delete = Request::delete("/api/v1/admin/agents/bot")
+            .header(header::AUTHORIZATION, AUTHORIZATION)
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(send(&app, delete).await.0, StatusCode::NO_CONTENT);
+        let reload = Request::post("/api/v1/admin/reload")
+            .header(header::AUTHORIZATION, AUTHORIZATION)
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(&app, reload).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["agents"], 0);
+    }
+
+    #[tokio::test]
+    async fn deploy_and_reload_report_missing_models() {
+        let server = MockServer::start(MockResponse::json(
+            200,
+            serde_json::json!({ "models": [{ "name": "llama3.2:latest" }] }),
+        ))
+        .await;
+        let tmp = TempDir::new().unwrap();
+        let app = app(&tmp, Some("secret"));
+
+        let yaml = format!(
+            "apiVersion: agnx/v1\nkind: Agent\nmetadata:\n  name: local\nspec:\n  model:\n    provider: ollama\n    name: qwen3:8b\n    base_url: {}\n",
+            server.url()
+        );
+        let (status, body) = send(&app, post(&yaml, "application/yaml")).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["warnings"][0]["code"], "missing-model");
+
+        let reload = Request::post("/api/v1/admin/reload")
+            .header(header::AUTHORIZATION, AUTHORIZATION)
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(&app, reload).await;
+        assert_eq!(status, StatusCode::OK);
+        let codes: Vec<&str> = body["diagnostics"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|d| d["code"].as_str())
+            .collect();
+        assert!(codes.contains(&"missing-model"), "{codes:?}");
+    }
+}
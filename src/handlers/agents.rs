@@ -1,24 +1,176 @@
-use crate::agent::AgentStore;
-use crate::response;
+use crate::agent::{
+    AgentRegistry, AgentSpec, AgentStore, LabelSelector, Prompt, PromptSource, RenderContext,
+};
+use crate::response::{self, ProblemDetails};
+use crate::server::AppState;
 use axum::Json;
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
 use axum::response::{IntoResponse, Response};
-use serde::Serialize;
+use chrono::{SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Query parameters for the agent list endpoint.
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    /// Only list agents in this namespace.
+    namespace: Option<String>,
+    /// Kubernetes-style selector over `metadata.labels`, e.g. `tier=premium,env in (prod)`.
+    #[serde(rename = "labelSelector")]
+    label_selector: Option<String>,
+    /// `name`, `namespace`, `version`, `provider` or `model`, prefixed with `-` for
+    /// descending order. Ties are broken by namespace and name.
+    sort: Option<String>,
+    /// Page size; without it every matching agent is returned.
+    limit: Option<usize>,
+    /// `next_cursor` of the previous page.
+    cursor: Option<String>,
+    /// Comma-separated summary fields to return besides `name` and `namespace`.
+    fields: Option<String>,
+}
+
+/// Query parameters for the agent spec endpoint.
+#[derive(Debug, Default, Deserialize)]
+pub struct SpecQuery {
+    /// Return `agent.yaml` together with the files it references, as JSON.
+    #[serde(default)]
+    bundle: bool,
+}
+
+/// Query parameters for the agent detail endpoint.
+#[derive(Debug, Default, Deserialize)]
+pub struct AgentQuery {
+    /// Render instructions template variables for the given user.
+    #[serde(default)]
+    render: bool,
+    user_name: Option<String>,
+    /// IANA timezone name (e.g. `Asia/Jakarta`); defaults to UTC.
+    user_timezone: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct AgentsResponse {
     agents: Vec<AgentSummary>,
+    /// Pass as `cursor` to get the next page; absent on the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct AgentSummary {
     name: String,
+    namespace: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    labels: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provider: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+}
+
+/// Fields `fields=` can select; `name` and `namespace` are always returned.
+const SUMMARY_FIELDS: &[&str] = &["description", "version", "labels", "provider", "model"];
+const DEFAULT_SUMMARY_FIELDS: &[&str] = &["description", "version"];
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// What the agent list can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortField {
+    Name,
+    Namespace,
+    Version,
+    Provider,
+    Model,
+}
+
+impl SortField {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "name" => Some(SortField::Name),
+            "namespace" => Some(SortField::Namespace),
+            "version" => Some(SortField::Version),
+            "provider" => Some(SortField::Provider),
+            "model" => Some(SortField::Model),
+            _ => None,
+        }
+    }
+
+    fn key(self, agent: &AgentSpec) -> String {
+        match self {
+            SortField::Name => agent.metadata.name.clone(),
+            SortField::Namespace => agent.namespace.clone(),
+            SortField::Version => agent.metadata.version.clone().unwrap_or_default(),
+            SortField::Provider => agent.model.provider.to_string(),
+            SortField::Model => agent.model.name.clone(),
+        }
+    }
+}
+
+/// Position after the last agent of a page: its sort key, namespace and name, tied to
+/// the `sort` it was produced with.
+#[derive(Debug, PartialEq, Eq)]
+struct Cursor {
+    sort: String,
+    position: (String, String, String),
+}
+
+impl Cursor {
+    const SEPARATOR: char = '\u{1f}';
+
+    /// Hex-encoded, so it is opaque to clients and safe in a query string.
+    fn encode(&self) -> String {
+        let (key, namespace, name) = &self.position;
+        let sep = Self::SEPARATOR;
+        format!("{}{sep}{key}{sep}{namespace}{sep}{name}", self.sort)
+            .bytes()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn decode(cursor: &str) -> Option<Self> {
+        if !cursor.len().is_multiple_of(2) {
+            return None;
+        }
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(cursor.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        let text = String::from_utf8(bytes).ok()?;
+        let mut parts = text.split(Self::SEPARATOR).map(str::to_string);
+        let (Some(sort), Some(key), Some(namespace), Some(name), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return None;
+        };
+        Some(Self {
+            sort,
+            position: (key, namespace, name),
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct SpecBundleResponse {
+    name: String,
+    namespace: String,
+    /// `agent.yaml` first, then referenced files with paths as written in it.
+    files: Vec<SourceFileResponse>,
+}
+
+#[derive(Serialize)]
+pub struct SourceFileResponse {
+    path: String,
+    content: String,
 }
 
 #[derive(Serialize)]
@@ -27,11 +179,32 @@ pub struct AgentDetailResponse {
     kind: String,
     metadata: MetadataResponse,
     spec: SpecResponse,
+    /// Parent chain from `extends:`, nearest first. `spec` is already merged. Paths
+    /// here and in `spec.skills` are relative to the agents directory.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extends: Vec<ParentResponse>,
+    revision: RevisionResponse,
+}
+
+#[derive(Serialize)]
+pub struct RevisionResponse {
+    /// SHA-256 over `agent.yaml`, its parents and the files it references.
+    hash: String,
+    /// RFC 3339 timestamp.
+    loaded_at: String,
+}
+
+#[derive(Serialize)]
+pub struct ParentResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    path: String,
 }
 
 #[derive(Serialize)]
 pub struct MetadataResponse {
     name: String,
+    namespace: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -44,9 +217,89 @@ pub struct MetadataResponse {
 pub struct SpecResponse {
     model: ModelResponse,
     #[serde(skip_serializing_if = "Option::is_none")]
-    system_prompt: Option<String>,
+    system_prompt: Option<PromptResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<PromptResponse>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    bootstrap: Vec<BootstrapResponse>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    skills: Vec<SkillResponse>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ToolResponse>,
+}
+
+/// A tool's name and type. MCP `config` may hold credentials, so it is not exposed.
+#[derive(Serialize)]
+pub struct ToolResponse {
+    name: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct SkillResponse {
+    id: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    allowed_tools: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    triggers: Vec<SkillTriggerResponse>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    references: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    scripts: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    assets: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct SkillTriggerResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pattern: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BootstrapResponse {
+    path: String,
+    required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    /// Whether the file was read; optional files may be missing.
+    loaded: bool,
+    /// Size on disk in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+pub struct PromptResponse {
+    /// `inline` or `file`, matching how the prompt was declared.
+    source: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    instructions: Option<String>,
+    content: Option<String>,
+}
+
+impl From<&Prompt> for PromptResponse {
+    fn from(prompt: &Prompt) -> Self {
+        let (source, path) = match &prompt.source {
+            PromptSource::Inline => ("inline", None),
+            PromptSource::File(path) => ("file", Some(path.clone())),
+        };
+        Self {
+            source,
+            path,
+            content: prompt.content.clone(),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -61,31 +314,313 @@ pub struct ModelResponse {
     max_output_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<&'static str>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    extra: HashMap<String, serde_json::Value>,
 }
 
-pub async fn list_agents(State(store): State<AgentStore>) -> Json<AgentsResponse> {
-    let agents: Vec<AgentSummary> = store
+/// List agents, filtered by namespace and labels, sorted and optionally paginated.
+pub async fn list_agents(
+    State(agents): State<AgentRegistry>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<AgentsResponse>, ProblemDetails> {
+    let selector = LabelSelector::parse(query.label_selector.as_deref().unwrap_or_default())
+        .map_err(|e| response::bad_request(e.to_string()))?;
+    let sort = query.sort.as_deref().unwrap_or("namespace");
+    let (field, descending) = match sort.strip_prefix('-') {
+        Some(field) => (field, true),
+        None => (sort, false),
+    };
+    let field = SortField::parse(field).ok_or_else(|| {
+        response::bad_request(format!(
+            "Unknown sort field '{field}'; expected name, namespace, version, provider or model"
+        ))
+    })?;
+    let fields = match &query.fields {
+        None => DEFAULT_SUMMARY_FIELDS.to_vec(),
+        Some(fields) => fields
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty() && *f != "name" && *f != "namespace")
+            .map(|f| {
+                SUMMARY_FIELDS
+                    .iter()
+                    .copied()
+                    .find(|known| *known == f)
+                    .ok_or(f)
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|f| {
+                response::bad_request(format!(
+                    "Unknown field '{f}'; expected any of name, namespace, {}",
+                    SUMMARY_FIELDS.join(", ")
+                ))
+            })?,
+    };
+    if query
+        .limit
+        .is_some_and(|limit| limit == 0 || limit > MAX_PAGE_SIZE)
+    {
+        return Err(response::bad_request(format!(
+            "limit must be between 1 and {MAX_PAGE_SIZE}"
+        )));
+    }
+    let after = match &query.cursor {
+        None => None,
+        Some(cursor) => match Cursor::decode(cursor) {
+            Some(cursor) if cursor.sort == sort => Some(cursor.position),
+            Some(_) => {
+                return Err(response::bad_request(
+                    "cursor was produced with a different sort",
+                ));
+            }
+            None => return Err(response::bad_request("Invalid cursor")),
+        },
+    };
+
+    let store = agents.snapshot();
+    let mut matching: Vec<((String, String, String), &AgentSpec)> = store
         .iter()
+        .map(|(_, spec)| spec)
+        .filter(|spec| {
+            query
+                .namespace
+                .as_ref()
+                .is_none_or(|ns| *ns == spec.namespace)
+                && selector.matches(&spec.metadata.labels)
+        })
+        .map(|spec| {
+            let position = (
+                field.key(spec),
+                spec.namespace.clone(),
+                spec.metadata.name.clone(),
+            );
+            (position, spec)
+        })
+        .filter(|(position, _)| match &after {
+            None => true,
+            Some(after) if descending => position < after,
+            Some(after) => position > after,
+        })
+        .collect();
+    matching.sort_by(|(a, _), (b, _)| if descending { b.cmp(a) } else { a.cmp(b) });
+
+    let next_cursor = match query.limit {
+        Some(limit) if matching.len() > limit => {
+            matching.truncate(limit);
+            matching.last().map(|(position, _)| {
+                Cursor {
+                    sort: sort.to_string(),
+                    position: position.clone(),
+                }
+                .encode()
+            })
+        }
+        _ => None,
+    };
+    let has = |field: &str| fields.contains(&field);
+    let agents = matching
+        .into_iter()
         .map(|(_, spec)| AgentSummary {
             name: spec.metadata.name.clone(),
-            description: spec.metadata.description.clone(),
-            version: spec.metadata.version.clone(),
+            namespace: spec.namespace.clone(),
+            description: spec
+                .metadata
+                .description
+                .clone()
+                .filter(|_| has("description")),
+            version: spec.metadata.version.clone().filter(|_| has("version")),
+            labels: has("labels").then(|| spec.metadata.labels.clone()),
+            provider: has("provider").then(|| spec.model.provider.to_string()),
+            model: has("model").then(|| spec.model.name.clone()),
         })
         .collect();
 
-    Json(AgentsResponse { agents })
+    Ok(Json(AgentsResponse {
+        agents,
+        next_cursor,
+    }))
+}
+
+/// Get an agent by name. Names that exist in several namespaces, none of them the
+/// default one, have to be requested with their namespace.
+pub async fn get_agent(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(query): Query<AgentQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let store = state.agents.snapshot();
+    let Some(agent) = store.fetch(&name).await else {
+        return agent_not_found(&store, &name);
+    };
+    agent_detail(&state, &agent, query, &headers)
+}
+
+/// Get an agent by namespace and name.
+pub async fn get_namespaced_agent(
+    State(state): State<AppState>,
+    Path((namespace, name)): Path<(String, String)>,
+    Query(query): Query<AgentQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let store = state.agents.snapshot();
+    let Some(agent) = store.fetch_in(&namespace, &name).await else {
+        return namespaced_agent_not_found(&namespace, &name);
+    };
+    agent_detail(&state, &agent, query, &headers)
+}
+
+/// Get an agent's `agent.yaml` exactly as it is on disk.
+pub async fn get_agent_spec(
+    State(agents): State<AgentRegistry>,
+    Path(name): Path<String>,
+    Query(query): Query<SpecQuery>,
+) -> Response {
+    let store = agents.snapshot();
+    let Some(agent) = store.fetch(&name).await else {
+        return agent_not_found(&store, &name);
+    };
+    agent_spec(&store, &agent, query)
+}
+
+/// Get the `agent.yaml` of an agent in a namespace exactly as it is on disk.
+pub async fn get_namespaced_agent_spec(
+    State(agents): State<AgentRegistry>,
+    Path((namespace, name)): Path<(String, String)>,
+    Query(query): Query<SpecQuery>,
+) -> Response {
+    let store = agents.snapshot();
+    let Some(agent) = store.fetch_in(&namespace, &name).await else {
+        return namespaced_agent_not_found(&namespace, &name);
+    };
+    agent_spec(&store, &agent, query)
 }
 
-pub async fn get_agent(State(store): State<AgentStore>, Path(name): Path<String>) -> Response {
-    let Some(agent) = store.get(&name) else {
-        return response::not_found(format!("Agent '{name}' not found")).into_response();
+/// `404` for a name that is not loaded, pointing at the namespaced route if the name
+/// is ambiguous.
+pub(super) fn agent_not_found(store: &AgentStore, name: &str) -> Response {
+    let namespaces = store.namespaces_of(name);
+    let detail = if namespaces.len() > 1 {
+        format!(
+            "Agent name '{name}' is used in namespaces {}; use /api/v1/agents/{{namespace}}/{name}",
+            namespaces.join(", ")
+        )
+    } else {
+        format!("Agent '{name}' not found")
     };
+    response::not_found(detail).into_response()
+}
+
+pub(super) fn namespaced_agent_not_found(namespace: &str, name: &str) -> Response {
+    response::not_found(format!(
+        "Agent '{name}' not found in namespace '{namespace}'"
+    ))
+    .into_response()
+}
 
+fn agent_spec(store: &AgentStore, agent: &AgentSpec, query: SpecQuery) -> Response {
+    let read_error = |e: std::io::Error| match e.kind() {
+        std::io::ErrorKind::NotFound => response::not_found(format!(
+            "agent.yaml of '{}' no longer exists",
+            agent.metadata.name
+        )),
+        _ => response::internal_error(format!("Failed to read agent files: {e}")),
+    };
+    if !query.bundle {
+        return match store.read_manifest(agent) {
+            Ok(manifest) => {
+                ([(header::CONTENT_TYPE, "application/yaml")], manifest).into_response()
+            }
+            Err(e) => read_error(e).into_response(),
+        };
+    }
+
+    let files = match store.read_bundle(agent) {
+        Ok(files) => files,
+        Err(e) => return read_error(e).into_response(),
+    };
+    Json(SpecBundleResponse {
+        name: agent.metadata.name.clone(),
+        namespace: agent.namespace.clone(),
+        files: files
+            .into_iter()
+            .map(|f| SourceFileResponse {
+                path: f.path,
+                content: f.content,
+            })
+            .collect(),
+    })
+    .into_response()
+}
+
+/// The agent's details. Unrendered responses carry the revision as their `ETag` and
+/// answer a matching `If-None-Match` with `304 Not Modified`; rendered ones also depend
+/// on the user and the clock, so they have no `ETag`.
+fn agent_detail(
+    state: &AppState,
+    agent: &AgentSpec,
+    query: AgentQuery,
+    headers: &HeaderMap,
+) -> Response {
+    let etag = (!query.render).then(|| agent.revision.etag());
+    if etag.is_some()
+        && let Some(if_none_match) = headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|v| v.to_str().ok())
+        && agent.revision.matches_weak(if_none_match)
+    {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, agent.revision.etag())],
+        )
+            .into_response();
+    }
+
+    let mut instructions = agent.instructions.as_ref().map(PromptResponse::from);
+    if query.render {
+        let timezone = match query.user_timezone.as_deref().map(str::parse::<Tz>) {
+            None => Tz::UTC,
+            Some(Ok(tz)) => tz,
+            Some(Err(_)) => {
+                return response::bad_request(format!(
+                    "Unknown timezone '{}'",
+                    query.user_timezone.unwrap_or_default()
+                ))
+                .into_response();
+            }
+        };
+        let ctx = RenderContext {
+            user_name: query.user_name.as_deref(),
+            timezone,
+            now: Utc::now(),
+            data_dir: &state.data_dir,
+        };
+        if let Some(instructions) = &mut instructions {
+            instructions.content = agent.render_instructions(&ctx);
+        }
+    }
+
+    let store = state.agents.snapshot();
     let response = AgentDetailResponse {
         api_version: agent.api_version.clone(),
         kind: agent.kind.clone(),
         metadata: MetadataResponse {
             name: agent.metadata.name.clone(),
+            namespace: agent.namespace.clone(),
             description: agent.metadata.description.clone(),
             version: agent.metadata.version.clone(),
             labels: agent.metadata.labels.clone(),
@@ -98,11 +633,341 @@ pub async fn get_agent(State(store): State<AgentStore>, Path(name): Path<String>
                 max_input_tokens: agent.model.max_input_tokens,
                 max_output_tokens: agent.model.max_output_tokens,
                 base_url: agent.model.base_url.clone(),
+                top_p: agent.model.top_p,
+                top_k: agent.model.top_k,
+                stop: agent.model.stop.clone(),
+                seed: agent.model.seed,
+                presence_penalty: agent.model.presence_penalty,
+                frequency_penalty: agent.model.frequency_penalty,
+                reasoning_effort: agent.model.reasoning_effort.map(|e| e.as_str()),
+                extra: agent.model.extra.clone(),
             },
-            system_prompt: agent.system_prompt.clone(),
-            instructions: agent.instructions.clone(),
+            system_prompt: agent.system_prompt.as_ref().map(PromptResponse::from),
+            instructions,
+            bootstrap: agent
+                .bootstrap
+                .iter()
+                .map(|b| BootstrapResponse {
+                    path: b.path.clone(),
+                    required: b.required,
+                    max_tokens: b.max_tokens,
+                    loaded: b.content.is_some(),
+                    size: b.size,
+                    truncated: b.truncated,
+                })
+                .collect(),
+            skills: agent
+                .skills
+                .iter()
+                .map(|skill| SkillResponse {
+                    id: skill.id.clone(),
+                    path: store.relative_path(&skill.dir),
+                    description: skill.frontmatter.description.clone(),
+                    version: skill.frontmatter.version.clone(),
+                    allowed_tools: skill.frontmatter.allowed_tools.clone(),
+                    triggers: skill
+                        .frontmatter
+                        .triggers
+                        .iter()
+                        .map(|t| SkillTriggerResponse {
+                            pattern: t.pattern.clone(),
+                            schedule: t.schedule.clone(),
+                        })
+                        .collect(),
+                    references: skill.references.clone(),
+                    scripts: skill.scripts.clone(),
+                    assets: skill.assets.clone(),
+                })
+                .collect(),
+            tools: agent
+                .tools
+                .iter()
+                .map(|tool| ToolResponse {
+                    name: tool.name().to_string(),
+                    kind: tool.kind(),
+                })
+                .collect(),
+        },
+        extends: agent
+            .parents
+            .iter()
+            .map(|p| ParentResponse {
+                name: p.name.clone(),
+                path: store.relative_path(&p.dir),
+            })
+            .collect(),
+        revision: RevisionResponse {
+            hash: agent.revision.hash.clone(),
+            loaded_at: agent
+                .revision
+                .loaded_at
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
         },
     };
 
-    (StatusCode::OK, Json(response)).into_response()
+    let mut response = (StatusCode::OK, Json(response)).into_response();
+    if let Some(etag) = etag
+        && let Ok(value) = etag.parse()
+    {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{AgentStore, LoadOptions};
+    use crate::config::ProvidersConfig;
+    use crate::llm::Providers;
+    use crate::server::build_app;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use std::fs;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    fn app(tmp: &TempDir) -> axum::Router {
+        let agents_dir = tmp.path().join("agents");
+        let agents = [
+            (
+                "billing",
+                "tier: premium\n    domain: finance",
+                "openrouter/b",
+            ),
+            ("legal", "tier: premium\n    domain: legal", "openrouter/a"),
+            ("support", "tier: free", "anthropic/claude-sonnet-4"),
+            ("triage", "tier: premium\n    env: staging", "openrouter/c"),
+        ];
+        for (name, labels, model) in agents {
+            let dir = agents_dir.join(name);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(
+                dir.join("agent.yaml"),
+                format!(
+                    "apiVersion: agnx/v1\nkind: Agent\nmetadata:\n  name: {name}\n  labels:\n    {labels}\nspec:\n  model: {model}\n"
+                ),
+            )
+            .unwrap();
+        }
+        let store = AgentStore::scan(&agents_dir, &LoadOptions::default()).store;
+        build_app(
+            AppState {
+                agents: AgentRegistry::new(store),
+                data_dir: Arc::new(tmp.path().to_path_buf()),
+                providers: Providers::new(ProvidersConfig::default()).unwrap(),
+                admin_token: None,
+            },
+            30,
+        )
+    }
+
+    async fn list(app: &axum::Router, query: &str) -> (StatusCode, serde_json::Value) {
+        let request = Request::get(format!("/api/v1/agents?{query}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn names(body: &serde_json::Value) -> Vec<&str> {
+        body["agents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["name"].as_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn list_filters_by_labels_and_sorts() {
+        let tmp = TempDir::new().unwrap();
+        let app = app(&tmp);
+
+        let (_, body) = list(&app, "").await;
+        assert_eq!(names(&body), ["billing", "legal", "support", "triage"]);
+        assert!(body["agents"][0].get("labels").is_none());
+
+        let (_, body) = list(&app, "labelSelector=tier%3Dpremium,domain!%3Dlegal").await;
+        assert_eq!(names(&body), ["billing", "triage"]);
+        let (_, body) = list(&app, "labelSelector=env%20in%20(prod,staging)").await;
+        assert_eq!(names(&body), ["triage"]);
+
+        let (_, body) = list(&app, "sort=-model&fields=model,provider,labels").await;
+        assert_eq!(names(&body), ["support", "triage", "billing", "legal"]);
+        assert_eq!(body["agents"][1]["provider"], "openrouter");
+        assert_eq!(body["agents"][1]["model"], "c");
+        assert_eq!(body["agents"][1]["labels"]["env"], "staging");
+
+        for query in [
+            "labelSelector=env%20in%20(prod",
+            "sort=size",
+            "fields=secrets",
+            "limit=0",
+            "cursor=zz",
+        ] {
+            let (status, body) = list(&app, query).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{query}: {body}");
+        }
+    }
+
+    #[tokio::test]
+    async fn list_paginates_with_cursors() {
+        let tmp = TempDir::new().unwrap();
+        let app = app(&tmp);
+
+        let mut pages = Vec::new();
+        let mut query = "sort=-name&limit=3".to_string();
+        loop {
+            let (status, body) = list(&app, &query).await;
+            assert_eq!(status, StatusCode::OK);
+            pages.push(names(&body).join(","));
+            let Some(cursor) = body["next_cursor"].as_str() else {
+                break;
+            };
+            query = format!("sort=-name&limit=3&cursor={cursor}");
+        }
+        assert_eq!(pages, ["triage,support,legal", "billing"]);
+
+        let (_, body) = list(&app, "sort=-name&limit=3").await;
+        let cursor = body["next_cursor"].as_str().unwrap();
+        let (status, _) = list(&app, &format!("sort=name&cursor={cursor}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn spec_returns_the_manifest_verbatim() {
+        let tmp = TempDir::new().unwrap();
+        let app = app(&tmp);
+        let manifest = fs::read_to_string(tmp.path().join("agents/support/agent.yaml")).unwrap();
+
+        for uri in [
+            "/api/v1/agents/support/spec",
+            "/api/v1/agents/default/support/spec",
+        ] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            assert_eq!(response.headers()[header::CONTENT_TYPE], "application/yaml");
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(bytes, manifest.as_bytes());
+        }
+
+        let request = Request::get("/api/v1/agents/support/spec?bundle=true")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["files"][0]["path"], "agent.yaml");
+        assert_eq!(body["files"][0]["content"], manifest.as_str());
+
+        let request = Request::get("/api/v1/agents/nobody/spec")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn reserved_names_cannot_be_shadowed_by_static_routes() {
+        let tmp = TempDir::new().unwrap();
+        let agents_dir = tmp.path().join("agents");
+        for (dir, name) in [("team/spec", "spec"), ("team/helper", "helper")] {
+            let dir = agents_dir.join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(
+                dir.join("agent.yaml"),
+                format!(
+                    "apiVersion: agnx/v1\nkind: Agent\nmetadata:\n  name: {name}\nspec:\n  model: openai/gpt-4o\n"
+                ),
+            )
+            .unwrap();
+        }
+        let report = AgentStore::scan(&agents_dir, &LoadOptions::default());
+        let rejected: Vec<_> = report
+            .warnings
+            .iter()
+            .filter_map(|warning| match warning {
+                crate::agent::AgentScanWarning::InvalidAgent { path, error } => {
+                    Some((path.clone(), error.diagnostics()[0].hint.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(rejected.len(), 1, "{rejected:?}");
+        assert!(rejected[0].0.ends_with("team/spec"));
+        assert!(rejected[0].1.as_ref().unwrap().contains("reserved"));
+
+        let app = build_app(
+            AppState {
+                agents: AgentRegistry::new(report.store),
+                data_dir: Arc::new(tmp.path().to_path_buf()),
+                providers: Providers::new(ProvidersConfig::default()).unwrap(),
+                admin_token: None,
+            },
+            30,
+        );
+        for (uri, status) in [
+            ("/api/v1/agents/team/helper", StatusCode::OK),
+            ("/api/v1/agents/helper/spec", StatusCode::OK),
+            ("/api/v1/agents/team/helper/spec", StatusCode::OK),
+            // Routed to the spec of an agent named `team`, which does not exist.
+            ("/api/v1/agents/team/spec", StatusCode::NOT_FOUND),
+        ] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn detail_paths_are_relative_to_the_agents_directory() {
+        let tmp = TempDir::new().unwrap();
+        let write = |dir: &str, text: &str| {
+            let dir = tmp.path().join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("agent.yaml"), text).unwrap();
+        };
+        write(
+            "shared/root",
+            "apiVersion: agnx/v1\nkind: Agent\nmetadata:\n  name: root\nspec:\n  model: openai/gpt-4o\n",
+        );
+        write(
+            "agents/base",
+            "extends: ../../shared/root\nmetadata:\n  name: base\n",
+        );
+        write(
+            "agents/team/child",
+            "extends: ../../base\nmetadata:\n  name: child\n",
+        );
+        let skill = tmp.path().join("agents/team/child/skills/notes");
+        fs::create_dir_all(&skill).unwrap();
+        fs::write(skill.join("SKILL.md"), "Take notes.\n").unwrap();
+
+        let store = AgentStore::scan(&tmp.path().join("agents"), &LoadOptions::default()).store;
+        let app = build_app(
+            AppState {
+                agents: AgentRegistry::new(store),
+                data_dir: Arc::new(tmp.path().to_path_buf()),
+                providers: Providers::new(ProvidersConfig::default()).unwrap(),
+                admin_token: None,
+            },
+            30,
+        );
+        let request = Request::get("/api/v1/agents/team/child")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["extends"][0]["path"], "base");
+        assert_eq!(body["extends"][1]["path"], "../shared/root");
+        assert_eq!(body["spec"]["skills"][0]["path"], "team/child/skills/notes");
+    }
 }
@@ -1,9 +1,17 @@
+mod admin;
 mod agents;
+mod chat;
 mod example_error;
 mod health;
+mod schemas;
 mod version;
 
-pub use agents::{get_agent, list_agents};
+pub use admin::{agent_cache, delete_agent, deploy_agent, reload_agents};
+pub use agents::{
+    get_agent, get_agent_spec, get_namespaced_agent, get_namespaced_agent_spec, list_agents,
+};
+pub use chat::{chat, chat_namespaced, chat_stream, chat_stream_namespaced};
 pub use example_error::{example_bad_request, example_internal_error, example_not_found};
 pub use health::{livez, readyz};
+pub use schemas::{agent_schema, config_schema};
 pub use version::version;
@@ -0,0 +1,14 @@
+use axum::Json;
+use serde_json::Value;
+
+use crate::schema::{SchemaKind, generate};
+
+/// GET /api/v1/schemas/agent - JSON Schema for `agent.yaml`.
+pub async fn agent_schema() -> Json<Value> {
+    Json(generate(SchemaKind::Agent))
+}
+
+/// GET /api/v1/schemas/config - JSON Schema for `agnx.yaml`.
+pub async fn config_schema() -> Json<Value> {
+    Json(generate(SchemaKind::Config))
+}
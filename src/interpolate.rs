@@ -0,0 +1,559 @@
+//! Environment variable interpolation for YAML files (`agnx.yaml`, `agent.yaml`).
+//!
+//! Supported forms:
+//! - `${VAR}`: value of `VAR`; an error if it is unset.
+//! - `${VAR:-default}`: `default` if `VAR` is unset or empty.
+//! - `${VAR:?message}`: an error with `message` if `VAR` is unset or empty.
+//! - `$${`: a literal `${`.
+//!
+//! Only references whose name is a valid environment variable name are expanded, so
+//! template placeholders such as `${user.name}` pass through untouched, as do the
+//! template variables that happen to look like one (`${date}`, `${time}`). Escaped
+//! placeholders (`$${date}`) are kept escaped for the template renderer, so they stay
+//! literal in inline prompts too. Comments are
+//! left alone, and a file can opt out entirely with a leading `# agnx: no-interpolate`
+//! comment.
+//!
+//! A value always stays inside the scalar its reference was written in: it is escaped
+//! in quoted scalars, indented in block scalars, and quoted when it makes up a whole
+//! plain scalar but is not safe to write unquoted (e.g. it contains `#` or a newline).
+//! A value that cannot be written safely is an error rather than a change to the
+//! document's structure.
+
+use std::borrow::Cow;
+
+/// Directive that disables interpolation when it appears in a file's leading comments.
+pub const NO_INTERPOLATE_DIRECTIVE: &str = "agnx: no-interpolate";
+
+/// Template variables whose names are also valid environment variable names. They are
+/// rendered per request (see `agent::template`), so they are never expanded here.
+pub const RESERVED_NAMES: &[&str] = &["date", "time"];
+
+/// A reference that could not be expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterpolationError {
+    /// The variable name, or the raw reference text if it could not be parsed.
+    pub variable: String,
+    pub kind: InterpolationErrorKind,
+    /// 1-indexed position of the `$` that starts the reference.
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterpolationErrorKind {
+    /// `${VAR}` where `VAR` is not set.
+    Unset,
+    /// `${VAR:?message}` where `VAR` is unset or empty.
+    Required(String),
+    /// `${` without a closing `}` on the same line.
+    Unterminated,
+    /// The value cannot be written where the reference is without changing the
+    /// document's structure, e.g. a multi-line value inside an unquoted string.
+    Unquotable,
+}
+
+impl std::fmt::Display for InterpolationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            InterpolationErrorKind::Unset => {
+                write!(f, "environment variable '{}' is not set", self.variable)
+            }
+            InterpolationErrorKind::Required(message) if message.is_empty() => {
+                write!(f, "environment variable '{}' is required", self.variable)
+            }
+            InterpolationErrorKind::Required(message) => {
+                write!(f, "environment variable '{}': {message}", self.variable)
+            }
+            InterpolationErrorKind::Unterminated => {
+                write!(f, "unterminated reference '{}'", self.variable)
+            }
+            InterpolationErrorKind::Unquotable => write!(
+                f,
+                "the value of environment variable '{}' cannot be written here; \
+                 put the reference in double quotes",
+                self.variable
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InterpolationError {}
+
+/// Expand references using the process environment.
+pub fn interpolate_env(text: &str) -> Result<Cow<'_, str>, InterpolationError> {
+    interpolate_with(text, |name| std::env::var(name).ok())
+}
+
+/// Expand references using `lookup` to resolve variables.
+pub fn interpolate_with(
+    text: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Cow<'_, str>, InterpolationError> {
+    if !text.contains("${") || is_disabled(text) {
+        return Ok(Cow::Borrowed(text));
+    }
+
+    let mut scanner = Scanner {
+        lookup: &lookup,
+        quote: Quote::None,
+        block_parent: None,
+    };
+    let mut out = String::with_capacity(text.len());
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        scanner.line(line, index as u32 + 1, &mut out)?;
+    }
+    Ok(Cow::Owned(out))
+}
+
+fn is_disabled(text: &str) -> bool {
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        match line.strip_prefix('#') {
+            Some(comment) if comment.trim() == NO_INTERPOLATE_DIRECTIVE => return true,
+            Some(_) => continue,
+            None => return false,
+        }
+    }
+    false
+}
+
+/// The kind of quoted scalar the scanner is in, which may span lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quote {
+    None,
+    Single,
+    Double,
+}
+
+/// Where a reference was written, which decides how its value is written out.
+#[derive(Debug, Clone, Copy)]
+enum Context<'a> {
+    /// Unquoted; `whole` if the reference makes up the entire scalar.
+    Plain {
+        whole: bool,
+    },
+    Single,
+    Double,
+    /// A line of a `|` or `>` block scalar, indented by the given whitespace.
+    Block(&'a str),
+}
+
+/// What a `$` at the scanner's position stands for.
+enum Expansion<'a> {
+    /// Text copied to the output as is.
+    Verbatim(&'a str),
+    Value {
+        name: &'a str,
+        value: String,
+    },
+}
+
+/// Walks a YAML file line by line, tracking just enough of its structure (quotes,
+/// comments and block scalars) to write each value safely.
+struct Scanner<'a, F> {
+    lookup: &'a F,
+    quote: Quote,
+    /// Indentation of the line that opened the block scalar being scanned, if any.
+    block_parent: Option<usize>,
+}
+
+impl<F: Fn(&str) -> Option<String>> Scanner<'_, F> {
+    fn line(
+        &mut self,
+        line: &str,
+        line_no: u32,
+        out: &mut String,
+    ) -> Result<(), InterpolationError> {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let indent = &content[..content.len() - content.trim_start_matches([' ', '\t']).len()];
+
+        if let Some(parent) = self.block_parent {
+            if content.trim().is_empty() || indent.len() > parent {
+                return self.block_line(line, line_no, indent, out);
+            }
+            self.block_parent = None;
+        }
+        if self.quote == Quote::None && content.trim_start().starts_with('#') {
+            out.push_str(line);
+            return Ok(());
+        }
+
+        let mut at_scalar_start = true;
+        let mut indicator = false;
+        let mut flow_depth = 0usize;
+        let mut code_end = content.len();
+        let mut i = 0;
+        while i < line.len() {
+            let rest = &line[i..];
+            let c = rest.chars().next().unwrap_or_default();
+
+            if c == '$' {
+                let column = i as u32 + 1;
+                let (expansion, len) = self.expansion(rest, line_no, column)?;
+                match expansion {
+                    Expansion::Verbatim(text) => out.push_str(text),
+                    Expansion::Value { name, value } => {
+                        let context = match self.quote {
+                            Quote::None => Context::Plain {
+                                whole: at_scalar_start && ends_scalar(&rest[len..], flow_depth),
+                            },
+                            Quote::Single => Context::Single,
+                            Quote::Double => Context::Double,
+                        };
+                        write_value(&value, context, out).map_err(|kind| InterpolationError {
+                            variable: name.to_string(),
+                            kind,
+                            line: line_no,
+                            column,
+                        })?;
+                    }
+                }
+                at_scalar_start = false;
+                indicator = false;
+                i += len;
+                continue;
+            }
+
+            match self.quote {
+                Quote::Double if c == '\\' => {
+                    // Copy the escape and the character it escapes.
+                    let escaped = rest[1..].chars().next().map_or(0, char::len_utf8);
+                    out.push_str(&rest[..1 + escaped]);
+                    i += 1 + escaped;
+                    continue;
+                }
+                Quote::Double if c == '"' => self.quote = Quote::None,
+                Quote::Single if rest.starts_with("''") => {
+                    out.push_str("''");
+                    i += 2;
+                    continue;
+                }
+                Quote::Single if c == '\'' => self.quote = Quote::None,
+                Quote::Single | Quote::Double => {}
+                Quote::None => {
+                    if c == '#' && (i == 0 || line[..i].ends_with([' ', '\t'])) {
+                        // The rest of the line is a comment.
+                        out.push_str(rest);
+                        code_end = i;
+                        break;
+                    }
+                    if at_scalar_start && c == '"' {
+                        self.quote = Quote::Double;
+                    } else if at_scalar_start && c == '\'' {
+                        self.quote = Quote::Single;
+                    }
+                }
+            }
+
+            if self.quote == Quote::None {
+                let starts = match c {
+                    ' ' | '\t' => at_scalar_start || indicator,
+                    '[' | '{' if at_scalar_start => {
+                        flow_depth += 1;
+                        true
+                    }
+                    ']' | '}' if flow_depth > 0 => {
+                        flow_depth -= 1;
+                        false
+                    }
+                    ',' => flow_depth > 0,
+                    _ => false,
+                };
+                indicator = c == ':' || (at_scalar_start && matches!(c, '-' | '?'));
+                at_scalar_start = starts;
+            } else {
+                at_scalar_start = false;
+                indicator = false;
+            }
+            out.push(c);
+            i += c.len_utf8();
+        }
+
+        if self.quote == Quote::None && opens_block_scalar(&content[..code_end.min(content.len())])
+        {
+            self.block_parent = Some(indent.len());
+        }
+        Ok(())
+    }
+
+    /// A line inside a block scalar: everything on it is content.
+    fn block_line(
+        &mut self,
+        line: &str,
+        line_no: u32,
+        indent: &str,
+        out: &mut String,
+    ) -> Result<(), InterpolationError> {
+        let mut rest = line;
+        while let Some(pos) = rest.find('$') {
+            out.push_str(&rest[..pos]);
+            let column = (line.len() - rest.len() + pos) as u32 + 1;
+            let (expansion, len) = self.expansion(&rest[pos..], line_no, column)?;
+            match expansion {
+                Expansion::Verbatim(text) => out.push_str(text),
+                Expansion::Value { value, .. } => {
+                    // Block scalars can hold anything, so this cannot fail.
+                    let _ = write_value(&value, Context::Block(indent), out);
+                }
+            }
+            rest = &rest[pos + len..];
+        }
+        out.push_str(rest);
+        Ok(())
+    }
+
+    /// Parse the reference at the start of `tail`, which starts with `$`, returning
+    /// what it expands to and how many bytes it spans.
+    fn expansion<'t>(
+        &self,
+        tail: &'t str,
+        line_no: u32,
+        column: u32,
+    ) -> Result<(Expansion<'t>, usize), InterpolationError> {
+        if let Some(escaped) = tail.strip_prefix("$${") {
+            // Template placeholders are never expanded here, so an escaped one is left
+            // for the template renderer, which shares the escape.
+            if let Some(end) = escaped.find('}')
+                && !is_expandable(escaped[..end].split(':').next().unwrap_or_default())
+            {
+                return Ok((Expansion::Verbatim(&tail[..end + 4]), end + 4));
+            }
+            return Ok((Expansion::Verbatim("${"), 3));
+        }
+        let Some(body_start) = tail.strip_prefix("${") else {
+            return Ok((Expansion::Verbatim("$"), 1));
+        };
+        let Some(end) = body_start.find('}') else {
+            return Err(InterpolationError {
+                variable: tail.trim_end().to_string(),
+                kind: InterpolationErrorKind::Unterminated,
+                line: line_no,
+                column,
+            });
+        };
+
+        let len = end + 3;
+        let verbatim = Ok((Expansion::Verbatim(&tail[..len]), len));
+        let body = &body_start[..end];
+        let (name, op) = match body.find(':') {
+            Some(i) => (&body[..i], Some(&body[i..])),
+            None => (body, None),
+        };
+        if !is_expandable(name) {
+            // Not an environment reference (e.g. `${user.name}`); keep it verbatim.
+            return verbatim;
+        }
+
+        let value = (self.lookup)(name);
+        let error = |kind| InterpolationError {
+            variable: name.to_string(),
+            kind,
+            line: line_no,
+            column,
+        };
+        let value = match op {
+            None => value.ok_or_else(|| error(InterpolationErrorKind::Unset))?,
+            Some(op) if op.starts_with(":-") => match value.filter(|v| !v.is_empty()) {
+                Some(v) => v,
+                None => op[2..].to_string(),
+            },
+            Some(op) if op.starts_with(":?") => match value.filter(|v| !v.is_empty()) {
+                Some(v) => v,
+                None => return Err(error(InterpolationErrorKind::Required(op[2..].to_string()))),
+            },
+            // Unknown operator; leave the reference as written.
+            Some(_) => return verbatim,
+        };
+        Ok((Expansion::Value { name, value }, len))
+    }
+}
+
+/// Write `value` so it stays inside the scalar described by `context`.
+fn write_value(
+    value: &str,
+    context: Context<'_>,
+    out: &mut String,
+) -> Result<(), InterpolationErrorKind> {
+    match context {
+        Context::Plain { .. } if is_plain_safe(value) => out.push_str(value),
+        Context::Plain { whole: true } | Context::Double => {
+            let quoted = serde_json::to_string(value).unwrap_or_default();
+            match context {
+                Context::Double => out.push_str(&quoted[1..quoted.len() - 1]),
+                _ => out.push_str(&quoted),
+            }
+        }
+        Context::Plain { whole: false } => return Err(InterpolationErrorKind::Unquotable),
+        Context::Single if value.contains(['\n', '\r']) => {
+            return Err(InterpolationErrorKind::Unquotable);
+        }
+        Context::Single => out.push_str(&value.replace('\'', "''")),
+        Context::Block(indent) => out.push_str(&value.replace('\n', &format!("\n{indent}"))),
+    }
+    Ok(())
+}
+
+/// Whether `value` reads back as the same string when written into a plain scalar.
+/// Empty values are allowed so that `key: ${EMPTY}` stays null.
+fn is_plain_safe(value: &str) -> bool {
+    let Some(first) = value.chars().next() else {
+        return true;
+    };
+    !"-?:,[]{}#&*!|>'\"%@`".contains(first)
+        && value.trim() == value
+        && !value.contains(|c: char| c.is_control() || "#,[]{}".contains(c))
+        && !value.contains(": ")
+        && !value.ends_with(':')
+}
+
+/// Whether the text after a reference ends the plain scalar it is in.
+fn ends_scalar(after: &str, flow_depth: usize) -> bool {
+    let trimmed = after.trim_start_matches([' ', '\t']);
+    trimmed.trim_end().is_empty()
+        || (trimmed.starts_with('#') && trimmed.len() < after.len())
+        || trimmed.starts_with(": ")
+        || trimmed.trim_end() == ":"
+        || (flow_depth > 0 && trimmed.starts_with([',', ']', '}']))
+}
+
+/// Whether a line (without its comment) ends with a `|` or `>` block scalar header.
+fn opens_block_scalar(code: &str) -> bool {
+    let code = code.trim_end();
+    let header = code.trim_end_matches(|c: char| c.is_ascii_digit() || c == '+' || c == '-');
+    if code.len() - header.len() > 2 || !header.ends_with(['|', '>']) {
+        return false;
+    }
+    let before = header[..header.len() - 1].trim_end();
+    before.is_empty() || before.ends_with(':') || before == "-" || before.ends_with(" -")
+}
+
+/// Whether `${name}` is an environment reference rather than a template placeholder.
+fn is_expandable(name: &str) -> bool {
+    is_env_name(name) && !RESERVED_NAMES.contains(&name)
+}
+
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "TOKEN" => Some("s3cret".to_string()),
+            "EMPTY" => Some(String::new()),
+            "MULTI" => Some("one\ntwo: 2".to_string()),
+            "HASH" => Some("pa#ss 'q' \"d\"".to_string()),
+            _ => None,
+        }
+    }
+
+    fn expand(text: &str) -> Result<String, InterpolationError> {
+        interpolate_with(text, lookup).map(Cow::into_owned)
+    }
+
+    #[test]
+    fn expands_set_variables_and_defaults() {
+        assert_eq!(expand("token: ${TOKEN}\n").unwrap(), "token: s3cret\n");
+        assert_eq!(
+            expand("url: ${DATABASE_URL:-sqlite://local.db}").unwrap(),
+            "url: sqlite://local.db"
+        );
+        assert_eq!(expand("a: ${EMPTY:-fallback}").unwrap(), "a: fallback");
+        assert_eq!(expand("a: ${TOKEN:?needed}").unwrap(), "a: s3cret");
+        assert_eq!(expand("cost: $5 and $HOME").unwrap(), "cost: $5 and $HOME");
+    }
+
+    #[test]
+    fn reports_missing_variables_with_position() {
+        let err = expand("server:\n  token: ${MISSING}\n").unwrap_err();
+        assert_eq!(err.variable, "MISSING");
+        assert_eq!(err.kind, InterpolationErrorKind::Unset);
+        assert_eq!((err.line, err.column), (2, 10));
+        assert_eq!(
+            err.to_string(),
+            "line 2, column 10: environment variable 'MISSING' is not set"
+        );
+
+        let err = expand("a: ${EMPTY:?set EMPTY to a token}").unwrap_err();
+        assert_eq!(
+            err.kind,
+            InterpolationErrorKind::Required("set EMPTY to a token".to_string())
+        );
+
+        let err = expand("a: ${OPEN").unwrap_err();
+        assert_eq!(err.kind, InterpolationErrorKind::Unterminated);
+    }
+
+    #[test]
+    fn escapes_templates_and_comments_are_left_alone() {
+        assert_eq!(expand("a: $${TOKEN}").unwrap(), "a: ${TOKEN}");
+        assert_eq!(expand("a: $${date} $${user.name}").unwrap(), "a: $${date} $${user.name}");
+        assert_eq!(expand("a: Hi ${user.name}").unwrap(), "a: Hi ${user.name}");
+        assert_eq!(expand("a: On ${date}").unwrap(), "a: On ${date}");
+        assert_eq!(expand("# ${MISSING}\na: 1").unwrap(), "# ${MISSING}\na: 1");
+    }
+
+    #[test]
+    fn directive_disables_interpolation() {
+        let text = "# Prompt file\n# agnx: no-interpolate\n\nprompt: ${MISSING}\n";
+        assert_eq!(expand(text).unwrap(), text);
+
+        // Only honoured in the leading comment block.
+        let text = "a: 1\n# agnx: no-interpolate\nb: ${MISSING}\n";
+        assert!(expand(text).is_err());
+    }
+
+    #[test]
+    fn values_stay_inside_their_scalar() {
+        fn parse(text: &str) -> serde_json::Value {
+            serde_saphyr::from_str(&expand(text).unwrap()).unwrap()
+        }
+
+        // A whole plain scalar is quoted when needed.
+        assert_eq!(
+            parse("a: ${MULTI}\nb: ${HASH} # comment\n"),
+            serde_json::json!({"a": "one\ntwo: 2", "b": "pa#ss 'q' \"d\""})
+        );
+        // Quoted scalars are escaped.
+        assert_eq!(
+            parse("a: \"x ${MULTI}\"\nb: 'x ${HASH}'\n"),
+            serde_json::json!({"a": "x one\ntwo: 2", "b": "x pa#ss 'q' \"d\""})
+        );
+        // Block scalars are indented.
+        assert_eq!(
+            parse("a: |\n  first\n  ${MULTI}\nb: ${TOKEN}\n"),
+            serde_json::json!({"a": "first\none\ntwo: 2\n", "b": "s3cret"})
+        );
+        // Flow collections and sequences.
+        assert_eq!(
+            parse("a: [${HASH}, ${TOKEN}]\nb:\n  - ${MULTI}\n"),
+            serde_json::json!({"a": ["pa#ss 'q' \"d\"", "s3cret"], "b": ["one\ntwo: 2"]})
+        );
+        // Plain values are written as is, so they are typed by where they land.
+        assert_eq!(expand("port: ${PORT:-8080}").unwrap(), "port: 8080");
+    }
+
+    #[test]
+    fn unsafe_values_inside_unquoted_text_are_errors() {
+        let err = expand("a: x ${HASH}\n").unwrap_err();
+        assert_eq!(err.kind, InterpolationErrorKind::Unquotable);
+        assert_eq!((err.line, err.column), (1, 6));
+        assert_eq!(
+            expand("a: '${MULTI}'").unwrap_err().kind,
+            InterpolationErrorKind::Unquotable
+        );
+        // References in trailing comments are not expanded.
+        assert_eq!(expand("a: 1 # ${MISSING}").unwrap(), "a: 1 # ${MISSING}");
+    }
+}
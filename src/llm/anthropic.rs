@@ -0,0 +1,618 @@
+//! Anthropic's native Messages API.
+//!
+//! Unlike Chat Completions, the system prompt is a separate `system` field, messages are
+//! lists of content blocks, tool calls and their results are `tool_use`/`tool_result`
+//! blocks, and streams are typed events. The agent's system prompt and instructions are
+//! marked as prompt-cache breakpoints, so the stable prefix of every conversation is
+//! cached.
+
+use async_trait::async_trait;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+
+use super::sse::{self, EventHandler, Queue, SseEvent};
+use super::{
+    ChatModel, ChatRequest, ChatStream, Completion, Endpoint, FinishReason, Message, ProviderError,
+    Role, StreamEvent, ToolCall, Usage, arguments, number,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+pub const API_KEY_ENV: &str = "ANTHROPIC_API_KEY";
+pub const API_VERSION: &str = "2023-06-01";
+
+/// `max_tokens` when `spec.model.max_output_tokens` is unset; the API requires one.
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// The API allows four cache breakpoints per request.
+const MAX_CACHE_BREAKPOINTS: usize = 4;
+
+pub struct Anthropic {
+    http: reqwest::Client,
+    endpoint: Endpoint,
+}
+
+impl Anthropic {
+    pub(super) fn new(http: reqwest::Client, endpoint: Endpoint) -> Self {
+        Self { http, endpoint }
+    }
+
+    async fn send(&self, body: &Value, stream: bool) -> Result<reqwest::Response, ProviderError> {
+        self.endpoint
+            .post(&self.http, "/messages", body, stream)
+            .await
+    }
+}
+
+#[async_trait]
+impl ChatModel for Anthropic {
+    async fn complete(&self, request: ChatRequest) -> Result<Completion, ProviderError> {
+        let provider = &self.endpoint.provider;
+        let response = self.send(&request_body(&request, false), false).await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| ProviderError::from_reqwest(provider, e))?;
+        parse_completion(provider, &body)
+    }
+
+    async fn stream(&self, request: ChatRequest) -> Result<ChatStream, ProviderError> {
+        let response = self.send(&request_body(&request, true), true).await?;
+        Ok(sse::chat_stream(
+            &self.endpoint.provider,
+            response,
+            self.endpoint.timeouts.idle,
+            EventAssembler::new(&self.endpoint.provider),
+        ))
+    }
+}
+
+/// The Messages API request for `request`. Parameters Anthropic does not accept are left
+/// out; `spec.model.extra` is applied last, so it can override anything.
+fn request_body(request: &ChatRequest, stream: bool) -> Value {
+    let model = &request.model;
+    let mut body = Map::new();
+    body.insert("model".into(), json!(model.name));
+    body.insert(
+        "max_tokens".into(),
+        json!(model.max_output_tokens.unwrap_or(DEFAULT_MAX_TOKENS)),
+    );
+    let (system, messages) = wire_messages(&request.messages);
+    if !system.is_empty() {
+        body.insert("system".into(), system.into());
+    }
+    body.insert("messages".into(), messages.into());
+    let mut set = |key: &str, value: Option<Value>| {
+        if let Some(value) = value {
+            body.insert(key.into(), value);
+        }
+    };
+    set("temperature", model.temperature.map(number));
+    set("top_p", model.top_p.map(number));
+    if model.provider.supports("top_k") {
+        set("top_k", model.top_k.map(Value::from));
+    }
+    set(
+        "stop_sequences",
+        (!model.stop.is_empty()).then(|| json!(model.stop)),
+    );
+    set(
+        "tools",
+        (!request.tools.is_empty()).then(|| {
+            request
+                .tools
+                .iter()
+                .map(|tool| {
+                    let mut wire = json!({ "name": tool.name, "input_schema": tool.parameters });
+                    if let Some(description) = &tool.description {
+                        wire["description"] = json!(description);
+                    }
+                    wire
+                })
+                .collect()
+        }),
+    );
+    if stream {
+        set("stream", Some(json!(true)));
+    }
+    for (key, value) in &model.extra {
+        body.insert(key.clone(), value.clone());
+    }
+    Value::Object(body)
+}
+
+/// The `system` blocks and the `messages` of a conversation.
+///
+/// Leading system messages (the agent's system prompt and instructions) each end with a
+/// cache breakpoint; later ones are appended without. Tool results go back as
+/// `tool_result` blocks in a user turn, and consecutive messages for the same role are
+/// merged, since the API expects user and assistant turns to alternate.
+fn wire_messages(messages: &[Message]) -> (Vec<Value>, Vec<Value>) {
+    let leading = messages
+        .iter()
+        .take_while(|message| message.role == Role::System)
+        .count();
+    let uncached = leading.saturating_sub(MAX_CACHE_BREAKPOINTS);
+    let mut system = Vec::new();
+    let mut turns: Vec<(&str, Vec<Value>)> = Vec::new();
+    for (i, message) in messages.iter().enumerate() {
+        let (role, blocks) = match message.role {
+            Role::System => {
+                let mut block = json!({ "type": "text", "text": message.content });
+                if (uncached..leading).contains(&i) {
+                    block["cache_control"] = json!({ "type": "ephemeral" });
+                }
+                system.push(block);
+                continue;
+            }
+            Role::User => ("user", text_block(&message.content)),
+            Role::Tool => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id.as_deref().unwrap_or_default(),
+                    "content": message.content,
+                })],
+            ),
+            Role::Assistant => {
+                let mut blocks = text_block(&message.content);
+                blocks.extend(message.tool_calls.iter().map(|call| {
+                    json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.name,
+                        "input": tool_input(&call.arguments),
+                    })
+                }));
+                ("assistant", blocks)
+            }
+        };
+        match turns.last_mut() {
+            Some((last, content)) if *last == role => content.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+    let messages = turns
+        .into_iter()
+        .map(|(role, content)| json!({ "role": role, "content": content }))
+        .collect();
+    (system, messages)
+}
+
+/// Empty text blocks are rejected, so empty content becomes no block at all.
+fn text_block(text: &str) -> Vec<Value> {
+    if text.is_empty() {
+        Vec::new()
+    } else {
+        vec![json!({ "type": "text", "text": text })]
+    }
+}
+
+/// `tool_use.input` must be an object; arguments that are not one are sent as `{}`.
+fn tool_input(value: &Value) -> Value {
+    match value {
+        Value::Object(_) => value.clone(),
+        Value::String(raw) => match arguments(raw) {
+            object @ Value::Object(_) => object,
+            _ => json!({}),
+        },
+        _ => json!({}),
+    }
+}
+
+fn parse_completion(provider: &str, body: &Value) -> Result<Completion, ProviderError> {
+    let content = body["content"]
+        .as_array()
+        .ok_or_else(|| ProviderError::invalid_response(provider, "no content in response"))?;
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for block in content {
+        match block["type"].as_str() {
+            Some("text") => text.push_str(block["text"].as_str().unwrap_or_default()),
+            Some("tool_use") => tool_calls.push(ToolCall {
+                id: block["id"].as_str().unwrap_or_default().to_string(),
+                name: block["name"].as_str().unwrap_or_default().to_string(),
+                arguments: block["input"].clone(),
+            }),
+            // Thinking blocks and anything newer are not part of the reply.
+            _ => {}
+        }
+    }
+    Ok(Completion {
+        message: Message {
+            tool_calls,
+            ..Message::new(Role::Assistant, text)
+        },
+        finish_reason: body["stop_reason"]
+            .as_str()
+            .map_or(FinishReason::Stop, finish_reason),
+        usage: usage(&body["usage"]).map(|(input_tokens, output_tokens)| Usage {
+            input_tokens,
+            output_tokens,
+        }),
+    })
+}
+
+fn finish_reason(stop_reason: &str) -> FinishReason {
+    match stop_reason {
+        "end_turn" | "stop_sequence" => FinishReason::Stop,
+        "max_tokens" => FinishReason::Length,
+        "tool_use" => FinishReason::ToolCalls,
+        "refusal" => FinishReason::ContentFilter,
+        other => FinishReason::Other(other.to_string()),
+    }
+}
+
+/// Input and output tokens from a `usage` object, either of which may be missing. Input
+/// counts cache reads and writes, which Anthropic reports separately.
+fn usage(usage: &Value) -> Option<(u32, u32)> {
+    if !usage.is_object() {
+        return None;
+    }
+    let count = |key: &str| usage[key].as_u64().and_then(|n| u32::try_from(n).ok());
+    let input = [
+        "input_tokens",
+        "cache_creation_input_tokens",
+        "cache_read_input_tokens",
+    ]
+    .into_iter()
+    .filter_map(count)
+    .sum();
+    Some((input, count("output_tokens").unwrap_or(0)))
+}
+
+/// The HTTP status an `error` event's type corresponds to, for classifying errors that
+/// arrive mid-stream.
+fn error_status(kind: &str) -> reqwest::StatusCode {
+    use reqwest::StatusCode;
+    match kind {
+        "invalid_request_error" => StatusCode::BAD_REQUEST,
+        "authentication_error" => StatusCode::UNAUTHORIZED,
+        "permission_error" => StatusCode::FORBIDDEN,
+        "not_found_error" => StatusCode::NOT_FOUND,
+        "request_too_large" => StatusCode::PAYLOAD_TOO_LARGE,
+        "rate_limit_error" => StatusCode::TOO_MANY_REQUESTS,
+        // `overloaded_error` is sent as 529, which is not a standard status.
+        "overloaded_error" => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Turns the Messages stream (`message_start`, `content_block_*`, `message_delta`,
+/// `message_stop`) into stream events. Tool calls are emitted when their block ends.
+struct EventAssembler {
+    provider: String,
+    /// Open `tool_use` blocks by index: id, name and the JSON received so far.
+    tool_calls: BTreeMap<u64, (String, String, String)>,
+    finish_reason: Option<FinishReason>,
+    input_tokens: u32,
+    output_tokens: u32,
+    done: bool,
+}
+
+impl EventAssembler {
+    fn new(provider: &str) -> Self {
+        Self {
+            provider: provider.to_string(),
+            tool_calls: BTreeMap::new(),
+            finish_reason: None,
+            input_tokens: 0,
+            output_tokens: 0,
+            done: false,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> Result<StreamEvent, ProviderError> {
+        Err(ProviderError::invalid_response(&self.provider, message))
+    }
+}
+
+impl EventHandler for EventAssembler {
+    fn event(&mut self, event: SseEvent, out: &mut Queue) {
+        if self.done {
+            return;
+        }
+        let data: Value = match serde_json::from_str(&event.data) {
+            Ok(data) => data,
+            Err(e) => return out.push_back(self.error(format!("malformed stream event: {e}"))),
+        };
+        let index = data["index"].as_u64().unwrap_or(0);
+        match data["type"].as_str().unwrap_or_default() {
+            "message_start" => {
+                if let Some((input, output)) = usage(&data["message"]["usage"]) {
+                    self.input_tokens = input;
+                    self.output_tokens = output;
+                }
+            }
+            "content_block_start" => {
+                let block = &data["content_block"];
+                if block["type"] == "tool_use" {
+                    self.tool_calls.insert(
+                        index,
+                        (
+                            block["id"].as_str().unwrap_or_default().to_string(),
+                            block["name"].as_str().unwrap_or_default().to_string(),
+                            String::new(),
+                        ),
+                    );
+                }
+            }
+            "content_block_delta" => {
+                let delta = &data["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => {
+                        if let Some(text) = delta["text"].as_str()
+                            && !text.is_empty()
+                        {
+                            out.push_back(Ok(StreamEvent::Token(text.to_string())));
+                        }
+                    }
+                    Some("input_json_delta") => {
+                        if let Some((_, _, raw)) = self.tool_calls.get_mut(&index) {
+                            raw.push_str(delta["partial_json"].as_str().unwrap_or_default());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            "content_block_stop" => {
+                if let Some((id, name, raw)) = self.tool_calls.remove(&index) {
+                    out.push_back(Ok(StreamEvent::ToolCall(ToolCall {
+                        id,
+                        name,
+                        arguments: arguments(&raw),
+                    })));
+                }
+            }
+            "message_delta" => {
+                if let Some(reason) = data["delta"]["stop_reason"].as_str() {
+                    self.finish_reason = Some(finish_reason(reason));
+                }
+                // Cumulative, so it replaces rather than adds.
+                if let Some(output) = data["usage"]["output_tokens"].as_u64() {
+                    self.output_tokens = u32::try_from(output).unwrap_or(u32::MAX);
+                }
+            }
+            "message_stop" => {
+                out.push_back(Ok(StreamEvent::Done {
+                    finish_reason: self.finish_reason.take().unwrap_or(FinishReason::Stop),
+                    usage: Some(Usage {
+                        input_tokens: self.input_tokens,
+                        output_tokens: self.output_tokens,
+                    }),
+                }));
+                self.done = true;
+            }
+            "error" => {
+                let error = &data["error"];
+                out.push_back(Err(ProviderError::from_status(
+                    &self.provider,
+                    error_status(error["type"].as_str().unwrap_or_default()),
+                    &reqwest::header::HeaderMap::new(),
+                    &data.to_string(),
+                )));
+            }
+            // `ping` and event types added after this was written.
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, out: &mut Queue) {
+        if !self.done {
+            out.push_back(self.error("stream ended before the message finished"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{ModelConfig, Provider};
+    use crate::llm::mock::{MockResponse, MockServer};
+    use crate::llm::{Timeouts, ToolDefinition};
+    use futures_util::StreamExt;
+    use reqwest::header::HeaderMap;
+    use std::time::Duration;
+
+    fn client(base_url: &str) -> Anthropic {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "sk-ant-test".parse().unwrap());
+        Anthropic::new(
+            reqwest::Client::new(),
+            Endpoint {
+                provider: "anthropic".to_string(),
+                base_url: base_url.to_string(),
+                headers,
+                timeouts: Timeouts {
+                    completion: Duration::from_millis(500),
+                    idle: Duration::from_millis(500),
+                },
+            },
+        )
+    }
+
+    fn request() -> ChatRequest {
+        let mut model = ModelConfig::new(Provider::Anthropic, "claude-sonnet-4-5");
+        model.temperature = Some(0.2);
+        model.seed = Some(7);
+        ChatRequest {
+            model,
+            messages: vec![
+                Message::new(Role::System, "You are a calculator."),
+                Message::new(Role::System, "Today is Monday."),
+                Message::new(Role::User, "What is 6 * 7?"),
+                Message {
+                    tool_calls: vec![ToolCall {
+                        id: "toolu_1".to_string(),
+                        name: "calculator".to_string(),
+                        arguments: json!({ "expression": "6*7" }),
+                    }],
+                    ..Message::new(Role::Assistant, "")
+                },
+                Message {
+                    tool_call_id: Some("toolu_1".to_string()),
+                    ..Message::new(Role::Tool, "42")
+                },
+                Message::new(Role::User, "Thanks!"),
+            ],
+            tools: vec![ToolDefinition {
+                name: "calculator".to_string(),
+                description: None,
+                parameters: json!({ "type": "object" }),
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn complete_sends_messages_api_requests() {
+        let server = MockServer::start(MockResponse::json(
+            200,
+            json!({
+                "type": "message",
+                "content": [
+                    { "type": "text", "text": "Let me check." },
+                    { "type": "tool_use", "id": "toolu_2", "name": "calculator", "input": { "expression": "42+0" } }
+                ],
+                "stop_reason": "tool_use",
+                "usage": { "input_tokens": 5, "cache_read_input_tokens": 100, "output_tokens": 9 }
+            }),
+        ))
+        .await;
+
+        let completion = client(&server.url()).complete(request()).await.unwrap();
+        assert_eq!(completion.message.content, "Let me check.");
+        assert_eq!(completion.finish_reason, FinishReason::ToolCalls);
+        assert_eq!(completion.message.tool_calls[0].id, "toolu_2");
+        assert_eq!(
+            completion.usage,
+            Some(Usage {
+                input_tokens: 105,
+                output_tokens: 9
+            })
+        );
+
+        let sent = server.request();
+        assert_eq!(sent.path, "/messages");
+        assert_eq!(sent.header("x-api-key"), Some("sk-ant-test"));
+        assert_eq!(
+            sent.body,
+            json!({
+                "model": "claude-sonnet-4-5",
+                "max_tokens": DEFAULT_MAX_TOKENS,
+                "temperature": 0.2,
+                "system": [
+                    { "type": "text", "text": "You are a calculator.", "cache_control": { "type": "ephemeral" } },
+                    { "type": "text", "text": "Today is Monday.", "cache_control": { "type": "ephemeral" } }
+                ],
+                "messages": [
+                    { "role": "user", "content": [{ "type": "text", "text": "What is 6 * 7?" }] },
+                    { "role": "assistant", "content": [
+                        { "type": "tool_use", "id": "toolu_1", "name": "calculator", "input": { "expression": "6*7" } }
+                    ] },
+                    { "role": "user", "content": [
+                        { "type": "tool_result", "tool_use_id": "toolu_1", "content": "42" },
+                        { "type": "text", "text": "Thanks!" }
+                    ] }
+                ],
+                "tools": [{ "name": "calculator", "input_schema": { "type": "object" } }]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_assembles_content_blocks() {
+        let events = [
+            (
+                "message_start",
+                json!({ "type": "message_start", "message": { "usage": { "input_tokens": 20, "output_tokens": 1 } } }),
+            ),
+            (
+                "content_block_start",
+                json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } }),
+            ),
+            ("ping", json!({ "type": "ping" })),
+            (
+                "content_block_delta",
+                json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Checking." } }),
+            ),
+            (
+                "content_block_stop",
+                json!({ "type": "content_block_stop", "index": 0 }),
+            ),
+            (
+                "content_block_start",
+                json!({ "type": "content_block_start", "index": 1, "content_block": { "type": "tool_use", "id": "toolu_1", "name": "calculator", "input": {} } }),
+            ),
+            (
+                "content_block_delta",
+                json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "{\"expression\":" } }),
+            ),
+            (
+                "content_block_delta",
+                json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "\"6*7\"}" } }),
+            ),
+            (
+                "content_block_stop",
+                json!({ "type": "content_block_stop", "index": 1 }),
+            ),
+            (
+                "message_delta",
+                json!({ "type": "message_delta", "delta": { "stop_reason": "tool_use" }, "usage": { "output_tokens": 15 } }),
+            ),
+            ("message_stop", json!({ "type": "message_stop" })),
+        ];
+        let body: String = events
+            .iter()
+            .map(|(name, data)| format!("event: {name}\ndata: {data}\n\n"))
+            .collect();
+        let server = MockServer::start(MockResponse::sse(body)).await;
+
+        let events: Vec<_> = client(&server.url())
+            .stream(request())
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            [
+                StreamEvent::Token("Checking.".to_string()),
+                StreamEvent::ToolCall(ToolCall {
+                    id: "toolu_1".to_string(),
+                    name: "calculator".to_string(),
+                    arguments: json!({ "expression": "6*7" }),
+                }),
+                StreamEvent::Done {
+                    finish_reason: FinishReason::ToolCalls,
+                    usage: Some(Usage {
+                        input_tokens: 20,
+                        output_tokens: 15
+                    }),
+                },
+            ]
+        );
+        assert_eq!(server.request().body["stream"], true);
+    }
+
+    #[tokio::test]
+    async fn stream_errors_are_classified() {
+        let server = MockServer::start(MockResponse::sse(concat!(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\n",
+            "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n",
+        )))
+        .await;
+        let results: Vec<_> = client(&server.url())
+            .stream(request())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(
+            matches!(
+                &results[..],
+                [Err(ProviderError::Unavailable { message, .. })] if message == "Overloaded"
+            ),
+            "{results:?}"
+        );
+    }
+}
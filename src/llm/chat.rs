@@ -0,0 +1,167 @@
+use async_trait::async_trait;
+use futures_util::Stream;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::pin::Pin;
+
+use super::ProviderError;
+use crate::agent::ModelConfig;
+
+/// Who a message is from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    /// The result of a tool call, answering the call with the same `tool_call_id`.
+    Tool,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+        }
+    }
+}
+
+/// One message of a conversation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: Role,
+    #[serde(default)]
+    pub content: String,
+    /// Tools an assistant message asked to call.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// For `tool` messages, the call this is the result of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl Message {
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+}
+
+/// A tool call requested by the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    /// Arguments as a JSON value. Providers that send malformed JSON get it as a string.
+    pub arguments: Value,
+}
+
+/// A tool the model may call, described by a JSON Schema for its arguments.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default = "empty_object_schema")]
+    pub parameters: Value,
+}
+
+fn empty_object_schema() -> Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+/// Everything a provider needs for one completion.
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    /// Model name and generation parameters.
+    pub model: ModelConfig,
+    pub messages: Vec<Message>,
+    pub tools: Vec<ToolDefinition>,
+}
+
+/// Why the model stopped generating.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinishReason {
+    Stop,
+    /// `max_output_tokens` was reached.
+    Length,
+    ToolCalls,
+    ContentFilter,
+    /// A reason this version of Agnx does not know, as the provider sent it.
+    Other(String),
+}
+
+impl FinishReason {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::ToolCalls => "tool_calls",
+            FinishReason::ContentFilter => "content_filter",
+            FinishReason::Other(reason) => reason,
+        }
+    }
+}
+
+impl From<&str> for FinishReason {
+    fn from(reason: &str) -> Self {
+        match reason {
+            "stop" => FinishReason::Stop,
+            "length" => FinishReason::Length,
+            "tool_calls" => FinishReason::ToolCalls,
+            "content_filter" => FinishReason::ContentFilter,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
+}
+
+/// Tokens used by one completion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+/// A finished, non-streaming completion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    /// The assistant message, with any tool calls.
+    pub message: Message,
+    pub finish_reason: FinishReason,
+    /// Absent if the provider did not report it.
+    pub usage: Option<Usage>,
+}
+
+/// One piece of a streaming completion.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    /// Generated text to append to the response.
+    Token(String),
+    /// A complete tool call; emitted once all of its arguments have arrived.
+    ToolCall(ToolCall),
+    /// The last event of a successful stream.
+    Done {
+        finish_reason: FinishReason,
+        usage: Option<Usage>,
+    },
+}
+
+pub type ChatStream = Pin<Box<dyn Stream<Item = Result<StreamEvent, ProviderError>> + Send>>;
+
+/// A model that can be chatted with.
+#[async_trait]
+pub trait ChatModel: Send + Sync {
+    /// Generate a complete response.
+    async fn complete(&self, request: ChatRequest) -> Result<Completion, ProviderError>;
+
+    /// Generate a response as a stream of tokens. Errors before the first event (such as
+    /// a rejected API key) are returned directly rather than through the stream.
+    async fn stream(&self, request: ChatRequest) -> Result<ChatStream, ProviderError>;
+}
@@ -0,0 +1,182 @@
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+/// Why a provider call failed.
+#[derive(Debug)]
+pub enum ProviderError {
+    /// No API key is configured for the provider.
+    MissingApiKey {
+        provider: String,
+        /// Environment variable the key can be set in.
+        env: &'static str,
+    },
+    /// The provider's configuration cannot be used, e.g. a custom provider without a
+    /// `base_url`.
+    Misconfigured {
+        provider: String,
+        message: String,
+    },
+    /// The provider rejected the API key (`401`/`403`).
+    Unauthorized {
+        provider: String,
+        message: String,
+    },
+    /// Too many requests (`429`).
+    RateLimited {
+        provider: String,
+        /// Seconds to wait, from `Retry-After`.
+        retry_after: Option<u64>,
+        message: String,
+    },
+    /// The provider rejected the request itself, e.g. an unknown model or a conversation
+    /// that does not fit the context window.
+    InvalidRequest {
+        provider: String,
+        status: u16,
+        message: String,
+    },
+    /// The provider failed or is overloaded (`5xx`).
+    Unavailable {
+        provider: String,
+        status: u16,
+        message: String,
+    },
+    Timeout {
+        provider: String,
+    },
+    /// The provider could not be reached.
+    Connection {
+        provider: String,
+        message: String,
+    },
+    /// The provider answered with something that could not be understood.
+    InvalidResponse {
+        provider: String,
+        message: String,
+    },
+}
+
+impl ProviderError {
+    /// Classify an unsuccessful HTTP response. `body` is searched for the usual
+    /// `{"error": {"message": ...}}` shape, and used as-is otherwise.
+    pub(super) fn from_status(
+        provider: &str,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &str,
+    ) -> Self {
+        let provider = provider.to_string();
+        let message = error_message(body).unwrap_or_else(|| {
+            let body = body.trim();
+            if body.is_empty() {
+                status.to_string()
+            } else {
+                body.to_string()
+            }
+        });
+        match status.as_u16() {
+            401 | 403 => ProviderError::Unauthorized { provider, message },
+            429 => ProviderError::RateLimited {
+                provider,
+                retry_after: headers
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse().ok()),
+                message,
+            },
+            status @ 400..=499 => ProviderError::InvalidRequest {
+                provider,
+                status,
+                message,
+            },
+            status => ProviderError::Unavailable {
+                provider,
+                status,
+                message,
+            },
+        }
+    }
+
+    /// Classify a transport error.
+    pub(super) fn from_reqwest(provider: &str, error: reqwest::Error) -> Self {
+        let provider = provider.to_string();
+        if error.is_timeout() {
+            ProviderError::Timeout { provider }
+        } else if error.is_decode() {
+            ProviderError::InvalidResponse {
+                provider,
+                message: error.to_string(),
+            }
+        } else {
+            ProviderError::Connection {
+                provider,
+                message: error.to_string(),
+            }
+        }
+    }
+
+    pub(super) fn invalid_response(provider: &str, message: impl Into<String>) -> Self {
+        ProviderError::InvalidResponse {
+            provider: provider.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// `error.message` (or a string `error`) from a JSON error body.
+fn error_message(body: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    let error = value.get("error")?;
+    error
+        .get("message")
+        .and_then(|m| m.as_str())
+        .or_else(|| error.as_str())
+        .map(str::to_string)
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProviderError::MissingApiKey { provider, env } => write!(
+                f,
+                "no API key for {provider}; set providers.{provider}.api_key or {env}"
+            ),
+            ProviderError::Misconfigured { provider, message } => {
+                write!(f, "{provider} is misconfigured: {message}")
+            }
+            ProviderError::Unauthorized { provider, message } => {
+                write!(f, "{provider} rejected the API key: {message}")
+            }
+            ProviderError::RateLimited {
+                provider,
+                retry_after,
+                message,
+            } => {
+                write!(f, "{provider} rate limit exceeded: {message}")?;
+                if let Some(seconds) = retry_after {
+                    write!(f, " (retry after {seconds}s)")?;
+                }
+                Ok(())
+            }
+            ProviderError::InvalidRequest {
+                provider,
+                status,
+                message,
+            } => write!(f, "{provider} rejected the request ({status}): {message}"),
+            ProviderError::Unavailable {
+                provider,
+                status,
+                message,
+            } => write!(f, "{provider} is unavailable ({status}): {message}"),
+            ProviderError::Timeout { provider } => write!(f, "{provider} did not respond in time"),
+            ProviderError::Connection { provider, message } => {
+                write!(f, "failed to connect to {provider}: {message}")
+            }
+            ProviderError::InvalidResponse { provider, message } => {
+                write!(f, "unexpected response from {provider}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
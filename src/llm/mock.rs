@@ -0,0 +1,122 @@
+//! A local HTTP server standing in for a provider API in tests.
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::http::{HeaderMap, StatusCode, Uri, header};
+use axum::response::{IntoResponse, Response};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// What the server answers every request with.
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, &'static str)>,
+    body: String,
+    delay: Duration,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: body.to_string(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn sse(body: impl Into<String>) -> Self {
+        Self {
+            content_type: "text/event-stream",
+            body: body.into(),
+            ..Self::json(200, Value::Null)
+        }
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: &'static str) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Wait this long before answering.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// A request the server received.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub path: String,
+    pub headers: HeaderMap,
+    /// The body as JSON, or `null` if it was not JSON.
+    pub body: Value,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+}
+
+pub struct MockServer {
+    url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    /// Serve `response` to every request on a free local port.
+    pub async fn start(response: MockResponse) -> Self {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let app = Router::new().fallback(move |uri: Uri, headers: HeaderMap, body: Bytes| {
+            let response = response.clone();
+            let recorded = recorded.clone();
+            async move {
+                recorded.lock().unwrap().push(RecordedRequest {
+                    path: uri.path().to_string(),
+                    headers,
+                    body: serde_json::from_slice(&body).unwrap_or_default(),
+                });
+                tokio::time::sleep(response.delay).await;
+                into_response(response)
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        Self { url, requests }
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// The most recent request.
+    pub fn request(&self) -> RecordedRequest {
+        self.requests
+            .lock()
+            .unwrap()
+            .last()
+            .cloned()
+            .expect("no request was received")
+    }
+}
+
+fn into_response(mock: MockResponse) -> Response {
+    let mut response = (
+        StatusCode::from_u16(mock.status).unwrap(),
+        [(header::CONTENT_TYPE, mock.content_type)],
+        mock.body,
+    )
+        .into_response();
+    for (name, value) in mock.headers {
+        response.headers_mut().insert(name, value.parse().unwrap());
+    }
+    response
+}
@@ -0,0 +1,446 @@
+//! Calling models: the [`ChatModel`] interface and a client for each provider.
+//!
+//! [`Providers`] turns an agent's [`ModelConfig`] into a [`ChatModel`], using credentials
+//! from the `providers:` section of `agnx.yaml`. All clients share one HTTP connection
+//! pool.
+
+mod anthropic;
+mod chat;
+mod error;
+#[cfg(test)]
+pub mod mock;
+mod ollama;
+mod openai;
+mod sse;
+
+pub use chat::{
+    ChatModel, ChatRequest, ChatStream, Completion, FinishReason, Message, Role, StreamEvent,
+    ToolCall, ToolDefinition, Usage,
+};
+pub use error::ProviderError;
+pub use openai::MaxTokensField;
+
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::agent::{AgentScanWarning, AgentSpec, ModelConfig, Provider};
+use crate::config::{ProviderEndpoint, ProvidersConfig};
+use anthropic::Anthropic;
+use ollama::Ollama;
+use openai::{OpenAiCompatible, Preset};
+
+/// Creates [`ChatModel`]s for agents.
+#[derive(Clone)]
+pub struct Providers {
+    http: reqwest::Client,
+    config: Arc<ProvidersConfig>,
+}
+
+/// An agent whose Ollama model could not be checked.
+#[derive(Debug)]
+pub enum ModelCheckError {
+    /// No client could be built for the agent's model.
+    Client { agent: String, error: ProviderError },
+    /// The server did not list its models.
+    Server { base_url: String, error: ProviderError },
+}
+
+impl std::fmt::Display for ModelCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelCheckError::Client { agent, error } => {
+                write!(f, "{agent}: cannot check Ollama model: {error}")
+            }
+            ModelCheckError::Server { base_url, error } => {
+                write!(f, "{base_url}: failed to list Ollama models: {error}")
+            }
+        }
+    }
+}
+
+impl Providers {
+    pub fn new(config: ProvidersConfig) -> Result<Self, reqwest::Error> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(config.connect_timeout))
+            .pool_idle_timeout(Duration::from_secs(90))
+            .user_agent(concat!("agnx/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self {
+            http,
+            config: Arc::new(config),
+        })
+    }
+
+    /// The model `model` refers to, ready to be called.
+    pub fn chat_model(&self, model: &ModelConfig) -> Result<Box<dyn ChatModel>, ProviderError> {
+        match &model.provider {
+            Provider::Anthropic => self.anthropic(model),
+            Provider::Ollama => Ok(Box::new(self.ollama(model)?)),
+            Provider::OpenAI => self.openai_compatible(model, &self.config.openai, &openai::OPENAI),
+            Provider::OpenRouter => {
+                self.openai_compatible(model, &self.config.openrouter, &openai::OPENROUTER)
+            }
+            Provider::Other(name) => {
+                let endpoint = self.config.custom.get(name).cloned().unwrap_or_default();
+                self.openai_compatible(model, &endpoint, &openai::GENERIC)
+            }
+        }
+    }
+
+    /// Which of `agents`, with the directories they were loaded from, have an Ollama
+    /// server that does not have their model. Each server is asked once; servers that
+    /// cannot be reached are logged and skipped.
+    pub async fn missing_models<'a>(
+        &self,
+        candidates: impl IntoIterator<Item = (&'a Path, &'a AgentSpec)>,
+    ) -> Vec<AgentScanWarning> {
+        let (warnings, errors) = self.check_models(candidates).await;
+        for error in errors {
+            match error {
+                ModelCheckError::Client { agent, error } => {
+                    warn!(agent = %agent, error = %error, "Cannot check Ollama model")
+                }
+                ModelCheckError::Server { base_url, error } => {
+                    warn!(base_url = %base_url, error = %error, "Failed to list Ollama models")
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Like [`Providers::missing_models`], but returns the agents and servers that
+    /// could not be checked instead of logging them.
+    pub async fn check_models<'a>(
+        &self,
+        candidates: impl IntoIterator<Item = (&'a Path, &'a AgentSpec)>,
+    ) -> (Vec<AgentScanWarning>, Vec<ModelCheckError>) {
+        let mut servers: BTreeMap<String, Ollama> = BTreeMap::new();
+        let mut agents: Vec<(String, &Path, &AgentSpec)> = Vec::new();
+        let mut errors = Vec::new();
+        for (dir, agent) in candidates {
+            if agent.model.provider != Provider::Ollama {
+                continue;
+            }
+            match self.ollama(&agent.model) {
+                Ok(client) => {
+                    let base_url = client.base_url().to_string();
+                    agents.push((base_url.clone(), dir, agent));
+                    servers.entry(base_url).or_insert(client);
+                }
+                Err(error) => errors.push(ModelCheckError::Client {
+                    agent: agent.metadata.name.clone(),
+                    error,
+                }),
+            }
+        }
+
+        let mut warnings = Vec::new();
+        for (base_url, client) in servers {
+            let models = match client.models().await {
+                Ok(models) => models,
+                Err(error) => {
+                    errors.push(ModelCheckError::Server { base_url, error });
+                    continue;
+                }
+            };
+            warnings.extend(
+                agents
+                    .iter()
+                    .filter(|(url, _, agent)| {
+                        *url == base_url && !ollama::has_model(&models, &agent.model.name)
+                    })
+                    .map(|(_, dir, agent)| AgentScanWarning::MissingModel {
+                        agent: agent.metadata.name.clone(),
+                        path: dir.to_path_buf(),
+                        model: agent.model.name.clone(),
+                        base_url: base_url.clone(),
+                    }),
+            );
+        }
+        (warnings, errors)
+    }
+
+    /// Download an Ollama model onto the server `model` refers to, reporting progress
+    /// through `status`.
+    pub async fn pull_model(
+        &self,
+        model: &ModelConfig,
+        status: impl FnMut(&str),
+    ) -> Result<(), ProviderError> {
+        self.ollama(model)?.pull(&model.name, status).await
+    }
+
+    /// Ollama needs no API key, but one is sent if configured (e.g. for a proxy).
+    fn ollama(&self, model: &ModelConfig) -> Result<Ollama, ProviderError> {
+        let config = &self.config.ollama;
+        let provider = model.provider.to_string();
+        let mut headers = HeaderMap::new();
+        if let Some(key) = config.api_key.as_deref().filter(|key| {
+            !key.is_empty() && sends_credentials(model, config, Some(ollama::DEFAULT_BASE_URL))
+        }) {
+            headers.insert(
+                AUTHORIZATION,
+                header_value(&provider, &format!("Bearer {key}"))?,
+            );
+        }
+        let endpoint = self.endpoint(model, config, Some(ollama::DEFAULT_BASE_URL), headers)?;
+        Ok(Ollama::new(
+            self.http.clone(),
+            endpoint,
+            config.keep_alive.clone(),
+        ))
+    }
+
+    fn openai_compatible(
+        &self,
+        model: &ModelConfig,
+        config: &ProviderEndpoint,
+        preset: &Preset,
+    ) -> Result<Box<dyn ChatModel>, ProviderError> {
+        let provider = model.provider.to_string();
+        let mut headers = HeaderMap::new();
+        for (name, value) in preset.headers {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        let trusted = sends_credentials(model, config, preset.base_url);
+        let key = match preset.api_key_env {
+            Some(_) if !trusted => return Err(base_url_override(&provider)),
+            Some(env) => Some(required_api_key(&provider, config, env)?),
+            // Self-hosted servers often need no key.
+            None => config
+                .api_key
+                .clone()
+                .filter(|key| !key.is_empty() && trusted),
+        };
+        if let Some(key) = key {
+            headers.insert(
+                AUTHORIZATION,
+                header_value(&provider, &format!("Bearer {key}"))?,
+            );
+        }
+        let endpoint = self.endpoint(model, config, preset.base_url, headers)?;
+        let quirks = preset.quirks.with_overrides(config);
+        Ok(Box::new(OpenAiCompatible::new(
+            self.http.clone(),
+            endpoint,
+            quirks,
+        )))
+    }
+
+    fn anthropic(&self, model: &ModelConfig) -> Result<Box<dyn ChatModel>, ProviderError> {
+        let config = &self.config.anthropic;
+        let provider = model.provider.to_string();
+        if !sends_credentials(model, config, Some(anthropic::DEFAULT_BASE_URL)) {
+            return Err(base_url_override(&provider));
+        }
+        let key = required_api_key(&provider, config, anthropic::API_KEY_ENV)?;
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", header_value(&provider, &key)?);
+        headers.insert(
+            "anthropic-version",
+            HeaderValue::from_static(anthropic::API_VERSION),
+        );
+        let endpoint = self.endpoint(model, config, Some(anthropic::DEFAULT_BASE_URL), headers)?;
+        Ok(Box::new(Anthropic::new(self.http.clone(), endpoint)))
+    }
+
+    /// Where `model` is served: `spec.model.base_url`, then the configured `base_url`,
+    /// then the provider's default. `headers` go first, so configured headers can
+    /// override them; configured headers are left out where credentials are not sent.
+    fn endpoint(
+        &self,
+        model: &ModelConfig,
+        config: &ProviderEndpoint,
+        default_base_url: Option<&str>,
+        mut headers: HeaderMap,
+    ) -> Result<Endpoint, ProviderError> {
+        let provider = model.provider.to_string();
+        let Some(base_url) = model
+            .base_url
+            .as_deref()
+            .or(config.base_url.as_deref())
+            .or(default_base_url)
+        else {
+            let section = match model.provider {
+                Provider::Other(_) => format!("providers.custom.{provider}"),
+                _ => format!("providers.{provider}"),
+            };
+            return Err(ProviderError::Misconfigured {
+                message: format!("no base_url; set spec.model.base_url or {section}.base_url"),
+                provider,
+            });
+        };
+        let trusted = sends_credentials(model, config, default_base_url);
+        for (name, value) in config.headers.iter().filter(|_| trusted) {
+            let name =
+                HeaderName::try_from(name.as_str()).map_err(|_| ProviderError::Misconfigured {
+                    provider: provider.clone(),
+                    message: format!("invalid header name '{name}'"),
+                })?;
+            headers.insert(name, header_value(&provider, value)?);
+        }
+        Ok(Endpoint {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            provider,
+            headers,
+            timeouts: Timeouts {
+                completion: Duration::from_secs(self.config.timeout),
+                idle: Duration::from_secs(self.config.read_timeout),
+            },
+        })
+    }
+}
+
+/// Whether credentials from `config` may be sent where `model` is served: to the
+/// configured or default base URL, or to any with `allow_base_url_override`.
+fn sends_credentials(
+    model: &ModelConfig,
+    config: &ProviderEndpoint,
+    default_base_url: Option<&str>,
+) -> bool {
+    let trimmed = |url: &str| url.trim_end_matches('/').to_string();
+    match model.base_url.as_deref() {
+        None => true,
+        _ if config.allow_base_url_override => true,
+        Some(url) => config
+            .base_url
+            .as_deref()
+            .or(default_base_url)
+            .is_some_and(|base| trimmed(base) == trimmed(url)),
+    }
+}
+
+fn base_url_override(provider: &str) -> ProviderError {
+    ProviderError::Misconfigured {
+        provider: provider.to_string(),
+        message: format!(
+            "spec.model.base_url is not providers.{provider}.base_url; set \
+             providers.{provider}.allow_base_url_override to send the API key there"
+        ),
+    }
+}
+
+/// The configured API key, or the provider's environment variable `env`.
+fn required_api_key(
+    provider: &str,
+    endpoint: &ProviderEndpoint,
+    env: &'static str,
+) -> Result<String, ProviderError> {
+    endpoint
+        .api_key
+        .clone()
+        .or_else(|| std::env::var(env).ok())
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| ProviderError::MissingApiKey {
+            provider: provider.to_string(),
+            env,
+        })
+}
+
+fn header_value(provider: &str, value: &str) -> Result<HeaderValue, ProviderError> {
+    let mut value = HeaderValue::try_from(value).map_err(|_| ProviderError::Misconfigured {
+        provider: provider.to_string(),
+        message: "header values must be visible ASCII".to_string(),
+    })?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// Tool call arguments sent as a JSON-encoded string, which may be empty or (from
+/// weaker models) not JSON at all.
+fn arguments(raw: &str) -> Value {
+    if raw.trim().is_empty() {
+        return serde_json::json!({});
+    }
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+/// An `f32` as the JSON number it was written as (`0.2`, not `0.20000000298023224`).
+fn number(value: f32) -> Value {
+    value
+        .to_string()
+        .parse::<f64>()
+        .map_or(Value::Null, Value::from)
+}
+
+/// How long requests may take.
+#[derive(Debug, Clone, Copy)]
+struct Timeouts {
+    /// For a whole non-streaming completion.
+    completion: Duration,
+    /// Between two pieces of a streaming completion.
+    idle: Duration,
+}
+
+/// Where a client sends requests: the API root, and the headers (credentials included)
+/// that go with every request.
+#[derive(Debug, Clone)]
+struct Endpoint {
+    /// Provider name for errors.
+    provider: String,
+    base_url: String,
+    headers: HeaderMap,
+    timeouts: Timeouts,
+}
+
+impl Endpoint {
+    async fn get(
+        &self,
+        http: &reqwest::Client,
+        path: &str,
+        timeout: Duration,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let request = http
+            .get(format!("{}{path}", self.base_url))
+            .headers(self.headers.clone())
+            .timeout(timeout);
+        self.send(request).await
+    }
+
+    /// POST `body` to `path` below the API root. Only non-streaming requests get the
+    /// completion timeout; streams are bounded by the idle timeout while being read.
+    async fn post(
+        &self,
+        http: &reqwest::Client,
+        path: &str,
+        body: &Value,
+        stream: bool,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let mut request = http
+            .post(format!("{}{path}", self.base_url))
+            .headers(self.headers.clone())
+            .json(body);
+        if !stream {
+            request = request.timeout(self.timeouts.completion);
+        }
+        self.send(request).await
+    }
+
+    /// Send `request`, returning the response if it succeeded.
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ProviderError::from_reqwest(&self.provider, e))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let headers = response.headers().clone();
+        let text = response.text().await.unwrap_or_default();
+        Err(ProviderError::from_status(
+            &self.provider,
+            status,
+            &headers,
+            &text,
+        ))
+    }
+}
@@ -0,0 +1,650 @@
+//! Ollama's native `/api/chat`, plus the model management endpoints used to check for
+//! and pull models.
+//!
+//! Streams are newline-delimited JSON rather than SSE. Generation parameters go in an
+//! `options` object; `max_input_tokens` sizes the context window (`num_ctx`), which
+//! Ollama otherwise keeps at a small default regardless of the model.
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::sse::{self, EventHandler, LineDecoder, Queue, SseEvent};
+use super::{
+    ChatModel, ChatRequest, ChatStream, Completion, Endpoint, FinishReason, Message, ProviderError,
+    Role, StreamEvent, ToolCall, Usage, arguments, number,
+};
+
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// How long listing a server's models may take; the check runs at startup.
+const TAGS_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Ollama {
+    http: reqwest::Client,
+    endpoint: Endpoint,
+    /// How long the model stays loaded after a request, as Ollama parses it (`10m`).
+    keep_alive: Option<String>,
+}
+
+impl Ollama {
+    pub(super) fn new(
+        http: reqwest::Client,
+        endpoint: Endpoint,
+        keep_alive: Option<String>,
+    ) -> Self {
+        Self {
+            http,
+            endpoint,
+            keep_alive,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.endpoint.base_url
+    }
+
+    /// Names of the models the server has, as `name:tag`.
+    pub async fn models(&self) -> Result<Vec<String>, ProviderError> {
+        let provider = &self.endpoint.provider;
+        let response = self
+            .endpoint
+            .get(&self.http, "/api/tags", TAGS_TIMEOUT)
+            .await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| ProviderError::from_reqwest(provider, e))?;
+        let models = body["models"]
+            .as_array()
+            .ok_or_else(|| ProviderError::invalid_response(provider, "no models in response"))?;
+        Ok(models
+            .iter()
+            .filter_map(|model| model["name"].as_str())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Download `model`, reporting each distinct status line (`pulling manifest`,
+    /// `downloading sha256:...`, `success`) as it arrives.
+    pub async fn pull(
+        &self,
+        model: &str,
+        mut status: impl FnMut(&str),
+    ) -> Result<(), ProviderError> {
+        let provider = &self.endpoint.provider;
+        let body = json!({ "model": model, "stream": true });
+        let response = self
+            .endpoint
+            .post(&self.http, "/api/pull", &body, true)
+            .await?;
+        let mut events = sse::events::<LineDecoder>(response).boxed();
+        let mut last = String::new();
+        loop {
+            let event = match tokio::time::timeout(self.endpoint.timeouts.idle, events.next()).await
+            {
+                Ok(Some(event)) => event.map_err(|e| ProviderError::from_reqwest(provider, e))?,
+                Ok(None) => {
+                    return Err(ProviderError::invalid_response(
+                        provider,
+                        "pull ended before it succeeded",
+                    ));
+                }
+                Err(_) => {
+                    return Err(ProviderError::Timeout {
+                        provider: provider.clone(),
+                    });
+                }
+            };
+            let line: Value = serde_json::from_str(&event.data).map_err(|e| {
+                ProviderError::invalid_response(provider, format!("malformed progress: {e}"))
+            })?;
+            if let Some(error) = stream_error(provider, &line) {
+                return Err(error);
+            }
+            let current = line["status"].as_str().unwrap_or_default();
+            if current != last {
+                status(current);
+                last = current.to_string();
+            }
+            if current == "success" {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn send(&self, body: &Value, stream: bool) -> Result<reqwest::Response, ProviderError> {
+        self.endpoint
+            .post(&self.http, "/api/chat", body, stream)
+            .await
+    }
+}
+
+#[async_trait]
+impl ChatModel for Ollama {
+    async fn complete(&self, request: ChatRequest) -> Result<Completion, ProviderError> {
+        let provider = &self.endpoint.provider;
+        let body = request_body(&request, self.keep_alive.as_deref(), false);
+        let response = self.send(&body, false).await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| ProviderError::from_reqwest(provider, e))?;
+        parse_completion(provider, &body)
+    }
+
+    async fn stream(&self, request: ChatRequest) -> Result<ChatStream, ProviderError> {
+        let body = request_body(&request, self.keep_alive.as_deref(), true);
+        let response = self.send(&body, true).await?;
+        Ok(sse::ndjson_stream(
+            &self.endpoint.provider,
+            response,
+            self.endpoint.timeouts.idle,
+            LineHandler::new(&self.endpoint.provider),
+        ))
+    }
+}
+
+/// Whether the server's `models` include `model`. A name without a tag means `:latest`.
+pub fn has_model(models: &[String], model: &str) -> bool {
+    let tagged = |name: &str| {
+        if name.contains(':') {
+            name.to_string()
+        } else {
+            format!("{name}:latest")
+        }
+    };
+    let model = tagged(model);
+    models.iter().any(|name| tagged(name) == model)
+}
+
+/// The `/api/chat` request for `request`. `spec.model.extra` is applied last, so it can
+/// override anything, including `keep_alive`.
+fn request_body(request: &ChatRequest, keep_alive: Option<&str>, stream: bool) -> Value {
+    let model = &request.model;
+    let mut options = Map::new();
+    let mut set = |key: &str, value: Option<Value>| {
+        if let Some(value) = value {
+            options.insert(key.into(), value);
+        }
+    };
+    set("temperature", model.temperature.map(number));
+    set("num_predict", model.max_output_tokens.map(Value::from));
+    // The context window holds the prompt and the response.
+    set(
+        "num_ctx",
+        model
+            .max_input_tokens
+            .map(|input| json!(input.saturating_add(model.max_output_tokens.unwrap_or(0)))),
+    );
+    set("top_p", model.top_p.map(number));
+    set("top_k", model.top_k.map(Value::from));
+    set("seed", model.seed.map(Value::from));
+    set("presence_penalty", model.presence_penalty.map(number));
+    set("frequency_penalty", model.frequency_penalty.map(number));
+    set("stop", (!model.stop.is_empty()).then(|| json!(model.stop)));
+
+    let mut body = Map::new();
+    body.insert("model".into(), json!(model.name));
+    body.insert("messages".into(), wire_messages(&request.messages).into());
+    body.insert("stream".into(), json!(stream));
+    if !options.is_empty() {
+        body.insert("options".into(), Value::Object(options));
+    }
+    if let Some(keep_alive) = keep_alive {
+        body.insert("keep_alive".into(), json!(keep_alive));
+    }
+    if !request.tools.is_empty() {
+        let tools = request
+            .tools
+            .iter()
+            .map(|tool| {
+                let mut function = json!({ "name": tool.name, "parameters": tool.parameters });
+                if let Some(description) = &tool.description {
+                    function["description"] = json!(description);
+                }
+                json!({ "type": "function", "function": function })
+            })
+            .collect();
+        body.insert("tools".into(), tools);
+    }
+    for (key, value) in &model.extra {
+        body.insert(key.clone(), value.clone());
+    }
+    Value::Object(body)
+}
+
+/// Leading system messages are joined into one, since most model templates only use a
+/// single system message. Tool results name the tool they answer, which Ollama matches
+/// on instead of call IDs.
+fn wire_messages(messages: &[Message]) -> Vec<Value> {
+    let leading = messages
+        .iter()
+        .take_while(|message| message.role == Role::System)
+        .count();
+    let mut wire = Vec::with_capacity(messages.len());
+    if leading > 0 {
+        let system: Vec<&str> = messages[..leading]
+            .iter()
+            .map(|message| message.content.as_str())
+            .collect();
+        wire.push(json!({ "role": "system", "content": system.join("\n\n") }));
+    }
+    let mut tool_names: HashMap<&str, &str> = HashMap::new();
+    for message in &messages[leading..] {
+        let mut entry = json!({ "role": message.role.as_str(), "content": message.content });
+        if !message.tool_calls.is_empty() {
+            entry["tool_calls"] = message
+                .tool_calls
+                .iter()
+                .map(|call| {
+                    tool_names.insert(&call.id, &call.name);
+                    let arguments = match &call.arguments {
+                        Value::String(raw) => arguments(raw),
+                        other => other.clone(),
+                    };
+                    json!({ "function": { "name": call.name, "arguments": arguments } })
+                })
+                .collect();
+        }
+        if let Some(name) = message
+            .tool_call_id
+            .as_deref()
+            .and_then(|id| tool_names.get(id))
+        {
+            entry["tool_name"] = json!(name);
+        }
+        wire.push(entry);
+    }
+    wire
+}
+
+fn parse_completion(provider: &str, body: &Value) -> Result<Completion, ProviderError> {
+    if let Some(error) = stream_error(provider, body) {
+        return Err(error);
+    }
+    let message = body
+        .get("message")
+        .ok_or_else(|| ProviderError::invalid_response(provider, "no message in response"))?;
+    let tool_calls: Vec<ToolCall> = message["tool_calls"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, call)| tool_call(i, call))
+        .collect();
+    Ok(Completion {
+        finish_reason: finish_reason(body, !tool_calls.is_empty()),
+        message: Message {
+            tool_calls,
+            ..Message::new(
+                Role::Assistant,
+                message["content"].as_str().unwrap_or_default(),
+            )
+        },
+        usage: usage(body),
+    })
+}
+
+/// Ollama does not give tool calls IDs, so they are numbered in order.
+fn tool_call(index: usize, call: &Value) -> ToolCall {
+    let function = &call["function"];
+    ToolCall {
+        id: call["id"]
+            .as_str()
+            .map_or_else(|| format!("call_{index}"), str::to_string),
+        name: function["name"].as_str().unwrap_or_default().to_string(),
+        arguments: match &function["arguments"] {
+            Value::String(raw) => arguments(raw),
+            Value::Null => json!({}),
+            other => other.clone(),
+        },
+    }
+}
+
+/// `done_reason` is `stop` even when the model called tools.
+fn finish_reason(body: &Value, called_tools: bool) -> FinishReason {
+    if called_tools {
+        return FinishReason::ToolCalls;
+    }
+    match body["done_reason"].as_str() {
+        None | Some("stop") => FinishReason::Stop,
+        Some("length") => FinishReason::Length,
+        Some(other) => FinishReason::Other(other.to_string()),
+    }
+}
+
+fn usage(body: &Value) -> Option<Usage> {
+    Some(Usage {
+        input_tokens: u32::try_from(body["prompt_eval_count"].as_u64()?).ok()?,
+        output_tokens: u32::try_from(body["eval_count"].as_u64()?).ok()?,
+    })
+}
+
+/// Errors that arrive as `{"error": "..."}` in a stream line.
+fn stream_error(provider: &str, line: &Value) -> Option<ProviderError> {
+    line.get("error")?;
+    Some(ProviderError::from_status(
+        provider,
+        reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+        &reqwest::header::HeaderMap::new(),
+        &line.to_string(),
+    ))
+}
+
+/// Turns `/api/chat` stream lines into stream events. Tool calls arrive whole, so they
+/// are passed on as soon as they are seen.
+struct LineHandler {
+    provider: String,
+    tool_calls: usize,
+    done: bool,
+}
+
+impl LineHandler {
+    fn new(provider: &str) -> Self {
+        Self {
+            provider: provider.to_string(),
+            tool_calls: 0,
+            done: false,
+        }
+    }
+}
+
+impl EventHandler for LineHandler {
+    fn event(&mut self, event: SseEvent, out: &mut Queue) {
+        if self.done {
+            return;
+        }
+        let line: Value = match serde_json::from_str(&event.data) {
+            Ok(line) => line,
+            Err(e) => {
+                return out.push_back(Err(ProviderError::invalid_response(
+                    &self.provider,
+                    format!("malformed stream line: {e}"),
+                )));
+            }
+        };
+        if let Some(error) = stream_error(&self.provider, &line) {
+            return out.push_back(Err(error));
+        }
+        let message = &line["message"];
+        if let Some(text) = message["content"].as_str()
+            && !text.is_empty()
+        {
+            out.push_back(Ok(StreamEvent::Token(text.to_string())));
+        }
+        for call in message["tool_calls"].as_array().into_iter().flatten() {
+            out.push_back(Ok(StreamEvent::ToolCall(tool_call(self.tool_calls, call))));
+            self.tool_calls += 1;
+        }
+        if line["done"] == true {
+            out.push_back(Ok(StreamEvent::Done {
+                finish_reason: finish_reason(&line, self.tool_calls > 0),
+                usage: usage(&line),
+            }));
+            self.done = true;
+        }
+    }
+
+    fn end(&mut self, out: &mut Queue) {
+        if !self.done {
+            out.push_back(Err(ProviderError::invalid_response(
+                &self.provider,
+                "stream ended before the response was done",
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{AgentScanWarning, AgentStore, LoadOptions, ModelConfig, Provider};
+    use crate::config::ProvidersConfig;
+    use crate::llm::mock::{MockResponse, MockServer};
+    use crate::llm::{Providers, Timeouts, ToolDefinition};
+    use reqwest::header::HeaderMap;
+
+    fn client(base_url: &str) -> Ollama {
+        Ollama::new(
+            reqwest::Client::new(),
+            Endpoint {
+                provider: "ollama".to_string(),
+                base_url: base_url.to_string(),
+                headers: HeaderMap::new(),
+                timeouts: Timeouts {
+                    completion: Duration::from_millis(500),
+                    idle: Duration::from_millis(500),
+                },
+            },
+            Some("10m".to_string()),
+        )
+    }
+
+    fn request() -> ChatRequest {
+        let mut model = ModelConfig::new(Provider::Ollama, "llama3.2");
+        model.temperature = Some(0.2);
+        model.max_input_tokens = Some(8000);
+        model.max_output_tokens = Some(192);
+        ChatRequest {
+            model,
+            messages: vec![
+                Message::new(Role::System, "You are a calculator."),
+                Message::new(Role::System, "Be brief."),
+                Message::new(Role::User, "What is 6 * 7?"),
+                Message {
+                    tool_calls: vec![ToolCall {
+                        id: "call_0".to_string(),
+                        name: "calculator".to_string(),
+                        arguments: json!({ "expression": "6*7" }),
+                    }],
+                    ..Message::new(Role::Assistant, "")
+                },
+                Message {
+                    tool_call_id: Some("call_0".to_string()),
+                    ..Message::new(Role::Tool, "42")
+                },
+            ],
+            tools: vec![ToolDefinition {
+                name: "calculator".to_string(),
+                description: None,
+                parameters: json!({ "type": "object" }),
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn complete_sends_options_and_parses_tool_calls() {
+        let server = MockServer::start(MockResponse::json(
+            200,
+            json!({
+                "model": "llama3.2",
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{ "function": { "name": "calculator", "arguments": { "expression": "42/2" } } }]
+                },
+                "done": true,
+                "done_reason": "stop",
+                "prompt_eval_count": 40,
+                "eval_count": 12
+            }),
+        ))
+        .await;
+
+        let completion = client(&server.url()).complete(request()).await.unwrap();
+        assert_eq!(completion.finish_reason, FinishReason::ToolCalls);
+        assert_eq!(
+            completion.message.tool_calls,
+            [ToolCall {
+                id: "call_0".to_string(),
+                name: "calculator".to_string(),
+                arguments: json!({ "expression": "42/2" }),
+            }]
+        );
+        assert_eq!(
+            completion.usage,
+            Some(Usage {
+                input_tokens: 40,
+                output_tokens: 12
+            })
+        );
+
+        let sent = server.request();
+        assert_eq!(sent.path, "/api/chat");
+        assert_eq!(
+            sent.body,
+            json!({
+                "model": "llama3.2",
+                "messages": [
+                    { "role": "system", "content": "You are a calculator.\n\nBe brief." },
+                    { "role": "user", "content": "What is 6 * 7?" },
+                    { "role": "assistant", "content": "", "tool_calls": [
+                        { "function": { "name": "calculator", "arguments": { "expression": "6*7" } } }
+                    ] },
+                    { "role": "tool", "content": "42", "tool_name": "calculator" }
+                ],
+                "stream": false,
+                "keep_alive": "10m",
+                "options": { "temperature": 0.2, "num_predict": 192, "num_ctx": 8192 },
+                "tools": [{
+                    "type": "function",
+                    "function": { "name": "calculator", "parameters": { "type": "object" } }
+                }]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_reads_newline_delimited_json() {
+        let lines = [
+            json!({ "message": { "role": "assistant", "content": "It is" }, "done": false }),
+            json!({ "message": { "role": "assistant", "content": " 42." }, "done": false }),
+            json!({ "message": { "role": "assistant", "content": "" }, "done": true, "done_reason": "stop", "prompt_eval_count": 30, "eval_count": 4 }),
+        ];
+        // The last line has no trailing newline.
+        let body = lines.map(|line| line.to_string()).join("\n");
+        let server = MockServer::start(
+            MockResponse::sse(body).with_header("content-type", "application/x-ndjson"),
+        )
+        .await;
+
+        let events: Vec<_> = client(&server.url())
+            .stream(request())
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            [
+                StreamEvent::Token("It is".to_string()),
+                StreamEvent::Token(" 42.".to_string()),
+                StreamEvent::Done {
+                    finish_reason: FinishReason::Stop,
+                    usage: Some(Usage {
+                        input_tokens: 30,
+                        output_tokens: 4
+                    }),
+                },
+            ]
+        );
+        assert_eq!(server.request().body["stream"], true);
+    }
+
+    #[tokio::test]
+    async fn lists_and_pulls_models() {
+        let server = MockServer::start(MockResponse::json(
+            200,
+            json!({ "models": [{ "name": "llama3.2:latest" }, { "name": "qwen3:8b" }] }),
+        ))
+        .await;
+        let models = client(&server.url()).models().await.unwrap();
+        assert!(has_model(&models, "llama3.2"));
+        assert!(has_model(&models, "qwen3:8b"));
+        assert!(!has_model(&models, "qwen3"));
+        assert_eq!(server.request().path, "/api/tags");
+
+        let progress = [
+            json!({ "status": "pulling manifest" }),
+            json!({ "status": "pulling 6a0746a1ec1a", "total": 100, "completed": 10 }),
+            json!({ "status": "pulling 6a0746a1ec1a", "total": 100, "completed": 100 }),
+            json!({ "status": "success" }),
+        ];
+        let body: String = progress.iter().map(|line| format!("{line}\n")).collect();
+        let server = MockServer::start(MockResponse::sse(body)).await;
+        let mut statuses = Vec::new();
+        client(&server.url())
+            .pull("qwen3", |status| statuses.push(status.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            statuses,
+            ["pulling manifest", "pulling 6a0746a1ec1a", "success"]
+        );
+        assert_eq!(server.request().body["model"], "qwen3");
+
+        let server = MockServer::start(MockResponse::sse(
+            "{\"error\":\"pull model manifest: file does not exist\"}\n",
+        ))
+        .await;
+        let error = client(&server.url())
+            .pull("nope", |_| {})
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, ProviderError::Unavailable { message, .. } if message.contains("does not exist")),
+            "{error:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn agents_with_missing_models_are_reported() {
+        let server = MockServer::start(MockResponse::json(
+            200,
+            json!({ "models": [{ "name": "llama3.2:latest" }] }),
+        ))
+        .await;
+        let tmp = tempfile::TempDir::new().unwrap();
+        for (name, model) in [("present", "llama3.2"), ("absent", "qwen3:8b")] {
+            let dir = tmp.path().join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(
+                dir.join("agent.yaml"),
+                format!(
+                    "apiVersion: agnx/v1\nkind: Agent\nmetadata:\n  name: {name}\nspec:\n  model:\n    provider: ollama\n    name: {model}\n    base_url: {}\n",
+                    server.url()
+                ),
+            )
+            .unwrap();
+        }
+        let store = AgentStore::scan(tmp.path(), &LoadOptions::default()).store;
+        let providers = Providers::new(ProvidersConfig::default()).unwrap();
+
+        let warnings = providers.missing_models(store.iter_dirs()).await;
+        assert!(
+            matches!(
+                &warnings[..],
+                [AgentScanWarning::MissingModel { agent, model, .. }] if agent == "absent" && model == "qwen3:8b"
+            ),
+            "{warnings:?}"
+        );
+        assert_eq!(
+            warnings[0].diagnostics()[0].code,
+            crate::agent::DiagnosticCode::MissingModel
+        );
+
+        // Lazily indexed agents are checked without being loaded.
+        let options = LoadOptions {
+            lazy_cache_size: std::num::NonZeroUsize::new(1),
+            ..LoadOptions::default()
+        };
+        let store = AgentStore::scan(tmp.path(), &options).store;
+        let warnings = providers.missing_models(store.iter_dirs()).await;
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(store.cache_stats().unwrap().len, 0);
+    }
+}
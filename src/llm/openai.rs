@@ -0,0 +1,771 @@
+//! OpenAI's Chat Completions API, and the servers that imitate it: OpenRouter, vLLM,
+//! llama.cpp server, LiteLLM and the like.
+//!
+//! One client serves them all. What differs between servers is recorded in a [`Preset`]:
+//! default endpoint, API key variable, extra headers and [`Quirks`] in the request
+//! format.
+
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeMap;
+
+use super::sse::{self, EventHandler, Queue, SseEvent};
+use super::{
+    ChatModel, ChatRequest, ChatStream, Completion, Endpoint, FinishReason, Message, ProviderError,
+    Role, StreamEvent, ToolCall, Usage, arguments, number,
+};
+use crate::config::ProviderEndpoint;
+
+/// The request field that carries `spec.model.max_output_tokens`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MaxTokensField {
+    /// Understood by nearly every compatible server.
+    MaxTokens,
+    /// OpenAI's replacement for `max_tokens`, which its reasoning models require.
+    MaxCompletionTokens,
+}
+
+/// Where a server departs from the request format OpenAI documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    pub max_tokens_field: MaxTokensField,
+    /// `reasoning_effort` is sent as OpenRouter's `reasoning: {effort}` object instead
+    /// of a top-level field.
+    pub nested_reasoning: bool,
+    /// Streams ask for token usage with `stream_options.include_usage`. Some older
+    /// servers reject the field.
+    pub stream_usage: bool,
+}
+
+impl Quirks {
+    /// These quirks, with whatever `providers.<provider>` sets explicitly.
+    pub fn with_overrides(self, config: &ProviderEndpoint) -> Self {
+        Self {
+            max_tokens_field: config.max_tokens_field.unwrap_or(self.max_tokens_field),
+            stream_usage: config.stream_usage.unwrap_or(self.stream_usage),
+            ..self
+        }
+    }
+}
+
+/// Defaults for one provider served by [`OpenAiCompatible`].
+pub struct Preset {
+    /// API root used when neither the agent nor `agnx.yaml` sets one.
+    pub base_url: Option<&'static str>,
+    /// Environment variable holding the API key. Without one, a key is optional.
+    pub api_key_env: Option<&'static str>,
+    pub headers: &'static [(&'static str, &'static str)],
+    pub quirks: Quirks,
+}
+
+pub const OPENAI: Preset = Preset {
+    base_url: Some("https://api.openai.com/v1"),
+    api_key_env: Some("OPENAI_API_KEY"),
+    headers: &[],
+    quirks: Quirks {
+        max_tokens_field: MaxTokensField::MaxCompletionTokens,
+        nested_reasoning: false,
+        stream_usage: true,
+    },
+};
+
+pub const OPENROUTER: Preset = Preset {
+    base_url: Some("https://openrouter.ai/api/v1"),
+    api_key_env: Some("OPENROUTER_API_KEY"),
+    // Attribution shown in OpenRouter's dashboards.
+    headers: &[("x-title", "Agnx")],
+    quirks: Quirks {
+        max_tokens_field: MaxTokensField::MaxTokens,
+        nested_reasoning: true,
+        stream_usage: true,
+    },
+};
+
+/// Custom providers: any server at `base_url` that speaks the API.
+pub const GENERIC: Preset = Preset {
+    base_url: None,
+    api_key_env: None,
+    headers: &[],
+    quirks: Quirks {
+        max_tokens_field: MaxTokensField::MaxTokens,
+        nested_reasoning: false,
+        stream_usage: true,
+    },
+};
+
+pub struct OpenAiCompatible {
+    http: reqwest::Client,
+    endpoint: Endpoint,
+    quirks: Quirks,
+}
+
+impl OpenAiCompatible {
+    pub(super) fn new(http: reqwest::Client, endpoint: Endpoint, quirks: Quirks) -> Self {
+        Self {
+            http,
+            endpoint,
+            quirks,
+        }
+    }
+
+    async fn send(&self, body: &Value, stream: bool) -> Result<reqwest::Response, ProviderError> {
+        self.endpoint
+            .post(&self.http, "/chat/completions", body, stream)
+            .await
+    }
+}
+
+#[async_trait]
+impl ChatModel for OpenAiCompatible {
+    async fn complete(&self, request: ChatRequest) -> Result<Completion, ProviderError> {
+        let provider = &self.endpoint.provider;
+        let body = request_body(&request, self.quirks, false);
+        let response = self.send(&body, false).await?;
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| ProviderError::from_reqwest(provider, e))?;
+        parse_completion(provider, &body)
+    }
+
+    async fn stream(&self, request: ChatRequest) -> Result<ChatStream, ProviderError> {
+        let body = request_body(&request, self.quirks, true);
+        let response = self.send(&body, true).await?;
+        Ok(sse::chat_stream(
+            &self.endpoint.provider,
+            response,
+            self.endpoint.timeouts.idle,
+            ChunkHandler::new(&self.endpoint.provider),
+        ))
+    }
+}
+
+/// The Chat Completions request for `request`. Parameters the provider does not accept
+/// are left out; `spec.model.extra` is applied last, so it can override anything.
+fn request_body(request: &ChatRequest, quirks: Quirks, stream: bool) -> Value {
+    let model = &request.model;
+    let mut body = Map::new();
+    body.insert("model".into(), json!(model.name));
+    body.insert("messages".into(), wire_messages(&request.messages).into());
+    let mut set = |key: &str, value: Option<Value>| {
+        if let Some(value) = value {
+            body.insert(key.into(), value);
+        }
+    };
+    let supported = |parameter: &str| model.provider.supports(parameter);
+    let max_tokens = match quirks.max_tokens_field {
+        MaxTokensField::MaxTokens => "max_tokens",
+        MaxTokensField::MaxCompletionTokens => "max_completion_tokens",
+    };
+    set("temperature", model.temperature.map(number));
+    set(max_tokens, model.max_output_tokens.map(Value::from));
+    set("top_p", model.top_p.map(number));
+    if supported("top_k") {
+        set("top_k", model.top_k.map(Value::from));
+    }
+    if supported("seed") {
+        set("seed", model.seed.map(Value::from));
+    }
+    if supported("presence_penalty") {
+        set("presence_penalty", model.presence_penalty.map(number));
+    }
+    if supported("frequency_penalty") {
+        set("frequency_penalty", model.frequency_penalty.map(number));
+    }
+    set("stop", (!model.stop.is_empty()).then(|| json!(model.stop)));
+    if supported("reasoning_effort")
+        && let Some(effort) = model.reasoning_effort
+    {
+        if quirks.nested_reasoning {
+            set("reasoning", Some(json!({ "effort": effort.as_str() })));
+        } else {
+            set("reasoning_effort", Some(json!(effort.as_str())));
+        }
+    }
+    set(
+        "tools",
+        (!request.tools.is_empty()).then(|| {
+            request
+                .tools
+                .iter()
+                .map(|tool| {
+                    let mut function = json!({ "name": tool.name, "parameters": tool.parameters });
+                    if let Some(description) = &tool.description {
+                        function["description"] = json!(description);
+                    }
+                    json!({ "type": "function", "function": function })
+                })
+                .collect()
+        }),
+    );
+    if stream {
+        set("stream", Some(json!(true)));
+        if quirks.stream_usage {
+            set("stream_options", Some(json!({ "include_usage": true })));
+        }
+    }
+    for (key, value) in &model.extra {
+        body.insert(key.clone(), value.clone());
+    }
+    Value::Object(body)
+}
+
+/// Leading system messages (the agent's system prompt and instructions) are joined into
+/// one, since many servers' chat templates accept only a single system message.
+fn wire_messages(messages: &[Message]) -> Vec<Value> {
+    let leading = messages
+        .iter()
+        .take_while(|message| message.role == Role::System)
+        .count();
+    let mut wire = Vec::with_capacity(messages.len());
+    if leading > 0 {
+        let system: Vec<&str> = messages[..leading]
+            .iter()
+            .map(|message| message.content.as_str())
+            .collect();
+        wire.push(json!({ "role": "system", "content": system.join("\n\n") }));
+    }
+    wire.extend(messages[leading..].iter().map(wire_message));
+    wire
+}
+
+fn wire_message(message: &Message) -> Value {
+    let mut wire = json!({ "role": message.role.as_str(), "content": message.content });
+    if !message.tool_calls.is_empty() {
+        wire["tool_calls"] = message
+            .tool_calls
+            .iter()
+            .map(|call| {
+                let arguments = match &call.arguments {
+                    Value::String(raw) => raw.clone(),
+                    other => other.to_string(),
+                };
+                json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": { "name": call.name, "arguments": arguments },
+                })
+            })
+            .collect();
+    }
+    if let Some(id) = &message.tool_call_id {
+        wire["tool_call_id"] = json!(id);
+    }
+    wire
+}
+
+fn parse_completion(provider: &str, body: &Value) -> Result<Completion, ProviderError> {
+    if let Some(error) = in_band_error(provider, body) {
+        return Err(error);
+    }
+    let choice = body["choices"]
+        .get(0)
+        .ok_or_else(|| ProviderError::invalid_response(provider, "no choices in response"))?;
+    let message = &choice["message"];
+    let tool_calls = message["tool_calls"]
+        .as_array()
+        .map(|calls| {
+            calls
+                .iter()
+                .map(|call| ToolCall {
+                    id: call["id"].as_str().unwrap_or_default().to_string(),
+                    name: call["function"]["name"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    arguments: arguments(call["function"]["arguments"].as_str().unwrap_or("")),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Completion {
+        message: Message {
+            tool_calls,
+            ..Message::new(
+                Role::Assistant,
+                message["content"].as_str().unwrap_or_default(),
+            )
+        },
+        finish_reason: choice["finish_reason"]
+            .as_str()
+            .map_or(FinishReason::Stop, FinishReason::from),
+        usage: usage(&body["usage"]),
+    })
+}
+
+/// OpenRouter and LiteLLM report some upstream failures as
+/// `{"error": {"code": 502, ...}}` inside a `200` response or stream.
+fn in_band_error(provider: &str, body: &Value) -> Option<ProviderError> {
+    let error = body.get("error")?;
+    let status = error["code"]
+        .as_u64()
+        .and_then(|code| u16::try_from(code).ok())
+        .and_then(|code| reqwest::StatusCode::from_u16(code).ok())
+        .unwrap_or(reqwest::StatusCode::BAD_GATEWAY);
+    Some(ProviderError::from_status(
+        provider,
+        status,
+        &reqwest::header::HeaderMap::new(),
+        &body.to_string(),
+    ))
+}
+
+fn usage(usage: &Value) -> Option<Usage> {
+    Some(Usage {
+        input_tokens: u32::try_from(usage["prompt_tokens"].as_u64()?).ok()?,
+        output_tokens: u32::try_from(usage["completion_tokens"].as_u64()?).ok()?,
+    })
+}
+
+/// Assembles streamed `chat.completion.chunk`s. Tool calls arrive in fragments keyed by
+/// index and are emitted whole at the end; usage arrives in a final chunk of its own.
+struct ChunkHandler {
+    provider: String,
+    tool_calls: BTreeMap<u64, (String, String, String)>,
+    finish_reason: Option<FinishReason>,
+    usage: Option<Usage>,
+    done: bool,
+}
+
+impl ChunkHandler {
+    fn new(provider: &str) -> Self {
+        Self {
+            provider: provider.to_string(),
+            tool_calls: BTreeMap::new(),
+            finish_reason: None,
+            usage: None,
+            done: false,
+        }
+    }
+
+    fn finish(&mut self, out: &mut Queue) {
+        for (id, name, raw) in std::mem::take(&mut self.tool_calls).into_values() {
+            out.push_back(Ok(StreamEvent::ToolCall(ToolCall {
+                id,
+                name,
+                arguments: arguments(&raw),
+            })));
+        }
+        out.push_back(Ok(StreamEvent::Done {
+            finish_reason: self.finish_reason.take().unwrap_or(FinishReason::Stop),
+            usage: self.usage,
+        }));
+        self.done = true;
+    }
+}
+
+impl EventHandler for ChunkHandler {
+    fn event(&mut self, event: SseEvent, out: &mut Queue) {
+        if self.done {
+            return;
+        }
+        if event.data == "[DONE]" {
+            return self.finish(out);
+        }
+        let chunk: Value = match serde_json::from_str(&event.data) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                return out.push_back(Err(ProviderError::invalid_response(
+                    &self.provider,
+                    format!("malformed stream chunk: {e}"),
+                )));
+            }
+        };
+        if let Some(error) = in_band_error(&self.provider, &chunk) {
+            return out.push_back(Err(error));
+        }
+        if let Some(usage) = usage(&chunk["usage"]) {
+            self.usage = Some(usage);
+        }
+        let Some(choice) = chunk["choices"].get(0) else {
+            return;
+        };
+        let delta = &choice["delta"];
+        if let Some(text) = delta["content"].as_str()
+            && !text.is_empty()
+        {
+            out.push_back(Ok(StreamEvent::Token(text.to_string())));
+        }
+        for call in delta["tool_calls"].as_array().into_iter().flatten() {
+            let (id, name, raw) = self
+                .tool_calls
+                .entry(call["index"].as_u64().unwrap_or(0))
+                .or_default();
+            if let Some(value) = call["id"].as_str() {
+                *id = value.to_string();
+            }
+            if let Some(value) = call["function"]["name"].as_str() {
+                name.push_str(value);
+            }
+            if let Some(value) = call["function"]["arguments"].as_str() {
+                raw.push_str(value);
+            }
+        }
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.finish_reason = Some(reason.into());
+        }
+    }
+
+    fn end(&mut self, out: &mut Queue) {
+        if self.done {
+            return;
+        }
+        if self.finish_reason.is_some() {
+            self.finish(out);
+        } else {
+            out.push_back(Err(ProviderError::invalid_response(
+                &self.provider,
+                "stream ended before the completion finished",
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{ModelConfig, Provider};
+    use crate::config::ProvidersConfig;
+    use crate::llm::mock::{MockResponse, MockServer};
+    use crate::llm::{Providers, Timeouts, ToolDefinition};
+    use futures_util::StreamExt;
+    use reqwest::header::HeaderMap;
+    use std::time::Duration;
+
+    fn client(base_url: &str) -> OpenAiCompatible {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer sk-test".parse().unwrap());
+        OpenAiCompatible::new(
+            reqwest::Client::new(),
+            Endpoint {
+                provider: "openrouter".to_string(),
+                base_url: base_url.to_string(),
+                headers,
+                timeouts: Timeouts {
+                    completion: Duration::from_millis(500),
+                    idle: Duration::from_millis(500),
+                },
+            },
+            OPENROUTER.quirks,
+        )
+    }
+
+    fn request() -> ChatRequest {
+        let mut model = ModelConfig::new(Provider::OpenRouter, "anthropic/claude-sonnet-4");
+        model.temperature = Some(0.2);
+        model.max_output_tokens = Some(256);
+        model.stop = vec!["END".to_string()];
+        model
+            .extra
+            .insert("transforms".into(), json!(["middle-out"]));
+        ChatRequest {
+            model,
+            messages: vec![
+                Message::new(Role::System, "Be brief."),
+                Message::new(Role::User, "What is 6 * 7?"),
+            ],
+            tools: vec![ToolDefinition {
+                name: "calculator".to_string(),
+                description: Some("Evaluate arithmetic".to_string()),
+                parameters: json!({ "type": "object" }),
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn complete_sends_parameters_and_parses_tool_calls() {
+        let server = MockServer::start(MockResponse::json(
+            200,
+            json!({
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "content": "",
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": { "name": "calculator", "arguments": "{\"expression\":\"6*7\"}" }
+                        }]
+                    },
+                    "finish_reason": "tool_calls"
+                }],
+                "usage": { "prompt_tokens": 20, "completion_tokens": 9 }
+            }),
+        ))
+        .await;
+
+        let completion = client(&server.url()).complete(request()).await.unwrap();
+        assert_eq!(completion.finish_reason, FinishReason::ToolCalls);
+        assert_eq!(
+            completion.message.tool_calls,
+            [ToolCall {
+                id: "call_1".to_string(),
+                name: "calculator".to_string(),
+                arguments: json!({ "expression": "6*7" }),
+            }]
+        );
+        assert_eq!(
+            completion.usage,
+            Some(Usage {
+                input_tokens: 20,
+                output_tokens: 9
+            })
+        );
+
+        let sent = server.request();
+        assert_eq!(sent.path, "/chat/completions");
+        assert_eq!(sent.header("authorization"), Some("Bearer sk-test"));
+        assert_eq!(
+            sent.body,
+            json!({
+                "model": "anthropic/claude-sonnet-4",
+                "messages": [
+                    { "role": "system", "content": "Be brief." },
+                    { "role": "user", "content": "What is 6 * 7?" }
+                ],
+                "temperature": 0.2,
+                "max_tokens": 256,
+                "stop": ["END"],
+                "tools": [{
+                    "type": "function",
+                    "function": {
+                        "name": "calculator",
+                        "description": "Evaluate arithmetic",
+                        "parameters": { "type": "object" }
+                    }
+                }],
+                "transforms": ["middle-out"]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_assembles_tokens_tool_calls_and_usage() {
+        let chunks = [
+            json!({ "choices": [{ "delta": { "role": "assistant", "content": "Let me " } }] }),
+            json!({ "choices": [{ "delta": { "content": "check." } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "id": "call_1", "function": { "name": "calculator", "arguments": "{\"expr" } }] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "function": { "arguments": "ession\":\"6*7\"}" } }] } }] }),
+            json!({ "choices": [{ "delta": {}, "finish_reason": "tool_calls" }] }),
+            json!({ "choices": [], "usage": { "prompt_tokens": 20, "completion_tokens": 12 } }),
+        ];
+        let mut body = String::from(": OPENROUTER PROCESSING\n\n");
+        for chunk in chunks {
+            body.push_str(&format!("data: {chunk}\n\n"));
+        }
+        body.push_str("data: [DONE]\n\n");
+        let server = MockServer::start(MockResponse::sse(body)).await;
+
+        let events: Vec<_> = client(&server.url())
+            .stream(request())
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            [
+                StreamEvent::Token("Let me ".to_string()),
+                StreamEvent::Token("check.".to_string()),
+                StreamEvent::ToolCall(ToolCall {
+                    id: "call_1".to_string(),
+                    name: "calculator".to_string(),
+                    arguments: json!({ "expression": "6*7" }),
+                }),
+                StreamEvent::Done {
+                    finish_reason: FinishReason::ToolCalls,
+                    usage: Some(Usage {
+                        input_tokens: 20,
+                        output_tokens: 12
+                    }),
+                },
+            ]
+        );
+        let sent = server.request();
+        assert_eq!(sent.body["stream"], true);
+        assert_eq!(sent.body["stream_options"]["include_usage"], true);
+    }
+
+    #[tokio::test]
+    async fn errors_are_classified() {
+        let server = MockServer::start(
+            MockResponse::json(429, json!({ "error": { "message": "slow down" } }))
+                .with_header("retry-after", "7"),
+        )
+        .await;
+        let error = client(&server.url()).complete(request()).await.unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                ProviderError::RateLimited { retry_after: Some(7), message, .. } if message == "slow down"
+            ),
+            "{error:?}"
+        );
+
+        let server = MockServer::start(MockResponse::json(
+            401,
+            json!({ "error": { "message": "bad key" } }),
+        ))
+        .await;
+        let error = client(&server.url()).stream(request()).await.err().unwrap();
+        assert!(
+            matches!(error, ProviderError::Unauthorized { .. }),
+            "{error:?}"
+        );
+
+        let server = MockServer::start(MockResponse::json(
+            200,
+            json!({ "error": { "code": 502, "message": "upstream down" } }),
+        ))
+        .await;
+        let error = client(&server.url()).complete(request()).await.unwrap_err();
+        assert!(
+            matches!(error, ProviderError::Unavailable { status: 502, .. }),
+            "{error:?}"
+        );
+
+        let server = MockServer::start(
+            MockResponse::json(200, json!({})).with_delay(Duration::from_secs(2)),
+        )
+        .await;
+        let error = client(&server.url()).complete(request()).await.unwrap_err();
+        assert!(matches!(error, ProviderError::Timeout { .. }), "{error:?}");
+    }
+
+    #[test]
+    fn openai_requests_follow_openai_conventions() {
+        let model: ModelConfig = serde_json::from_value(json!({
+            "provider": "openai",
+            "name": "o4-mini",
+            "max_output_tokens": 256,
+            "top_k": 40,
+            "seed": 7,
+            "reasoning_effort": "high"
+        }))
+        .unwrap();
+        let request = ChatRequest {
+            model,
+            messages: vec![Message::new(Role::User, "Hi")],
+            tools: Vec::new(),
+        };
+
+        let body = request_body(&request, OPENAI.quirks, false);
+        assert_eq!(
+            body,
+            json!({
+                "model": "o4-mini",
+                "messages": [{ "role": "user", "content": "Hi" }],
+                "max_completion_tokens": 256,
+                "seed": 7,
+                "reasoning_effort": "high"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn stored_credentials_only_go_to_the_configured_base_url() {
+        let server = MockServer::start(MockResponse::json(
+            200,
+            json!({ "choices": [{ "message": { "content": "Hi" }, "finish_reason": "stop" }] }),
+        ))
+        .await;
+        let config = |allow: bool| {
+            serde_saphyr::from_str::<ProvidersConfig>(&format!(
+                "openrouter:\n  api_key: or-key\n  allow_base_url_override: {allow}\n\
+                 custom:\n  vllm:\n    api_key: vllm-key\n    base_url: http://vllm.internal/v1\n    \
+                 headers:\n      X-Team: agents\n"
+            ))
+            .unwrap()
+        };
+        let request = |model: &ModelConfig| ChatRequest {
+            model: model.clone(),
+            messages: vec![Message::new(Role::User, "Hello")],
+            tools: Vec::new(),
+        };
+
+        let mut openrouter = ModelConfig::new(Provider::OpenRouter, "m");
+        openrouter.base_url = Some(format!("{}/v1", server.url()));
+        let providers = Providers::new(config(false)).unwrap();
+        let error = providers.chat_model(&openrouter).err().unwrap();
+        assert!(
+            matches!(error, ProviderError::Misconfigured { .. }),
+            "{error:?}"
+        );
+
+        // A custom provider is still called, without its key and headers.
+        let mut vllm = ModelConfig::new(Provider::Other("vllm".into()), "qwen3-8b");
+        vllm.base_url = openrouter.base_url.clone();
+        let chat = providers.chat_model(&vllm).unwrap();
+        chat.complete(request(&vllm)).await.unwrap();
+        let sent = server.request();
+        assert_eq!(sent.header("authorization"), None);
+        assert_eq!(sent.header("x-team"), None);
+
+        let providers = Providers::new(config(true)).unwrap();
+        let chat = providers.chat_model(&openrouter).unwrap();
+        chat.complete(request(&openrouter)).await.unwrap();
+        assert_eq!(
+            server.request().header("authorization"),
+            Some("Bearer or-key")
+        );
+    }
+
+    #[tokio::test]
+    async fn custom_providers_are_served_from_their_base_url() {
+        let server = MockServer::start(MockResponse::sse(concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: [DONE]\n\n",
+        )))
+        .await;
+        let config: ProvidersConfig = serde_saphyr::from_str(&format!(
+            "custom:\n  vllm:\n    base_url: {}/v1\n    headers:\n      X-Team: agents\n    stream_usage: false\n",
+            server.url()
+        ))
+        .unwrap();
+        let providers = Providers::new(config).unwrap();
+        let mut model = ModelConfig::new(Provider::Other("vllm".into()), "qwen3-8b");
+        model.max_output_tokens = Some(64);
+
+        let events: Vec<_> = providers
+            .chat_model(&model)
+            .unwrap()
+            .stream(ChatRequest {
+                model,
+                messages: vec![Message::new(Role::User, "Hello")],
+                tools: Vec::new(),
+            })
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            [
+                StreamEvent::Token("Hi".to_string()),
+                StreamEvent::Done {
+                    finish_reason: FinishReason::Stop,
+                    usage: None,
+                },
+            ]
+        );
+
+        let sent = server.request();
+        assert_eq!(sent.path, "/v1/chat/completions");
+        assert_eq!(sent.header("x-team"), Some("agents"));
+        assert_eq!(sent.header("authorization"), None);
+        assert_eq!(sent.body["max_tokens"], 64);
+        assert_eq!(sent.body.get("stream_options"), None);
+
+        let unknown = ModelConfig::new(Provider::Other("tgi".into()), "llama");
+        let error = providers.chat_model(&unknown).err().unwrap();
+        assert!(
+            matches!(error, ProviderError::Misconfigured { .. }),
+            "{error:?}"
+        );
+    }
+}
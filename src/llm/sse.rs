@@ -0,0 +1,225 @@
+//! Streamed response bodies: server-sent events, as most provider APIs send them, and
+//! newline-delimited JSON, as Ollama does.
+
+use futures_util::{Stream, StreamExt, stream};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::{ChatStream, ProviderError, StreamEvent};
+
+/// Stream items waiting to be handed out.
+pub(super) type Queue = VecDeque<Result<StreamEvent, ProviderError>>;
+
+/// Turns a provider's events into [`StreamEvent`]s.
+pub(super) trait EventHandler: Send + 'static {
+    fn event(&mut self, event: SseEvent, out: &mut Queue);
+    /// The body ended; queue whatever is still owed, or an error if the stream was cut
+    /// short.
+    fn end(&mut self, out: &mut Queue);
+}
+
+/// One event of a `text/event-stream` body, or one line of a newline-delimited one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct SseEvent {
+    /// The `event:` field, if any.
+    pub event: Option<String>,
+    /// `data:` lines, joined with newlines.
+    pub data: String,
+}
+
+/// Splits a byte stream into events. Chunks may end anywhere, even inside a UTF-8
+/// sequence.
+pub(super) trait Decoder: Default + Send + 'static {
+    /// Feed a chunk, returning the events it completes.
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent>;
+
+    /// The body ended; return the last event if it was not terminated.
+    fn finish(&mut self) -> Option<SseEvent> {
+        None
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl Decoder for SseDecoder {
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        event: self.event.take(),
+                        data: self.data.join("\n"),
+                    });
+                    self.data.clear();
+                }
+                self.event = None;
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                // Comments (`: keep-alive`), `id` and `retry` are not needed.
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// Newline-delimited JSON: every non-empty line is an event's data.
+#[derive(Debug, Default)]
+pub(super) struct LineDecoder {
+    buffer: Vec<u8>,
+}
+
+impl LineDecoder {
+    fn event(line: &[u8]) -> Option<SseEvent> {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+        (!line.is_empty()).then(|| SseEvent {
+            event: None,
+            data: line.to_string(),
+        })
+    }
+}
+
+impl Decoder for LineDecoder {
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            events.extend(Self::event(&line));
+        }
+        events
+    }
+
+    fn finish(&mut self) -> Option<SseEvent> {
+        Self::event(&std::mem::take(&mut self.buffer))
+    }
+}
+
+/// A [`ChatStream`] over an SSE response. The stream ends after the first error, or
+/// with a timeout error when nothing arrives for `idle_timeout`.
+pub(super) fn chat_stream(
+    provider: &str,
+    response: reqwest::Response,
+    idle_timeout: Duration,
+    handler: impl EventHandler,
+) -> ChatStream {
+    decoded_stream::<SseDecoder>(provider, response, idle_timeout, handler)
+}
+
+/// Like [`chat_stream`], over a newline-delimited JSON response.
+pub(super) fn ndjson_stream(
+    provider: &str,
+    response: reqwest::Response,
+    idle_timeout: Duration,
+    handler: impl EventHandler,
+) -> ChatStream {
+    decoded_stream::<LineDecoder>(provider, response, idle_timeout, handler)
+}
+
+fn decoded_stream<D: Decoder>(
+    provider: &str,
+    response: reqwest::Response,
+    idle_timeout: Duration,
+    handler: impl EventHandler,
+) -> ChatStream {
+    let provider = provider.to_string();
+    let state = (events::<D>(response).boxed(), handler, Queue::new(), false);
+    Box::pin(stream::unfold(
+        state,
+        move |(mut events, mut handler, mut out, mut ended)| {
+            let provider = provider.clone();
+            async move {
+                loop {
+                    if let Some(item) = out.pop_front() {
+                        if item.is_err() {
+                            out.clear();
+                            ended = true;
+                        }
+                        return Some((item, (events, handler, out, ended)));
+                    }
+                    if ended {
+                        return None;
+                    }
+                    match tokio::time::timeout(idle_timeout, events.next()).await {
+                        Ok(Some(Ok(event))) => handler.event(event, &mut out),
+                        Ok(Some(Err(e))) => {
+                            out.push_back(Err(ProviderError::from_reqwest(&provider, e)))
+                        }
+                        Ok(None) => {
+                            handler.end(&mut out);
+                            ended = true;
+                        }
+                        Err(_) => out.push_back(Err(ProviderError::Timeout {
+                            provider: provider.clone(),
+                        })),
+                    }
+                }
+            }
+        },
+    ))
+}
+
+/// The events of a response body.
+pub(super) fn events<D: Decoder>(
+    response: reqwest::Response,
+) -> impl Stream<Item = Result<SseEvent, reqwest::Error>> + Send {
+    let body = response.bytes_stream().boxed();
+    let state = (body, D::default(), VecDeque::new());
+    stream::unfold(state, |(mut body, mut decoder, mut pending)| async move {
+        loop {
+            if let Some(event) = pending.pop_front() {
+                return Some((Ok(event), (body, decoder, pending)));
+            }
+            match body.next().await {
+                Some(Ok(chunk)) => pending.extend(decoder.push(&chunk)),
+                Some(Err(e)) => return Some((Err(e), (body, decoder, pending))),
+                None => pending.extend(Some(decoder.finish()?)),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_events_split_across_chunks() {
+        let mut decoder = SseDecoder::default();
+        let body =
+            "event: message_start\r\ndata: {\"a\":\ndata: 1}\r\n\r\n: keep-alive\n\ndata: é\n\n";
+        let mut events = Vec::new();
+        for chunk in body.as_bytes().chunks(3) {
+            events.extend(decoder.push(chunk));
+        }
+        assert_eq!(
+            events,
+            [
+                SseEvent {
+                    event: Some("message_start".to_string()),
+                    data: "{\"a\":\n1}".to_string(),
+                },
+                SseEvent {
+                    event: None,
+                    data: "é".to_string(),
+                },
+            ]
+        );
+    }
+}
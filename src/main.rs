@@ -2,15 +2,22 @@ mod agent;
 mod build_info;
 mod config;
 mod handlers;
+mod interpolate;
+mod llm;
+mod migrate;
+mod models;
 mod response;
+mod schema;
 mod server;
+mod validate;
 
 use clap::{Parser, Subcommand};
 use config::Config;
 use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::signal;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 
 /// Agnx - A minimal and fast self-hosted runtime for durable and portable AI agents
@@ -40,6 +47,79 @@ enum Commands {
         /// Agents directory (overrides config file). If relative, it is resolved relative to the config file directory.
         #[arg(long)]
         agents_dir: Option<PathBuf>,
+
+        /// Watch the agents directory and reload agents when their files change
+        #[arg(long)]
+        watch: bool,
+
+        /// Reject agents with unknown fields (overrides config file)
+        #[arg(long)]
+        strict: bool,
+
+        /// Admin API token (overrides config file). The admin API is disabled without one.
+        #[arg(long, env = "AGNX_ADMIN_TOKEN", hide_env_values = true)]
+        admin_token: Option<String>,
+    },
+    /// Validate an agent directory or a directory of agents
+    ///
+    /// Exits with 0 when valid and 1 when any errors (or, with --deny-warnings, warnings)
+    /// are found.
+    Validate {
+        /// Agent directory (containing agent.yaml) or agents directory
+        path: PathBuf,
+
+        /// Path to configuration file (for model aliases)
+        #[arg(short, long, default_value = "agnx.yaml")]
+        config: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = validate::OutputFormat::Text)]
+        format: validate::OutputFormat,
+
+        /// Treat warnings as errors
+        #[arg(long)]
+        deny_warnings: bool,
+
+        /// Report unknown fields as errors (overrides config file)
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Rewrite agent.yaml files to the newest apiVersion
+    ///
+    /// Comments and formatting are kept; only the affected values change.
+    Migrate {
+        /// agent.yaml file, agent directory, or agents directory
+        path: PathBuf,
+
+        /// Report files that need migrating without writing them; exits with 1 if any do
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print the JSON Schema for agent.yaml or agnx.yaml
+    Schema {
+        /// Which file to describe
+        #[arg(value_enum, default_value_t = schema::SchemaKind::Agent)]
+        kind: schema::SchemaKind,
+    },
+    /// Manage local models
+    Models {
+        #[command(subcommand)]
+        command: ModelsCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ModelsCommands {
+    /// Download Ollama models that agents use and their server does not have
+    ///
+    /// Exits with 1 if any download fails.
+    Pull {
+        /// Models to pull from the configured Ollama server instead
+        models: Vec<String>,
+
+        /// Path to configuration file
+        #[arg(short, long, default_value = "agnx.yaml")]
+        config: String,
     },
 }
 
@@ -48,7 +128,7 @@ async fn main() -> std::process::ExitCode {
     init_tracing();
 
     match run().await {
-        Ok(()) => std::process::ExitCode::SUCCESS,
+        Ok(code) => code,
         Err(e) => {
             error!("{e}");
             std::process::ExitCode::FAILURE
@@ -56,7 +136,7 @@ async fn main() -> std::process::ExitCode {
     }
 }
 
-async fn run() -> Result<(), Box<dyn std::error::Error>> {
+async fn run() -> Result<std::process::ExitCode, Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
     match cli.command {
@@ -65,7 +145,49 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
             port,
             host,
             agents_dir,
-        } => run_server(config, port, host, agents_dir).await,
+            watch,
+            strict,
+            admin_token,
+        } => {
+            run_server(config, port, host, agents_dir, watch, strict, admin_token).await?;
+            Ok(std::process::ExitCode::SUCCESS)
+        }
+        Commands::Validate {
+            path,
+            config,
+            format,
+            deny_warnings,
+            strict,
+        } => {
+            let mut options = Config::load(&config)?.agent_load_options();
+            options.strict |= strict;
+            Ok(if validate::run(&path, &options, format, deny_warnings) {
+                std::process::ExitCode::SUCCESS
+            } else {
+                std::process::ExitCode::FAILURE
+            })
+        }
+        Commands::Migrate { path, check } => Ok(if migrate::run(&path, check) {
+            std::process::ExitCode::SUCCESS
+        } else {
+            std::process::ExitCode::FAILURE
+        }),
+        Commands::Schema { kind } => {
+            schema::run(kind)?;
+            Ok(std::process::ExitCode::SUCCESS)
+        }
+        Commands::Models {
+            command: ModelsCommands::Pull { models, config },
+        } => {
+            let path = Path::new(&config);
+            Ok(
+                if models::pull(path, Config::load(&config)?, models).await? {
+                    std::process::ExitCode::SUCCESS
+                } else {
+                    std::process::ExitCode::FAILURE
+                },
+            )
+        }
     }
 }
 
@@ -74,6 +196,9 @@ async fn run_server(
     port_override: Option<u16>,
     host_override: Option<IpAddr>,
     agents_dir_override: Option<PathBuf>,
+    watch: bool,
+    strict: bool,
+    admin_token_override: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut config = Config::load(&config_path)?;
 
@@ -87,14 +212,61 @@ async fn run_server(
     if let Some(dir) = agents_dir_override {
         config.agents_dir = dir;
     }
+    config.strict |= strict;
+    if admin_token_override.is_some() {
+        config.admin.token = admin_token_override;
+    }
+    let admin_token = config.admin.token.take().filter(|token| !token.is_empty());
+    if admin_token.is_none() {
+        info!("No admin token configured, admin API disabled");
+    }
 
     // Load agents from configured directory
     let agents_dir = agent::resolve_agents_dir(Path::new(&config_path), &config.agents_dir);
-    let scan = agent::AgentStore::scan(&agents_dir);
-    info!(agents_dir = %agents_dir.display(), agents = scan.store.len(), "Loaded agents");
+    let dynamic_agents_dir =
+        config::resolve_relative(Path::new(&config_path), &config.dynamic_agents_dir);
+    let scan = agent::AgentStore::scan_all(
+        &agents_dir,
+        Some(&dynamic_agents_dir),
+        &config.agent_load_options(),
+    );
+    if config.lazy_load {
+        info!(
+            agents_dir = %agents_dir.display(),
+            agents = scan.store.len(),
+            cache_size = config.cache_size,
+            "Indexed agents, loading them on first use"
+        );
+    } else {
+        info!(agents_dir = %agents_dir.display(), agents = scan.store.len(), "Loaded agents");
+    }
     agent::log_scan_warnings(&scan.warnings);
 
-    let app = server::build_app(scan.store, config.server.request_timeout);
+    let agents = agent::AgentRegistry::new(scan.store);
+    let providers = llm::Providers::new(config.providers)?;
+    if watch {
+        match agent::watch_agents_dir(agents.clone(), providers.clone(), &agents_dir) {
+            Ok(_) => info!(agents_dir = %agents_dir.display(), "Watching agents for changes"),
+            Err(e) => {
+                warn!(agents_dir = %agents_dir.display(), error = %e, "Failed to watch agents directory")
+            }
+        }
+    }
+
+    // One round trip per Ollama server, so it does not hold up startup.
+    let (checker, store) = (providers.clone(), agents.snapshot());
+    tokio::spawn(async move {
+        agent::log_scan_warnings(&checker.missing_models(store.iter_dirs()).await);
+    });
+
+    let data_dir = config::resolve_relative(Path::new(&config_path), &config.data_dir);
+    let state = server::AppState {
+        agents,
+        data_dir: Arc::new(data_dir),
+        providers,
+        admin_token: admin_token.map(Arc::from),
+    };
+    let app = server::build_app(state, config.server.request_timeout);
 
     let ip: IpAddr = config.server.host.parse()?;
     let addr = SocketAddr::new(ip, config.server.port);
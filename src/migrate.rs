@@ -0,0 +1,205 @@
+//! `agnx migrate`: rewrite agent definitions to the newest apiVersion.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::agent::{DEFAULT_MAX_DEPTH, MigrateError, migrate};
+
+/// Outcome of migrating the files under one path.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MigrateSummary {
+    /// Files that were rewritten (or, with `check`, would be).
+    pub migrated: usize,
+    pub up_to_date: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// Migrate a single `agent.yaml`, an agent directory, or every agent in an agents
+/// directory. With `check`, nothing is written.
+pub fn migrate_path(path: &Path, check: bool) -> MigrateSummary {
+    let mut summary = MigrateSummary::default();
+    let manifests = match manifests(path) {
+        Ok(manifests) => manifests,
+        Err(e) => {
+            eprintln!("{}: {e}", path.display());
+            summary.failed += 1;
+            return summary;
+        }
+    };
+
+    for manifest in manifests {
+        let text = match fs::read_to_string(&manifest) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("{}: failed to read: {e}", manifest.display());
+                summary.failed += 1;
+                continue;
+            }
+        };
+        let migration = match migrate(&text) {
+            Ok(migration) => migration,
+            Err(e @ MigrateError::MissingApiVersion) => {
+                println!("{}: skipped, {e}", manifest.display());
+                summary.skipped += 1;
+                continue;
+            }
+            Err(e) => {
+                eprintln!("{}: {e}", manifest.display());
+                summary.failed += 1;
+                continue;
+            }
+        };
+
+        let version = if migration.inherited {
+            "inherits apiVersion"
+        } else {
+            migration.to
+        };
+        if migration.is_noop() {
+            println!("{}: up to date ({version})", manifest.display());
+            summary.up_to_date += 1;
+            continue;
+        }
+        if !check && let Err(e) = fs::write(&manifest, &migration.text) {
+            eprintln!("{}: failed to write: {e}", manifest.display());
+            summary.failed += 1;
+            continue;
+        }
+        let verb = if check { "would migrate" } else { "migrated" };
+        if migration.inherited {
+            println!("{}: {verb} ({version})", manifest.display());
+        } else {
+            println!(
+                "{}: {verb} {} -> {}",
+                manifest.display(),
+                migration.from,
+                migration.to
+            );
+        }
+        for change in &migration.changes {
+            println!("  {change}");
+        }
+        summary.migrated += 1;
+    }
+    summary
+}
+
+/// Run `agnx migrate` and return whether it succeeded. With `check`, any file that
+/// still needs migrating counts as a failure.
+pub fn run(path: &Path, check: bool) -> bool {
+    let summary = migrate_path(path, check);
+    println!(
+        "{} migrated, {} up to date, {} skipped, {} failed",
+        summary.migrated, summary.up_to_date, summary.skipped, summary.failed
+    );
+    summary.failed == 0 && !(check && summary.migrated > 0)
+}
+
+/// The `agent.yaml` files under `path`, following the same layout as `agnx validate`.
+fn manifests(path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if path.join("agent.yaml").is_file() {
+        return Ok(vec![path.join("agent.yaml")]);
+    }
+
+    let mut manifests = Vec::new();
+    collect_manifests(path, DEFAULT_MAX_DEPTH, &mut manifests)?;
+    manifests.sort();
+    Ok(manifests)
+}
+
+fn collect_manifests(dir: &Path, levels: usize, found: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if levels == 0 {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if path.join("agent.yaml").is_file() {
+            found.push(path.join("agent.yaml"));
+        } else {
+            collect_manifests(&path, levels - 1, found)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn migrates_agents_directory_and_check_leaves_files_alone() {
+        let tmp = TempDir::new().unwrap();
+        let write = |name: &str, text: &str| {
+            let dir = tmp.path().join(name);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("agent.yaml"), text).unwrap();
+        };
+        let old = "apiVersion: agnx/v1alpha1\nkind: Agent\nspec:\n  system_prompt: ./S.md\n";
+        write("old", old);
+        write("child", "extends: ../old\nmetadata:\n  name: child\n");
+        write("team/new", "apiVersion: agnx/v1\nkind: Agent\n");
+        write("loose", "kind: Agent\n");
+
+        let summary = migrate_path(tmp.path(), true);
+        assert_eq!(
+            summary,
+            MigrateSummary {
+                migrated: 1,
+                up_to_date: 2,
+                skipped: 1,
+                failed: 0
+            }
+        );
+        let manifest = tmp.path().join("old").join("agent.yaml");
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), old);
+
+        assert_eq!(migrate_path(tmp.path(), false).migrated, 1);
+        assert_eq!(
+            fs::read_to_string(&manifest).unwrap(),
+            "apiVersion: agnx/v1\nkind: Agent\nspec:\n  system_prompt: { file: ./S.md }\n"
+        );
+        assert_eq!(migrate_path(tmp.path(), false).up_to_date, 3);
+    }
+
+    #[test]
+    fn children_inheriting_the_api_version_are_migrated_with_their_parent() {
+        let tmp = TempDir::new().unwrap();
+        let write = |name: &str, text: &str| {
+            let dir = tmp.path().join(name);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("agent.yaml"), text).unwrap();
+        };
+        write(
+            "base",
+            "apiVersion: agnx/v1alpha1\nkind: Agent\nmetadata:\n  name: base\nspec:\n  model:\n    provider: openai\n    name: gpt-4o\n  system_prompt: ./S.md\n",
+        );
+        write(
+            "child",
+            "extends: ../base\nmetadata:\n  name: child\nspec:\n  model:\n    max_tokens: 64\n  instructions: ./I.md\n",
+        );
+        fs::write(tmp.path().join("base").join("S.md"), "Base.").unwrap();
+        fs::write(tmp.path().join("child").join("I.md"), "Child.").unwrap();
+
+        let summary = migrate_path(tmp.path(), false);
+        assert_eq!((summary.migrated, summary.failed), (2, 0));
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("child").join("agent.yaml")).unwrap(),
+            "extends: ../base\nmetadata:\n  name: child\nspec:\n  model:\n    max_output_tokens: 64\n  instructions: { file: ./I.md }\n"
+        );
+        let (child, _) = crate::agent::AgentSpec::load_with_warnings(
+            &tmp.path().join("child"),
+            &crate::agent::LoadOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(child.model.max_output_tokens, Some(64));
+        assert_eq!(migrate_path(tmp.path(), false).up_to_date, 2);
+    }
+}
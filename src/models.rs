@@ -0,0 +1,98 @@
+//! `agnx models pull`: download the Ollama models that agents use.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::agent::{self, AgentScanWarning, AgentStore, ModelConfig, Provider};
+use crate::config::{self, Config};
+use crate::llm::Providers;
+
+/// Pull `models` from the default Ollama server, or, without any, every model that an
+/// agent uses and its Ollama server does not have. Returns whether all pulls succeeded
+/// and every server could be checked.
+pub async fn pull(
+    config_path: &Path,
+    config: Config,
+    models: Vec<String>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let agents_dir = agent::resolve_agents_dir(config_path, &config.agents_dir);
+    let dynamic_agents_dir = config::resolve_relative(config_path, &config.dynamic_agents_dir);
+    let options = config.agent_load_options();
+    let providers = Providers::new(config.providers)?;
+
+    let mut ok = true;
+    let wanted: BTreeSet<(Option<String>, String)> = if models.is_empty() {
+        let store = AgentStore::scan_all(&agents_dir, Some(&dynamic_agents_dir), &options).store;
+        let (missing, errors) = providers.check_models(store.iter_dirs()).await;
+        // A server that cannot be asked may be missing models too.
+        for error in &errors {
+            eprintln!("{error}");
+            ok = false;
+        }
+        missing
+            .into_iter()
+            .filter_map(|warning| match warning {
+                AgentScanWarning::MissingModel {
+                    model, base_url, ..
+                } => Some((Some(base_url), model)),
+                _ => None,
+            })
+            .collect()
+    } else {
+        models.into_iter().map(|model| (None, model)).collect()
+    };
+    if wanted.is_empty() {
+        if ok {
+            println!("All Ollama models used by agents are available");
+        }
+        return Ok(ok);
+    }
+
+    for (base_url, name) in wanted {
+        let mut model = ModelConfig::new(Provider::Ollama, &name);
+        model.base_url = base_url;
+        let result = providers
+            .pull_model(&model, |status| println!("{name}: {status}"))
+            .await;
+        if let Err(e) = result {
+            eprintln!("{name}: {e}");
+            ok = false;
+        }
+    }
+    Ok(ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn pull_fails_when_a_server_cannot_be_checked() {
+        // A port that nothing listens on.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().join("agents/local");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("agent.yaml"),
+            format!(
+                "apiVersion: agnx/v1\nkind: Agent\nmetadata:\n  name: local\nspec:\n  model:\n    provider: ollama\n    name: llama3.2\n    base_url: {base_url}\n"
+            ),
+        )
+        .unwrap();
+
+        let config = Config {
+            agents_dir: "agents".into(),
+            ..Config::default()
+        };
+        let ok = pull(&tmp.path().join("agnx.yaml"), config, Vec::new())
+            .await
+            .unwrap();
+        assert!(!ok);
+    }
+}
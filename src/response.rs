@@ -5,10 +5,19 @@ use axum::http::header;
 use axum::response::{IntoResponse, Response};
 use serde::Serialize;
 
+use crate::agent::Diagnostic;
+
 /// URN-style identifiers for RFC 7807 `type`.
 pub const TYPE_BAD_REQUEST: &str = "urn:agnx:problem:bad-request";
+pub const TYPE_CONFLICT: &str = "urn:agnx:problem:conflict";
+pub const TYPE_FORBIDDEN: &str = "urn:agnx:problem:forbidden";
+pub const TYPE_INVALID_AGENT: &str = "urn:agnx:problem:invalid-agent";
 pub const TYPE_INTERNAL_ERROR: &str = "urn:agnx:problem:internal-error";
 pub const TYPE_NOT_FOUND: &str = "urn:agnx:problem:not-found";
+pub const TYPE_PRECONDITION_FAILED: &str = "urn:agnx:problem:precondition-failed";
+pub const TYPE_PROVIDER_ERROR: &str = "urn:agnx:problem:provider-error";
+pub const TYPE_UNAUTHORIZED: &str = "urn:agnx:problem:unauthorized";
+pub const TYPE_UNSUPPORTED_MEDIA_TYPE: &str = "urn:agnx:problem:unsupported-media-type";
 
 /// RFC 7807 Problem Details response
 #[derive(Debug, Serialize)]
@@ -20,6 +29,9 @@ pub struct ProblemDetails {
     pub detail: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub instance: Option<String>,
+    /// Extension member: agent diagnostics behind the problem.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl ProblemDetails {
@@ -30,6 +42,7 @@ impl ProblemDetails {
             status: status.as_u16(),
             detail: None,
             instance: None,
+            diagnostics: Vec::new(),
         }
     }
 
@@ -47,6 +60,11 @@ impl ProblemDetails {
         self.instance = Some(instance.into());
         self
     }
+
+    pub fn with_diagnostics(mut self, diagnostics: Vec<Diagnostic>) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
 }
 
 impl IntoResponse for ProblemDetails {
@@ -74,6 +92,54 @@ pub fn bad_request(detail: impl Into<String>) -> ProblemDetails {
         .with_detail(detail)
 }
 
+pub fn conflict(detail: impl Into<String>) -> ProblemDetails {
+    ProblemDetails::new(StatusCode::CONFLICT, "Conflict")
+        .with_type(TYPE_CONFLICT)
+        .with_detail(detail)
+}
+
+pub fn unauthorized(detail: impl Into<String>) -> ProblemDetails {
+    ProblemDetails::new(StatusCode::UNAUTHORIZED, "Unauthorized")
+        .with_type(TYPE_UNAUTHORIZED)
+        .with_detail(detail)
+}
+
+pub fn forbidden(detail: impl Into<String>) -> ProblemDetails {
+    ProblemDetails::new(StatusCode::FORBIDDEN, "Forbidden")
+        .with_type(TYPE_FORBIDDEN)
+        .with_detail(detail)
+}
+
+/// An agent definition that failed validation, with its diagnostics.
+pub fn invalid_agent(detail: impl Into<String>, diagnostics: Vec<Diagnostic>) -> ProblemDetails {
+    ProblemDetails::new(StatusCode::UNPROCESSABLE_ENTITY, "Invalid Agent")
+        .with_type(TYPE_INVALID_AGENT)
+        .with_detail(detail)
+        .with_diagnostics(diagnostics)
+}
+
+pub fn precondition_failed(detail: impl Into<String>) -> ProblemDetails {
+    ProblemDetails::new(StatusCode::PRECONDITION_FAILED, "Precondition Failed")
+        .with_type(TYPE_PRECONDITION_FAILED)
+        .with_detail(detail)
+}
+
+/// A model provider call that failed; `status` tells whose fault it was.
+pub fn provider_error(status: StatusCode, detail: impl Into<String>) -> ProblemDetails {
+    ProblemDetails::new(
+        status,
+        status.canonical_reason().unwrap_or("Provider Error"),
+    )
+    .with_type(TYPE_PROVIDER_ERROR)
+    .with_detail(detail)
+}
+
+pub fn unsupported_media_type(detail: impl Into<String>) -> ProblemDetails {
+    ProblemDetails::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported Media Type")
+        .with_type(TYPE_UNSUPPORTED_MEDIA_TYPE)
+        .with_detail(detail)
+}
+
 pub fn internal_error(detail: impl Into<String>) -> ProblemDetails {
     ProblemDetails::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error")
         .with_type(TYPE_INTERNAL_ERROR)
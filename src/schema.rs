@@ -0,0 +1,57 @@
+//! `agnx schema`: print the JSON Schemas for `agent.yaml` and `agnx.yaml`.
+
+use serde_json::Value;
+
+use crate::agent::agent_schema;
+use crate::config::Config;
+
+/// Which file a schema describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaKind {
+    /// `agent.yaml`
+    Agent,
+    /// `agnx.yaml`
+    Config,
+}
+
+/// The JSON Schema for `kind`.
+pub fn generate(kind: SchemaKind) -> Value {
+    match kind {
+        SchemaKind::Agent => agent_schema(),
+        SchemaKind::Config => Config::schema(),
+    }
+}
+
+/// Run `agnx schema`, printing the schema as pretty JSON.
+pub fn run(kind: SchemaKind) -> Result<(), serde_json::Error> {
+    println!("{}", serde_json::to_string_pretty(&generate(kind))?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The published schemas are checked in so editors can reference them by URL. If
+    /// this fails, a struct changed: regenerate with `agnx schema <kind> > schemas/...`.
+    #[test]
+    fn published_schemas_are_up_to_date() {
+        for (kind, published) in [
+            (
+                SchemaKind::Agent,
+                include_str!("../schemas/agent.schema.json"),
+            ),
+            (
+                SchemaKind::Config,
+                include_str!("../schemas/config.schema.json"),
+            ),
+        ] {
+            let published: Value = serde_json::from_str(published).unwrap();
+            assert_eq!(
+                published,
+                generate(kind),
+                "schemas/{kind:?}.schema.json is out of date; regenerate it with `agnx schema`"
+            );
+        }
+    }
+}
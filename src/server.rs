@@ -1,31 +1,129 @@
 use axum::Router;
-use axum::http::StatusCode;
-use axum::routing::get;
+use axum::extract::{FromRef, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
+use subtle::ConstantTimeEq;
 use tower_http::timeout::TimeoutLayer;
 
-use crate::agent::AgentStore;
+use crate::agent::AgentRegistry;
 use crate::handlers;
+use crate::llm::Providers;
+use crate::response;
+
+/// Shared state for API handlers.
+#[derive(Clone)]
+pub struct AppState {
+    pub agents: AgentRegistry,
+    /// Resolved runtime data root, used to render `${agnx.data_dir}`.
+    pub data_dir: Arc<PathBuf>,
+    pub providers: Providers,
+    /// Bearer token for `/api/v1/admin/*`; without one the admin routes are not mounted.
+    pub admin_token: Option<Arc<str>>,
+}
+
+impl FromRef<AppState> for AgentRegistry {
+    fn from_ref(state: &AppState) -> Self {
+        state.agents.clone()
+    }
+}
+
+/// Build the router. Every route except chat is cut off after `request_timeout_secs`;
+/// chat is bounded by the provider timeouts instead, since completions routinely take
+/// longer and streams may run for minutes.
+pub fn build_app(state: AppState, request_timeout_secs: u64) -> Router {
+    let timeout = TimeoutLayer::with_status_code(
+        StatusCode::REQUEST_TIMEOUT,
+        Duration::from_secs(request_timeout_secs),
+    );
 
-pub fn build_app(agent_store: AgentStore, request_timeout_secs: u64) -> Router {
     let api_v1 = Router::new()
         .route("/agents", get(handlers::list_agents))
         .route("/agents/{name}", get(handlers::get_agent))
-        .with_state(agent_store);
+        .route(
+            "/agents/{namespace}/{name}",
+            get(handlers::get_namespaced_agent),
+        )
+        .route("/agents/{name}/spec", get(handlers::get_agent_spec))
+        .route(
+            "/agents/{namespace}/{name}/spec",
+            get(handlers::get_namespaced_agent_spec),
+        )
+        .route("/schemas/agent", get(handlers::agent_schema))
+        .route("/schemas/config", get(handlers::config_schema));
+    let api_v1 = match state.admin_token.clone() {
+        Some(token) => api_v1.merge(
+            Router::new()
+                .route("/admin/agents", post(handlers::deploy_agent))
+                .route("/admin/agents/{name}", delete(handlers::delete_agent))
+                .route("/admin/reload", post(handlers::reload_agents))
+                .route("/admin/cache", get(handlers::agent_cache))
+                .route_layer(middleware::from_fn_with_state(token, require_admin_token)),
+        ),
+        None => api_v1,
+    }
+    .layer(timeout)
+    // Added after the timeout layer, which only wraps the routes before it.
+    .route("/agents/{name}/chat", post(handlers::chat))
+    .route("/agents/{name}/chat/stream", post(handlers::chat_stream))
+    .route(
+        "/agents/{namespace}/{name}/chat",
+        post(handlers::chat_namespaced),
+    )
+    .route(
+        "/agents/{namespace}/{name}/chat/stream",
+        post(handlers::chat_stream_namespaced),
+    )
+    .with_state(state);
 
     Router::new()
         .route("/livez", get(handlers::livez))
         .route("/readyz", get(handlers::readyz))
         .route("/version", get(handlers::version))
-        .nest("/api/v1", api_v1)
         .route("/example-bad-request", get(handlers::example_bad_request))
         .route("/example-not-found", get(handlers::example_not_found))
         .route(
             "/example-internal-error",
             get(handlers::example_internal_error),
         )
-        .layer(TimeoutLayer::with_status_code(
-            StatusCode::REQUEST_TIMEOUT,
-            Duration::from_secs(request_timeout_secs),
-        ))
+        .layer(timeout)
+        .nest("/api/v1", api_v1)
+}
+
+/// Reject admin requests without `Authorization: Bearer <token>` (401) or with a
+/// different token (403).
+async fn require_admin_token(
+    State(token): State<Arc<str>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match presented {
+        None => (
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            response::unauthorized("The admin API requires a bearer token"),
+        )
+            .into_response(),
+        Some(presented) if !token_matches(presented, &token) => {
+            response::forbidden("Invalid admin token").into_response()
+        }
+        Some(_) => next.run(request).await,
+    }
+}
+
+/// Compare digests so neither the contents nor the length of the token leak through
+/// timing.
+fn token_matches(presented: &str, token: &str) -> bool {
+    Sha256::digest(presented.as_bytes())
+        .ct_eq(&Sha256::digest(token.as_bytes()))
+        .into()
 }
@@ -0,0 +1,212 @@
+//! `agnx validate`: check agent definitions without starting the server.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::agent::{AgentSpec, AgentStore, Diagnostic, DiagnosticCode, LoadOptions, Severity};
+
+/// Output format for validation results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Result of validating one agent or a whole agents directory.
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub path: PathBuf,
+    /// Number of agents that loaded successfully.
+    pub agents: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    fn new(path: &Path, agents: usize, mut diagnostics: Vec<Diagnostic>) -> Self {
+        // Stable output for CI logs: by file, then position.
+        diagnostics.sort_by(|a, b| {
+            (&a.path, a.span.map(|s| (s.line, s.column)))
+                .cmp(&(&b.path, b.span.map(|s| (s.line, s.column))))
+        });
+        let errors = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+
+        Self {
+            path: path.to_path_buf(),
+            agents,
+            errors,
+            warnings: diagnostics.len() - errors,
+            diagnostics,
+        }
+    }
+
+    /// Whether the report should be treated as a pass.
+    pub fn passes(&self, deny_warnings: bool) -> bool {
+        self.errors == 0 && !(deny_warnings && self.warnings > 0)
+    }
+}
+
+/// Validate a single agent directory (containing `agent.yaml`) or an agents directory.
+pub fn validate_path(path: &Path, options: &LoadOptions) -> ValidationReport {
+    let path = if path.file_name().is_some_and(|n| n == "agent.yaml") {
+        path.parent().unwrap_or(Path::new("."))
+    } else {
+        path
+    };
+
+    if path.join("agent.yaml").is_file() {
+        return match AgentSpec::load_with_warnings(path, options) {
+            Ok((_, warnings)) => ValidationReport::new(
+                path,
+                1,
+                warnings.iter().map(|w| w.to_diagnostic()).collect(),
+            ),
+            Err(e) => ValidationReport::new(path, 0, e.diagnostics()),
+        };
+    }
+
+    // Lazy loading would skip the referenced files, which validation has to check.
+    let options = LoadOptions {
+        lazy_cache_size: None,
+        ..options.clone()
+    };
+    let scan = AgentStore::scan(path, &options);
+    let mut diagnostics: Vec<Diagnostic> =
+        scan.warnings.iter().flat_map(|w| w.diagnostics()).collect();
+    if scan.store.len() == 0 && diagnostics.is_empty() {
+        diagnostics.push(
+            Diagnostic::warning(DiagnosticCode::NoAgents, path, "no agents found")
+                .with_hint("expected agent.yaml here or in subdirectories"),
+        );
+    }
+
+    ValidationReport::new(path, scan.store.len(), diagnostics)
+}
+
+/// Run `agnx validate` and return whether validation passed.
+pub fn run(path: &Path, options: &LoadOptions, format: OutputFormat, deny_warnings: bool) -> bool {
+    let report = validate_path(path, options);
+    let passed = report.passes(deny_warnings);
+
+    match format {
+        OutputFormat::Text => {
+            for d in &report.diagnostics {
+                eprintln!("{d}");
+            }
+            let status = if passed { "ok" } else { "failed" };
+            println!(
+                "{status}: {} agent(s) loaded, {} error(s), {} warning(s)",
+                report.agents, report.errors, report.warnings
+            );
+        }
+        OutputFormat::Json => {
+            #[derive(Serialize)]
+            struct JsonOutput<'a> {
+                valid: bool,
+                #[serde(flatten)]
+                report: &'a ValidationReport,
+            }
+
+            let output = JsonOutput {
+                valid: passed,
+                report: &report,
+            };
+            match serde_json::to_string_pretty(&output) {
+                Ok(json) => println!("{json}"),
+                Err(e) => eprintln!("failed to serialize validation report: {e}"),
+            }
+        }
+    }
+
+    passed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write_agent(dir: &Path, name: &str, extra: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join("agent.yaml"),
+            format!(
+                r#"apiVersion: agnx/v1alpha1
+kind: Agent
+metadata:
+  name: {name}
+spec:
+  model:
+    provider: openrouter
+    name: anthropic/claude-sonnet-4
+{extra}"#
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn validate_single_agent_dir() {
+        let tmp = TempDir::new().unwrap();
+        write_agent(tmp.path(), "solo", "");
+
+        let report = validate_path(tmp.path(), &LoadOptions::default());
+        assert_eq!(report.agents, 1);
+        assert!(report.diagnostics.is_empty());
+        assert!(report.passes(true));
+
+        let report = validate_path(&tmp.path().join("agent.yaml"), &LoadOptions::default());
+        assert_eq!(report.agents, 1);
+    }
+
+    #[test]
+    fn validate_reports_duplicate_names_across_agents() {
+        let tmp = TempDir::new().unwrap();
+        write_agent(&tmp.path().join("a"), "same", "");
+        write_agent(&tmp.path().join("b"), "same", "");
+
+        let report = validate_path(tmp.path(), &LoadOptions::default());
+        assert_eq!(report.agents, 1);
+        assert_eq!(report.errors, 1);
+        assert_eq!(report.diagnostics[0].code, DiagnosticCode::DuplicateName);
+        assert_eq!(
+            report.diagnostics[0].path,
+            tmp.path().join("b").join("agent.yaml")
+        );
+        assert!(!report.passes(false));
+    }
+
+    #[test]
+    fn deny_warnings_fails_on_warnings_only() {
+        let tmp = TempDir::new().unwrap();
+        write_agent(tmp.path(), "warned", "  system_prompt: ./MISSING.md\n");
+
+        let report = validate_path(tmp.path(), &LoadOptions::default());
+        assert_eq!(report.errors, 0);
+        assert_eq!(report.warnings, 1);
+        assert!(report.passes(false));
+        assert!(!report.passes(true));
+    }
+
+    #[test]
+    fn validate_missing_path_is_error() {
+        let tmp = TempDir::new().unwrap();
+        let report = validate_path(&tmp.path().join("nope"), &LoadOptions::default());
+        assert_eq!(report.errors, 1);
+        assert_eq!(report.diagnostics[0].code, DiagnosticCode::AgentsDirMissing);
+    }
+
+    #[test]
+    fn validate_empty_dir_warns() {
+        let tmp = TempDir::new().unwrap();
+        let report = validate_path(tmp.path(), &LoadOptions::default());
+        assert_eq!(report.warnings, 1);
+        assert_eq!(report.diagnostics[0].code, DiagnosticCode::NoAgents);
+    }
+}